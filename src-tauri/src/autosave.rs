@@ -0,0 +1,227 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+
+use crate::config::ConfigManager;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AutosaveMode {
+    Off,
+    Delay,
+    FocusLoss,
+    WindowClose,
+}
+
+impl AutosaveMode {
+    pub fn parse(mode: Option<&str>) -> Self {
+        match mode {
+            Some("delay") => AutosaveMode::Delay,
+            Some("focus_loss") => AutosaveMode::FocusLoss,
+            Some("window_close") => AutosaveMode::WindowClose,
+            _ => AutosaveMode::Off,
+        }
+    }
+}
+
+// Latest content of the dirty buffers the frontend has reported, keyed by
+// window label and buffer id. `generation` tells a delayed save whether the
+// buffer changed again while it was waiting.
+pub struct AutosaveState {
+    buffers: HashMap<(String, String), DirtyBuffer>,
+    generation: u64,
+}
+
+impl AutosaveState {
+    pub fn new() -> Self {
+        Self {
+            buffers: HashMap::new(),
+            generation: 0,
+        }
+    }
+}
+
+#[derive(Clone)]
+struct DirtyBuffer {
+    path: String,
+    content: String,
+    encoding: Option<String>,
+    saved_hash: Option<String>,
+    generation: u64,
+}
+
+#[derive(Deserialize)]
+pub struct AutosaveBuffer {
+    buffer_id: String,
+    path: String,
+    content: String,
+    encoding: Option<String>,
+    saved_hash: Option<String>,
+    read_only: Option<bool>,
+}
+
+#[derive(Serialize, Clone)]
+struct FileAutosaved {
+    buffer_id: String,
+    path: String,
+    hash: String,
+}
+
+#[derive(Serialize, Clone)]
+struct FileAutosaveFailed {
+    buffer_id: String,
+    path: String,
+    error: String,
+}
+
+fn save(app_handle: &tauri::AppHandle, key: &(String, String), buffer: DirtyBuffer) {
+    let (label, buffer_id) = key;
+    let result = crate::write_file_content(
+        app_handle,
+        &buffer.path,
+        &buffer.content,
+        buffer.encoding.as_deref(),
+        buffer.saved_hash.as_deref(),
+    );
+
+    match result {
+        Ok(hash) => {
+            // Edits that arrived while saving are now based on the new content.
+            {
+                let state = app_handle.state::<Mutex<AutosaveState>>();
+                let mut state = state.lock().unwrap();
+                if let Some(newer) = state.buffers.get_mut(key) {
+                    newer.saved_hash = Some(hash.clone());
+                }
+            }
+            let _ = app_handle.emit_to(label.as_str(), "file-autosaved", FileAutosaved {
+                buffer_id: buffer_id.clone(),
+                path: buffer.path,
+                hash,
+            });
+        }
+        Err(error) => {
+            let _ = app_handle.emit_to(label.as_str(), "file-autosave-failed", FileAutosaveFailed {
+                buffer_id: buffer_id.clone(),
+                path: buffer.path,
+                error,
+            });
+        }
+    }
+}
+
+fn save_if_unchanged(app_handle: &tauri::AppHandle, key: (String, String), generation: u64) {
+    let buffer = {
+        let state = app_handle.state::<Mutex<AutosaveState>>();
+        let mut state = state.lock().unwrap();
+        match state.buffers.get(&key) {
+            Some(buffer) if buffer.generation == generation => state.buffers.remove(&key),
+            _ => None,
+        }
+    };
+
+    if let Some(buffer) = buffer {
+        save(app_handle, &key, buffer);
+    }
+}
+
+// Saves every buffer reported by the window, for focus loss and close.
+pub fn save_window(app_handle: &tauri::AppHandle, label: &str) {
+    let buffers: Vec<((String, String), DirtyBuffer)> = {
+        let state = app_handle.state::<Mutex<AutosaveState>>();
+        let mut state = state.lock().unwrap();
+        let keys: Vec<(String, String)> = state.buffers.keys()
+            .filter(|(window, _)| window == label)
+            .cloned()
+            .collect();
+        keys.into_iter()
+            .filter_map(|key| state.buffers.remove(&key).map(|buffer| (key, buffer)))
+            .collect()
+    };
+
+    for (key, buffer) in buffers {
+        save(app_handle, &key, buffer);
+    }
+}
+
+pub fn handle_window_event(window: &tauri::Window, event: &tauri::WindowEvent) {
+    let app_handle = window.app_handle();
+    let mode = match ConfigManager::autosave_settings(app_handle) {
+        Ok((mode, _)) => mode,
+        Err(_) => return,
+    };
+
+    match event {
+        // Saving off the event loop, as a delayed save does. On close the
+        // save stays inline so it is done before the app can exit.
+        tauri::WindowEvent::Focused(false) if mode == AutosaveMode::FocusLoss => {
+            let handle = app_handle.clone();
+            let label = window.label().to_string();
+            std::thread::spawn(move || save_window(&handle, &label));
+        }
+        tauri::WindowEvent::Destroyed if mode != AutosaveMode::Off => {
+            save_window(app_handle, window.label());
+        }
+        _ => {}
+    }
+}
+
+#[tauri::command]
+pub fn update_autosave_buffer(buffer: AutosaveBuffer, window: tauri::Window) -> Result<(), String> {
+    let app_handle = window.app_handle();
+    let (mode, delay_ms) = ConfigManager::autosave_settings(app_handle)?;
+    let key = (window.label().to_string(), buffer.buffer_id);
+
+    let state = app_handle.state::<Mutex<AutosaveState>>();
+    let mut state = state.lock().unwrap();
+
+    if mode == AutosaveMode::Off || buffer.path.is_empty() || buffer.read_only.unwrap_or(false) {
+        state.buffers.remove(&key);
+        return Ok(());
+    }
+
+    state.generation += 1;
+    let generation = state.generation;
+    state.buffers.insert(key.clone(), DirtyBuffer {
+        path: buffer.path,
+        content: buffer.content,
+        encoding: buffer.encoding,
+        saved_hash: buffer.saved_hash,
+        generation,
+    });
+
+    if mode == AutosaveMode::Delay {
+        let handle = app_handle.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(delay_ms));
+            save_if_unchanged(&handle, key, generation);
+        });
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn clear_autosave_buffer(buffer_id: String, window: tauri::Window) -> Result<(), String> {
+    let app_handle = window.app_handle();
+    let state = app_handle.state::<Mutex<AutosaveState>>();
+    let mut state = state.lock().unwrap();
+
+    state.buffers.remove(&(window.label().to_string(), buffer_id));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_falls_back_to_off() {
+        assert_eq!(AutosaveMode::parse(Some("delay")), AutosaveMode::Delay);
+        assert_eq!(AutosaveMode::parse(Some("focus_loss")), AutosaveMode::FocusLoss);
+        assert_eq!(AutosaveMode::parse(Some("window_close")), AutosaveMode::WindowClose);
+        assert_eq!(AutosaveMode::parse(Some("off")), AutosaveMode::Off);
+        assert_eq!(AutosaveMode::parse(Some("Delay")), AutosaveMode::Off);
+        assert_eq!(AutosaveMode::parse(None), AutosaveMode::Off);
+    }
+}
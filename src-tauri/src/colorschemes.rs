@@ -0,0 +1,610 @@
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{Emitter, Manager};
+
+use crate::config::{self, ConfigManager};
+use crate::themes;
+
+pub const COLORSCHEMES_DIR: &str = "colorschemes";
+
+// The themes in `theme.ts` and `firowtheme.css`. A user scheme starts from
+// one of them and overrides some of its colors.
+const BUILTIN_SCHEMES: &[&str] = &[
+    "Firow", "catppuccin", "cerberus", "concord", "crimson", "fennec",
+    "hamlindigo", "legacy", "mint", "modern", "mona", "nosh", "nouveau",
+    "pine", "reign", "rocket", "rose", "sahara", "seafoam", "terminus",
+    "vintage", "vox", "wintry",
+];
+const DEFAULT_BASE: &str = "Firow";
+
+// A scheme derived from the active Monaco theme, whichever it is.
+pub const MONACO_SCHEME: &str = "monaco";
+
+// Colors a scheme can set by name. Each one becomes `--firow-<name>`, with
+// dashes, which the panel, tab and status bar styles in app.css read.
+const SCHEME_COLORS: &[&str] = &[
+    "panel_background", "panel_foreground",
+    "titlebar_background", "titlebar_foreground",
+    "toolbar_background", "toolbar_foreground",
+    "tab_background", "tab_foreground",
+    "tab_active_background", "tab_active_foreground",
+    "tab_modified_background", "tab_modified_foreground",
+    "tab_active_modified_background", "tab_active_modified_foreground",
+    "statusbar_background", "statusbar_foreground",
+    "accent",
+];
+
+// CSS variables a scheme may set directly, such as the Skeleton palette.
+const VARIABLE_PREFIXES: &[&str] = &["--firow-", "--color-", "--body-background-color", "--base-font-color"];
+
+const COLOR_FUNCTIONS: &[&str] = &["rgb", "rgba", "hsl", "hsla", "hwb", "lab", "lch", "oklab", "oklch", "color-mix", "var"];
+const GRADIENT_FUNCTIONS: &[&str] = &["linear-gradient", "radial-gradient"];
+
+// The accent replaces the primary palette: lighter shades mix it with
+// white and darker ones with black, keeping this much of it.
+const ACCENT_SHADES: &[(u32, u32)] = &[
+    (50, 15), (100, 30), (200, 50), (300, 70), (400, 85), (500, 100),
+    (600, 85), (700, 70), (800, 55), (900, 40), (950, 30),
+];
+
+// Workbench colors of an imported VS Code theme that carry over to a
+// derived scheme, most specific first.
+const DERIVED_COLORS: &[(&str, &[&str])] = &[
+    ("panel_background", &["sideBar.background", "editor.background"]),
+    ("panel_foreground", &["sideBar.foreground", "editor.foreground"]),
+    ("titlebar_background", &["titleBar.activeBackground"]),
+    ("titlebar_foreground", &["titleBar.activeForeground"]),
+    ("toolbar_background", &["activityBar.background", "editorGroupHeader.tabsBackground"]),
+    ("toolbar_foreground", &["activityBar.foreground"]),
+    ("tab_background", &["tab.inactiveBackground"]),
+    ("tab_foreground", &["tab.inactiveForeground"]),
+    ("tab_active_background", &["tab.activeBackground"]),
+    ("tab_active_foreground", &["tab.activeForeground"]),
+    ("statusbar_background", &["statusBar.background"]),
+    ("statusbar_foreground", &["statusBar.foreground"]),
+    ("accent", &["focusBorder", "button.background", "editorCursor.foreground"]),
+];
+
+pub struct ColorSchemeState {
+    watcher: Option<notify::RecommendedWatcher>,
+}
+
+impl ColorSchemeState {
+    pub fn new() -> Self {
+        Self { watcher: None }
+    }
+}
+
+// A scheme as written by users, in JSON. CSS files are read into the same
+// shape.
+#[derive(Deserialize, Default, Debug)]
+#[serde(deny_unknown_fields)]
+struct SchemeFile {
+    name: Option<String>,
+    base: Option<String>,
+    #[serde(default)]
+    colors: HashMap<String, String>,
+    #[serde(default)]
+    variables: HashMap<String, String>,
+}
+
+// A validated scheme, ready to apply: the built-in theme to start from and
+// the CSS variables to set on top of it.
+#[derive(Serialize, Clone, Debug)]
+pub struct ColorScheme {
+    pub name: String,
+    pub display_name: String,
+    pub base: String,
+    pub variables: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ColorSchemePreview {
+    pub background: Option<String>,
+    pub foreground: Option<String>,
+    pub accent: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ColorSchemeInfo {
+    pub name: String,
+    pub display_name: String,
+    pub base: String,
+    pub builtin: bool,
+    pub preview: ColorSchemePreview,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+struct ColorSchemesChanged {
+    schemes: Vec<ColorSchemeInfo>,
+    changed: Vec<String>,
+}
+
+pub fn is_builtin(name: &str) -> bool {
+    name == MONACO_SCHEME || BUILTIN_SCHEMES.contains(&name)
+}
+
+fn is_balanced(value: &str) -> bool {
+    let mut depth = 0i32;
+    for c in value.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ => {}
+        }
+        if depth < 0 {
+            return false;
+        }
+    }
+    depth == 0
+}
+
+// Accepts hex colors, color keywords and color functions. Values end up in
+// the page's styles, so anything that could load a resource or break out of
+// the declaration is rejected.
+fn is_css_color(value: &str, allow_gradient: bool) -> bool {
+    let value = value.trim();
+    if let Some(digits) = value.strip_prefix('#') {
+        return themes::is_hex(digits);
+    }
+    if value.chars().all(|c| c.is_ascii_alphabetic()) {
+        return (1..=32).contains(&value.len());
+    }
+
+    let Some((function, args)) = value.strip_suffix(')').and_then(|value| value.split_once('(')) else {
+        return false;
+    };
+    let function = function.to_ascii_lowercase();
+    let known = COLOR_FUNCTIONS.contains(&function.as_str())
+        || (allow_gradient && GRADIENT_FUNCTIONS.contains(&function.as_str()));
+    known
+        && !args.to_ascii_lowercase().contains("url(")
+        && args.chars().all(|c| c.is_ascii_alphanumeric() || " #%.,/+-_()".contains(c))
+        && is_balanced(args)
+}
+
+fn is_variable_name(name: &str) -> bool {
+    VARIABLE_PREFIXES.iter().any(|prefix| name.starts_with(prefix))
+        && name[2..].chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn accent_ramp(accent: &str) -> Vec<(String, String)> {
+    ACCENT_SHADES
+        .iter()
+        .map(|(shade, amount)| {
+            let value = match shade {
+                500 => accent.to_string(),
+                shade if *shade < 500 => format!("color-mix(in oklab, {} {}%, white)", accent, amount),
+                _ => format!("color-mix(in oklab, {} {}%, black)", accent, amount),
+            };
+            (format!("--color-primary-{}", shade), value)
+        })
+        .collect()
+}
+
+fn sorted(map: &HashMap<String, String>) -> Vec<(&String, &String)> {
+    let mut entries: Vec<_> = map.iter().collect();
+    entries.sort();
+    entries
+}
+
+// Checks a scheme and turns its named colors into CSS variables. Variables
+// set directly win over the ones the named colors produce.
+fn resolve(name: &str, file: SchemeFile) -> Result<ColorScheme, String> {
+    let mut errors = Vec::new();
+
+    let base = file.base.unwrap_or_else(|| DEFAULT_BASE.to_string());
+    if !BUILTIN_SCHEMES.contains(&base.as_str()) {
+        errors.push(format!("base: {} is not a built-in scheme", base));
+    }
+
+    let mut variables = BTreeMap::new();
+    for (key, value) in sorted(&file.colors) {
+        if !SCHEME_COLORS.contains(&key.as_str()) {
+            errors.push(format!("colors.{}: unknown color, expected one of {}", key, SCHEME_COLORS.join(", ")));
+        } else if !is_css_color(value, key.ends_with("_background")) {
+            errors.push(format!("colors.{}: {} is not a CSS color", key, value));
+        } else {
+            variables.insert(format!("--firow-{}", key.replace('_', "-")), value.trim().to_string());
+            if key == "accent" {
+                variables.extend(accent_ramp(value.trim()));
+            }
+        }
+    }
+
+    for (variable, value) in sorted(&file.variables) {
+        if !is_variable_name(variable) {
+            errors.push(format!("variables.{}: must start with one of {}", variable, VARIABLE_PREFIXES.join(", ")));
+        } else if !is_css_color(value, variable.ends_with("background")) {
+            errors.push(format!("variables.{}: {} is not a CSS color", variable, value));
+        } else {
+            variables.insert(variable.clone(), value.trim().to_string());
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors.join("; "));
+    }
+    Ok(ColorScheme {
+        name: name.to_string(),
+        display_name: file.name.unwrap_or_else(|| name.to_string()),
+        base,
+        variables,
+    })
+}
+
+// Reads `--name: value` declarations from a CSS file, so they can be
+// wrapped in `:root { }` or not. Comments starting with `@name` or `@base`
+// set the display name and the built-in scheme to start from.
+fn parse_css(content: &str) -> Result<SchemeFile, String> {
+    let mut file = SchemeFile::default();
+    let mut body = String::new();
+    let mut rest = content;
+
+    while let Some(start) = rest.find("/*") {
+        body.push_str(&rest[..start]);
+        let end = rest[start..].find("*/").ok_or("unterminated comment")? + start;
+        for line in rest[start + 2..end].lines() {
+            let line = line.trim().trim_start_matches('*').trim();
+            if let Some(name) = line.strip_prefix("@name") {
+                file.name = Some(name.trim().to_string());
+            } else if let Some(base) = line.strip_prefix("@base") {
+                file.base = Some(base.trim().to_string());
+            }
+        }
+        rest = &rest[end + 2..];
+    }
+    body.push_str(rest);
+    body.push(';');
+
+    let mut chunk_start = 0;
+    for (index, c) in body.char_indices() {
+        if !matches!(c, ';' | '{' | '}') {
+            continue;
+        }
+        let chunk = body[chunk_start..index].trim();
+        chunk_start = index + 1;
+        // What comes before `{` is a selector.
+        if chunk.is_empty() || c == '{' {
+            continue;
+        }
+        let (variable, value) = chunk
+            .split_once(':')
+            .filter(|(variable, _)| variable.trim().starts_with("--"))
+            .ok_or_else(|| format!("only CSS variables are allowed: {}", chunk))?;
+        file.variables.insert(variable.trim().to_string(), value.trim().to_string());
+    }
+    Ok(file)
+}
+
+// Scheme names come from the frontend and become file names, so anything
+// that could leave the directory is rejected. JSON wins over CSS when both
+// exist.
+fn scheme_path(schemes_dir: &Path, name: &str) -> Result<PathBuf, String> {
+    if !config::is_safe_file_name(name) {
+        return Err(format!("Invalid color scheme name: {}", name));
+    }
+    let json_path = schemes_dir.join(format!("{}.json", name));
+    if json_path.exists() {
+        return Ok(json_path);
+    }
+    Ok(schemes_dir.join(format!("{}.css", name)))
+}
+
+fn read_scheme(schemes_dir: &Path, name: &str) -> Result<ColorScheme, String> {
+    let path = scheme_path(schemes_dir, name)?;
+    if !path.exists() {
+        return Err(format!("Color scheme not found: {}", name));
+    }
+
+    let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    let file = if path.extension().and_then(|e| e.to_str()) == Some("css") {
+        parse_css(&content)
+    } else {
+        serde_json::from_str(&content).map_err(|e| e.to_string())
+    };
+    file.and_then(|file| resolve(name, file))
+        .map_err(|e| format!("Color scheme {} is invalid: {}", name, e))
+}
+
+fn preview(scheme: &ColorScheme) -> ColorSchemePreview {
+    let variable = |name: &str| scheme.variables.get(name).cloned();
+    ColorSchemePreview {
+        background: variable("--firow-panel-background"),
+        foreground: variable("--firow-panel-foreground"),
+        accent: variable("--firow-accent").or_else(|| variable("--color-primary-500")),
+    }
+}
+
+fn scheme_name(path: &Path) -> Option<String> {
+    let extension = path.extension().and_then(|e| e.to_str())?;
+    if extension != "json" && extension != "css" {
+        return None;
+    }
+    path.file_stem()
+        .and_then(|stem| stem.to_str())
+        .filter(|stem| config::is_safe_file_name(stem))
+        .map(|stem| stem.to_string())
+}
+
+fn builtin_info(name: &str, display_name: &str) -> ColorSchemeInfo {
+    ColorSchemeInfo {
+        name: name.to_string(),
+        display_name: display_name.to_string(),
+        base: name.to_string(),
+        builtin: true,
+        preview: ColorSchemePreview { background: None, foreground: None, accent: None },
+        error: None,
+    }
+}
+
+// Built-in schemes first, then the one following the Monaco theme, then the
+// scheme files sorted by display name. Files that fail validation, or that
+// would shadow a built-in scheme, are listed with their error.
+pub fn list(schemes_dir: &Path) -> Vec<ColorSchemeInfo> {
+    let mut names: Vec<String> = fs::read_dir(schemes_dir)
+        .map(|entries| entries.flatten().filter_map(|entry| scheme_name(&entry.path())).collect())
+        .unwrap_or_default();
+    names.sort();
+    names.dedup();
+
+    let mut schemes: Vec<ColorSchemeInfo> = names
+        .into_iter()
+        .map(|name| {
+            let scheme = if is_builtin(&name) {
+                Err(format!("{} is the name of a built-in color scheme", name))
+            } else {
+                read_scheme(schemes_dir, &name)
+            };
+            match scheme {
+                Ok(scheme) => ColorSchemeInfo {
+                    preview: preview(&scheme),
+                    name: scheme.name,
+                    display_name: scheme.display_name,
+                    base: scheme.base,
+                    builtin: false,
+                    error: None,
+                },
+                Err(e) => ColorSchemeInfo {
+                    builtin: false,
+                    error: Some(e),
+                    ..builtin_info(&name, &name)
+                },
+            }
+        })
+        .collect();
+    schemes.sort_by_key(|scheme| scheme.display_name.to_lowercase());
+
+    let mut builtins: Vec<ColorSchemeInfo> = BUILTIN_SCHEMES.iter().map(|name| builtin_info(name, name)).collect();
+    builtins.push(builtin_info(MONACO_SCHEME, "Match editor theme"));
+    builtins.into_iter().chain(schemes).collect()
+}
+
+fn colorschemes_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let notepad_dir = ConfigManager::get_notepad_md_dir(app_handle)?;
+    let schemes_dir = notepad_dir.join(COLORSCHEMES_DIR);
+    if !schemes_dir.exists() {
+        fs::create_dir_all(&schemes_dir).map_err(|e| e.to_string())?;
+    }
+    Ok(schemes_dir)
+}
+
+// Builds a scheme from a Monaco theme: workbench colors it carries from VS
+// Code are used as they are, the rest is mixed from the editor background,
+// foreground and keyword color.
+pub fn derive_from_monaco(theme_name: &str, theme: &Value) -> Result<ColorScheme, String> {
+    let info = themes::theme_info(theme_name, theme, false);
+    let colors = theme.get("colors").and_then(Value::as_object);
+    let background = info.preview.background.clone();
+    let foreground = info.preview.foreground.clone();
+    let accent = info.preview.keyword.clone().unwrap_or_else(|| foreground.clone());
+
+    let mut derived: HashMap<String, String> = HashMap::new();
+    for (key, ids) in DERIVED_COLORS {
+        let color = ids.iter().find_map(|id| colors?.get(*id)?.as_str().map(str::to_string));
+        if let Some(color) = color {
+            derived.insert(key.to_string(), color);
+        }
+    }
+
+    let mix = |color: &str, amount: u32, with: &str| format!("color-mix(in oklab, {} {}%, {})", color, amount, with);
+    let mut fallback = |key: &str, value: String| {
+        derived.entry(key.to_string()).or_insert(value);
+    };
+    fallback("panel_background", background.clone());
+    fallback("panel_foreground", foreground.clone());
+    fallback("accent", accent.clone());
+    fallback("titlebar_background", mix(&background, 85, "black"));
+    fallback("titlebar_foreground", foreground.clone());
+    fallback("toolbar_background", mix(&background, 80, &accent));
+    fallback("toolbar_foreground", foreground.clone());
+    fallback("tab_background", mix(&background, 92, &foreground));
+    fallback("tab_foreground", foreground.clone());
+    fallback("tab_active_background", mix(&background, 70, &accent));
+    fallback("tab_active_foreground", foreground.clone());
+    fallback("tab_modified_background", mix(&background, 85, &foreground));
+    fallback("tab_modified_foreground", foreground.clone());
+    fallback("tab_active_modified_background", mix(&background, 55, &accent));
+    fallback("tab_active_modified_foreground", foreground.clone());
+    fallback("statusbar_background", mix(&accent, 60, &background));
+    fallback("statusbar_foreground", foreground.clone());
+
+    let file = SchemeFile {
+        name: Some(format!("{} (editor theme)", info.display_name)),
+        base: Some(DEFAULT_BASE.to_string()),
+        colors: derived,
+        variables: HashMap::new(),
+    };
+    resolve(MONACO_SCHEME, file)
+}
+
+// Emits `colorschemes-changed` with the new list whenever a scheme file is
+// added, changed or removed.
+pub fn watch_colorschemes(app_handle: &tauri::AppHandle) -> Result<(), String> {
+    let schemes_dir = colorschemes_dir(app_handle)?;
+
+    let handle = app_handle.clone();
+    let dir = schemes_dir.clone();
+    let mut watcher = notify::recommended_watcher(move |res: Result<Event, notify::Error>| {
+        match res {
+            Ok(event) => {
+                // Listing the schemes reads every file, which must not
+                // trigger another round.
+                let relevant = matches!(event.kind, EventKind::Create(_) | EventKind::Remove(_))
+                    || matches!(event.kind, EventKind::Modify(kind) if !matches!(kind, notify::event::ModifyKind::Metadata(_)));
+                if !relevant {
+                    return;
+                }
+                let changed: Vec<String> = event.paths.iter().filter_map(|path| scheme_name(path)).collect();
+                if !changed.is_empty() {
+                    let _ = handle.emit("colorschemes-changed", ColorSchemesChanged { schemes: list(&dir), changed });
+                }
+            }
+            Err(e) => println!("Color scheme watch error: {:?}", e),
+        }
+    }).map_err(|e| e.to_string())?;
+
+    watcher.watch(&schemes_dir, RecursiveMode::NonRecursive)
+        .map_err(|e| e.to_string())?;
+
+    let state = app_handle.state::<Mutex<ColorSchemeState>>();
+    state.lock().map_err(|e| e.to_string())?.watcher = Some(watcher);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_colorschemes(app_handle: tauri::AppHandle) -> Result<Vec<ColorSchemeInfo>, String> {
+    let schemes_dir = colorschemes_dir(&app_handle)?;
+    Ok(list(&schemes_dir))
+}
+
+// `None` for built-in schemes, which need nothing beyond their theme.
+#[tauri::command]
+pub fn read_colorscheme(app_handle: tauri::AppHandle, name: String) -> Result<Option<ColorScheme>, String> {
+    if is_builtin(&name) {
+        return Ok(None);
+    }
+    let schemes_dir = colorschemes_dir(&app_handle)?;
+    read_scheme(&schemes_dir, &name).map(Some)
+}
+
+#[tauri::command]
+pub fn derive_colorscheme(app_handle: tauri::AppHandle, theme_name: String) -> Result<ColorScheme, String> {
+    let theme = themes::load_theme(&app_handle, &theme_name)?;
+    derive_from_monaco(&theme_name, &theme)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn css_colors_accept_hex_keywords_and_functions() {
+        assert!(is_css_color("#1e1e1e", false));
+        assert!(is_css_color("  transparent ", false));
+        assert!(is_css_color("rgb(30, 30, 30)", false));
+        assert!(is_css_color("color-mix(in oklab, var(--firow-accent) 50%, white)", false));
+        assert!(!is_css_color("#12345z", false));
+        assert!(!is_css_color("", false));
+    }
+
+    #[test]
+    fn css_colors_reject_unsafe_values() {
+        assert!(!is_css_color("rgb(0,0,0); background: red", false));
+        assert!(!is_css_color("var(--x) url(evil.png)", false));
+        assert!(!is_css_color("rgb(0, 0, 0))", false));
+        assert!(!is_css_color("expression(alert(1))", false));
+        assert!(!is_css_color("rgb(\"0\")", false));
+    }
+
+    #[test]
+    fn gradients_are_only_allowed_for_backgrounds() {
+        let gradient = "linear-gradient(90deg, #000, rgb(1, 2, 3))";
+        assert!(is_css_color(gradient, true));
+        assert!(!is_css_color(gradient, false));
+    }
+
+    #[test]
+    fn balanced_parentheses() {
+        assert!(is_balanced("a(b(c))"));
+        assert!(!is_balanced("a)("));
+        assert!(!is_balanced("(("));
+    }
+
+    #[test]
+    fn variable_names_need_a_known_prefix() {
+        assert!(is_variable_name("--firow-panel-background"));
+        assert!(is_variable_name("--color-primary-500"));
+        assert!(!is_variable_name("--other"));
+        assert!(!is_variable_name("--color-a;b"));
+    }
+
+    #[test]
+    fn accent_becomes_the_primary_palette() {
+        let ramp = accent_ramp("#ff0000");
+        assert_eq!(ramp.len(), ACCENT_SHADES.len());
+        assert!(ramp.contains(&("--color-primary-500".to_string(), "#ff0000".to_string())));
+        assert!(ramp.contains(&(
+            "--color-primary-50".to_string(),
+            "color-mix(in oklab, #ff0000 15%, white)".to_string(),
+        )));
+        assert!(ramp.contains(&(
+            "--color-primary-900".to_string(),
+            "color-mix(in oklab, #ff0000 40%, black)".to_string(),
+        )));
+    }
+
+    #[test]
+    fn resolve_turns_colors_into_variables() {
+        let mut file = SchemeFile::default();
+        file.colors.insert("panel_background".to_string(), " #101010 ".to_string());
+        file.colors.insert("accent".to_string(), "#ff0000".to_string());
+        file.variables.insert("--firow-panel-background".to_string(), "#202020".to_string());
+
+        let scheme = resolve("dark", file).unwrap();
+        assert_eq!(scheme.display_name, "dark");
+        assert_eq!(scheme.base, DEFAULT_BASE);
+        assert_eq!(scheme.variables["--firow-panel-background"], "#202020");
+        assert_eq!(scheme.variables["--firow-accent"], "#ff0000");
+        assert_eq!(scheme.variables["--color-primary-500"], "#ff0000");
+    }
+
+    #[test]
+    fn resolve_reports_every_problem() {
+        let mut file = SchemeFile { base: Some("nope".to_string()), ..Default::default() };
+        file.colors.insert("unknown".to_string(), "#000".to_string());
+        file.colors.insert("panel_foreground".to_string(), "url(x)".to_string());
+        file.variables.insert("--other".to_string(), "#000".to_string());
+
+        let error = resolve("bad", file).unwrap_err();
+        assert!(error.contains("base: nope"));
+        assert!(error.contains("colors.unknown"));
+        assert!(error.contains("colors.panel_foreground"));
+        assert!(error.contains("variables.--other"));
+    }
+
+    #[test]
+    fn parse_css_reads_variables_and_header_comments() {
+        let css = "/*\n * @name Night Owl\n * @base rocket\n */\n:root {\n  --firow-accent: #7fdbca;\n  --color-surface-500: rgb(1, 2, 3);\n}\n";
+        let file = parse_css(css).unwrap();
+        assert_eq!(file.name.as_deref(), Some("Night Owl"));
+        assert_eq!(file.base.as_deref(), Some("rocket"));
+        assert_eq!(file.variables["--firow-accent"], "#7fdbca");
+        assert_eq!(file.variables["--color-surface-500"], "rgb(1, 2, 3)");
+
+        let file = parse_css("--firow-accent: red").unwrap();
+        assert_eq!(file.variables["--firow-accent"], "red");
+    }
+
+    #[test]
+    fn parse_css_rejects_other_declarations() {
+        assert!(parse_css(":root { color: red; }").is_err());
+        assert!(parse_css("/* @name x").is_err());
+    }
+}
@@ -1,11 +1,55 @@
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::Mutex;
-use tauri::Manager;
+use tauri::{Emitter, Manager};
+
+mod bundle;
+mod instances;
+mod keybindings;
+mod languages;
+pub mod location;
+mod migration;
+pub mod profiles;
+mod recent;
+mod recovery;
+mod sessions;
+mod sync;
+mod validation;
+mod view_state;
+pub use bundle::{BundleChange, BundleContents, BundleOptions, BundlePreview, ImportMode};
+pub use instances::{InstanceInfo, InstanceLifecycle};
+pub use keybindings::KeybindingReport;
+pub use languages::{EditorSettings, LanguageSettings};
+pub use location::ConfigLocation;
+pub use profiles::{ProfileInfo, ProfileState};
+pub use recent::{RecentEntry, RecentFile};
+pub use sessions::{Session, SessionFile, SessionSummary};
+pub use validation::{ConfigValidation, FieldError};
+pub use view_state::FileViewState;
+use migration::CONFIG_SCHEMA_VERSION;
+use notify::{RecursiveMode, Watcher};
+use sync::GlobalConfigLock;
+pub use recovery::{write_atomic, ConfigRecovery};
+
+pub const GLOBAL_CONFIG_FILE: &str = "firow-notepad-global.json";
+
+// Names of sessions and profiles double as file names, so anything that
+// could escape their directory or is invalid on Windows is rejected.
+pub fn is_safe_file_name(name: &str) -> bool {
+    !(name.trim().is_empty()
+        || name != name.trim()
+        || name.len() > 100
+        || name.starts_with('.')
+        || name.ends_with('.')
+        || name.chars().any(|c| c.is_control() || "/\\:*?\"<>|".contains(c)))
+}
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct GlobalConfig {
+    pub schema_version: Option<u32>,
     pub colorscheme: Option<String>,
     pub monaco_editor_theme: Option<String>,
     pub font_size: Option<i32>,
@@ -13,11 +57,20 @@ pub struct GlobalConfig {
     pub show_invisibles: Option<bool>,
     pub transparent_mode: Option<bool>,
     pub window_opacity: Option<f32>,
+    pub default_encoding: Option<String>,
+    pub languages: Option<HashMap<String, LanguageSettings>>,
+    pub instance_retention_days: Option<u32>,
+    pub autosave: Option<String>,
+    pub autosave_delay_ms: Option<u64>,
+    pub keybindings: Option<HashMap<String, String>>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 impl Default for GlobalConfig {
     fn default() -> Self {
         Self {
+            schema_version: Some(CONFIG_SCHEMA_VERSION),
             colorscheme: Some("FirowNotepad".to_string()),
             monaco_editor_theme: Some("vs-dark".to_string()),
             font_size: Some(14),
@@ -25,21 +78,51 @@ impl Default for GlobalConfig {
             show_invisibles: Some(false),
             transparent_mode: Some(false),
             window_opacity: Some(0.85),
+            default_encoding: Some("utf-8".to_string()),
+            languages: Some(HashMap::new()),
+            instance_retention_days: Some(instances::DEFAULT_INSTANCE_RETENTION_DAYS),
+            autosave: Some("off".to_string()),
+            autosave_delay_ms: Some(1000),
+            keybindings: Some(HashMap::new()),
+            extra: Map::new(),
         }
     }
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct WatchedFolder {
+    pub path: String,
+    pub pattern: Option<String>,
+    pub follow: Option<bool>,
+    // Label of the window that watches the folder and opens its new files.
+    #[serde(default)]
+    pub window: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct InstanceConfig {
+    pub schema_version: Option<u32>,
     pub recent_files: Option<Vec<String>>,
+    pub recent_entries: Option<HashMap<String, RecentEntry>>,
     pub opened_files: Option<Vec<String>>,
+    pub watched_folders: Option<Vec<WatchedFolder>>,
+    pub file_states: Option<HashMap<String, FileViewState>>,
+    pub lifecycle: Option<InstanceLifecycle>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 impl Default for InstanceConfig {
     fn default() -> Self {
         Self {
+            schema_version: Some(CONFIG_SCHEMA_VERSION),
             recent_files: Some(vec![]),
+            recent_entries: Some(HashMap::new()),
             opened_files: Some(vec![]),
+            watched_folders: Some(vec![]),
+            file_states: Some(HashMap::new()),
+            lifecycle: None,
+            extra: Map::new(),
         }
     }
 }
@@ -55,21 +138,19 @@ pub struct AppConfig {
     pub show_invisibles: Option<bool>,
     pub transparent_mode: Option<bool>,
     pub window_opacity: Option<f32>,
+    pub default_encoding: Option<String>,
+    pub languages: Option<HashMap<String, LanguageSettings>>,
+    pub instance_retention_days: Option<u32>,
+    pub autosave: Option<String>,
+    pub autosave_delay_ms: Option<u64>,
+    pub keybindings: Option<HashMap<String, String>>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 impl Default for AppConfig {
     fn default() -> Self {
-        Self {
-            colorscheme: Some("FirowNotepad".to_string()),
-            monaco_editor_theme: Some("vs-dark".to_string()),
-            recent_files: Some(vec![]),
-            opened_files: Some(vec![]),
-            font_size: Some(14),
-            word_wrap: Some(false),
-            show_invisibles: Some(false),
-            transparent_mode: Some(false),
-            window_opacity: Some(0.85),
-        }
+        Self::from_global_and_instance(GlobalConfig::default(), InstanceConfig::default())
     }
 }
 
@@ -79,6 +160,9 @@ impl AppConfig {
     }
 
     pub fn from_global_and_instance(global: GlobalConfig, instance: InstanceConfig) -> Self {
+        let mut extra = global.extra;
+        extra.extend(instance.extra);
+
         Self {
             colorscheme: global.colorscheme,
             monaco_editor_theme: global.monaco_editor_theme,
@@ -87,13 +171,21 @@ impl AppConfig {
             show_invisibles: global.show_invisibles,
             transparent_mode: global.transparent_mode,
             window_opacity: global.window_opacity,
+            default_encoding: global.default_encoding,
+            languages: global.languages,
+            instance_retention_days: global.instance_retention_days,
+            autosave: global.autosave,
+            autosave_delay_ms: global.autosave_delay_ms,
+            keybindings: global.keybindings,
             recent_files: instance.recent_files,
             opened_files: instance.opened_files,
+            extra,
         }
     }
 
     pub fn to_global(&self) -> GlobalConfig {
         GlobalConfig {
+            schema_version: Some(CONFIG_SCHEMA_VERSION),
             colorscheme: self.colorscheme.clone(),
             monaco_editor_theme: self.monaco_editor_theme.clone(),
             font_size: self.font_size,
@@ -101,54 +193,79 @@ impl AppConfig {
             show_invisibles: self.show_invisibles,
             transparent_mode: self.transparent_mode,
             window_opacity: self.window_opacity,
+            default_encoding: self.default_encoding.clone(),
+            languages: self.languages.clone(),
+            instance_retention_days: self.instance_retention_days,
+            autosave: self.autosave.clone(),
+            autosave_delay_ms: self.autosave_delay_ms,
+            keybindings: self.keybindings.clone(),
+            extra: self.extra.clone(),
         }
     }
 
     pub fn to_instance(&self) -> InstanceConfig {
         InstanceConfig {
+            schema_version: Some(CONFIG_SCHEMA_VERSION),
             recent_files: self.recent_files.clone(),
+            recent_entries: None,
             opened_files: self.opened_files.clone(),
+            watched_folders: None,
+            file_states: None,
+            lifecycle: None,
+            extra: Map::new(),
         }
     }
+}
 
-    pub fn from_file(path: &Path) -> Result<Self, String> {
-        let config_file = fs::File::open(path).map_err(|e| e.to_string())?;
-        serde_json::from_reader(config_file).map_err(|e| e.to_string())
+impl GlobalConfig {
+    pub fn to_map(&self) -> Result<Map<String, Value>, String> {
+        match serde_json::to_value(self).map_err(|e| e.to_string())? {
+            Value::Object(map) => Ok(map),
+            _ => Err("Global config did not serialize to an object".to_string()),
+        }
     }
 
-    pub fn save_to_file(&self, path: &Path) -> Result<(), String> {
-        let config_str = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
-        fs::write(path, config_str).map_err(|e| e.to_string())
+    pub fn from_map(map: Map<String, Value>) -> Result<Self, String> {
+        serde_json::from_value(Value::Object(map)).map_err(|e| e.to_string())
     }
-}
 
-impl GlobalConfig {
     pub fn from_file(path: &Path) -> Result<Self, String> {
-        let config_file = fs::File::open(path).map_err(|e| e.to_string())?;
-        serde_json::from_reader(config_file).map_err(|e| e.to_string())
+        migration::load_global(path).map(|(config, _)| config)
     }
 
     pub fn save_to_file(&self, path: &Path) -> Result<(), String> {
+        migration::check_writable(path)?;
         let config_str = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
-        fs::write(path, config_str).map_err(|e| e.to_string())
+        write_atomic(path, &config_str)
     }
 }
 
 impl InstanceConfig {
     pub fn from_file(path: &Path) -> Result<Self, String> {
-        let config_file = fs::File::open(path).map_err(|e| e.to_string())?;
-        serde_json::from_reader(config_file).map_err(|e| e.to_string())
+        migration::load_instance(path).map(|(config, _)| config)
     }
 
     pub fn save_to_file(&self, path: &Path) -> Result<(), String> {
+        migration::check_writable(path)?;
         let config_str = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
-        fs::write(path, config_str).map_err(|e| e.to_string())
+        write_atomic(path, &config_str)
     }
 }
 
 pub struct AppData {
     pub app_config: AppConfig,
     pub instance_id: Option<String>,
+    pub watched_folders: Vec<WatchedFolder>,
+    pub file_states: HashMap<String, FileViewState>,
+    pub recent_entries: HashMap<String, RecentEntry>,
+    pub lifecycle: InstanceLifecycle,
+    pub instance_lock: Option<instances::InstanceLock>,
+    pub instance_extra_keys: Vec<String>,
+    pub recoveries: Vec<ConfigRecovery>,
+    pub validations: Vec<ConfigValidation>,
+    pub global_baseline: Map<String, Value>,
+    pub global_watcher: Option<notify::RecommendedWatcher>,
+    pub glob_cache: languages::GlobCache,
 }
 
 impl AppData {
@@ -156,8 +273,47 @@ impl AppData {
         Self {
             app_config: AppConfig::new(),
             instance_id: None,
+            watched_folders: vec![],
+            file_states: HashMap::new(),
+            recent_entries: HashMap::new(),
+            lifecycle: InstanceLifecycle::default(),
+            instance_lock: None,
+            instance_extra_keys: vec![],
+            recoveries: vec![],
+            validations: vec![],
+            global_baseline: Map::new(),
+            global_watcher: None,
+            glob_cache: languages::GlobCache::default(),
         }
     }
+
+    // Unknown keys are written back to the file they were loaded from; keys
+    // that appeared in neither file (e.g. from a newer frontend) go to the
+    // global config.
+    pub fn global_config(&self) -> GlobalConfig {
+        let mut global_config = self.app_config.to_global();
+        global_config.extra.retain(|key, _| !self.instance_extra_keys.contains(key));
+        global_config
+    }
+
+    pub fn instance_config(&self) -> InstanceConfig {
+        let mut instance_config = self.app_config.to_instance();
+        instance_config.watched_folders = Some(self.watched_folders.clone());
+        instance_config.file_states = Some(self.file_states.clone());
+        instance_config.recent_entries = Some(self.recent_entries.clone());
+        instance_config.lifecycle = Some(self.lifecycle.clone());
+        instance_config.extra = self.app_config.extra.iter()
+            .filter(|(key, _)| self.instance_extra_keys.contains(key))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        instance_config
+    }
+
+    // `recent_files` is kept for the frontend and older versions; the entries
+    // are the source of truth.
+    pub fn sync_recent_files(&mut self) {
+        self.app_config.recent_files = Some(recent::by_recency(&self.recent_entries));
+    }
 }
 
 pub struct Storage {
@@ -184,11 +340,15 @@ pub struct ConfigManager;
 
 impl ConfigManager {
     pub fn get_notepad_md_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
-        let config_dir = app_handle
-            .path()
-            .config_dir()
-            .map_err(|e| e.to_string())?;
-        let notepad_dir = config_dir.join("FirowNotepad");
+        let location = app_handle.state::<ConfigLocation>();
+        let notepad_dir = match &location.dir {
+            Some(dir) => dir.clone(),
+            None => app_handle
+                .path()
+                .config_dir()
+                .map_err(|e| e.to_string())?
+                .join("FirowNotepad"),
+        };
         
         if !notepad_dir.exists() {
             fs::create_dir_all(&notepad_dir).map_err(|e| e.to_string())?;
@@ -197,22 +357,105 @@ impl ConfigManager {
         Ok(notepad_dir)
     }
     
+    pub fn active_profile(app_handle: &tauri::AppHandle) -> Result<String, String> {
+        let notepad_dir = Self::get_notepad_md_dir(app_handle)?;
+        let profile_state = app_handle.state::<ProfileState>();
+        Ok(profiles::resolve(&profile_state, &notepad_dir))
+    }
+
+    pub fn get_profile_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+        let notepad_dir = Self::get_notepad_md_dir(app_handle)?;
+        let profile = Self::active_profile(app_handle)?;
+        let profile_dir = profiles::profile_dir(&notepad_dir, &profile)?;
+        
+        if !profile_dir.exists() {
+            fs::create_dir_all(&profile_dir).map_err(|e| e.to_string())?;
+        }
+        
+        Ok(profile_dir)
+    }
+    
     pub fn get_global_config_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+        let profile_dir = Self::get_profile_dir(app_handle)?;
+        Ok(profile_dir.join(GLOBAL_CONFIG_FILE))
+    }
+
+    pub fn get_sessions_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
         let notepad_dir = Self::get_notepad_md_dir(app_handle)?;
-        Ok(notepad_dir.join("firow-notepad-global.json"))
+        let sessions_dir = notepad_dir.join(sessions::SESSIONS_DIR);
+        
+        if !sessions_dir.exists() {
+            fs::create_dir_all(&sessions_dir).map_err(|e| e.to_string())?;
+        }
+        
+        Ok(sessions_dir)
     }
 
-    pub fn get_instance_config_path(app_handle: &tauri::AppHandle, instance_id: &str) -> Result<PathBuf, String> {
+    pub fn get_monaco_themes_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+        let notepad_dir = Self::get_notepad_md_dir(app_handle)?;
+        let themes_dir = notepad_dir.join(bundle::MONACO_THEMES_DIR);
+        
+        if !themes_dir.exists() {
+            fs::create_dir_all(&themes_dir).map_err(|e| e.to_string())?;
+        }
+        
+        Ok(themes_dir)
+    }
+
+    pub fn get_instances_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
         let notepad_dir = Self::get_notepad_md_dir(app_handle)?;
-        let instances_dir = notepad_dir.join("firow-notepad-instances");
+        let instances_dir = notepad_dir.join(instances::INSTANCES_DIR);
         
         if !instances_dir.exists() {
             fs::create_dir_all(&instances_dir).map_err(|e| e.to_string())?;
         }
         
+        Ok(instances_dir)
+    }
+
+    pub fn get_instance_config_path(app_handle: &tauri::AppHandle, instance_id: &str) -> Result<PathBuf, String> {
+        let instances_dir = Self::get_instances_dir(app_handle)?;
         Ok(instances_dir.join(format!("{}.json", instance_id)))
     }
 
+    fn report_recovery(app_handle: &tauri::AppHandle, app_data: &mut AppData, recovery: Option<ConfigRecovery>) {
+        if let Some(recovery) = recovery {
+            let _ = app_handle.emit("config-recovered", &recovery);
+            app_data.recoveries.push(recovery);
+        }
+    }
+
+    fn report_validation(app_handle: &tauri::AppHandle, app_data: &mut AppData, path: &Path, errors: Vec<FieldError>) {
+        if errors.is_empty() {
+            return;
+        }
+        let validation = ConfigValidation {
+            file: path.to_string_lossy().into_owned(),
+            errors,
+        };
+        let _ = app_handle.emit("config-invalid", &validation);
+        app_data.validations.push(validation);
+    }
+
+    fn sanitize_global_config(app_handle: &tauri::AppHandle, app_data: &mut AppData, path: &Path, mut config: GlobalConfig) -> GlobalConfig {
+        let errors = validation::sanitize_global(&mut config);
+        Self::report_validation(app_handle, app_data, path, errors);
+        config
+    }
+
+    fn load_global_config(app_handle: &tauri::AppHandle, app_data: &mut AppData, global_path: &Path) -> Result<GlobalConfig, String> {
+        if global_path.exists() {
+            let (config, errors, recovery) = recovery::load_or_recover(global_path, migration::load_global)?;
+            Self::report_recovery(app_handle, app_data, recovery);
+            Self::report_validation(app_handle, app_data, global_path, errors);
+            Ok(config)
+        } else {
+            let config = GlobalConfig::default();
+            config.save_to_file(global_path)?;
+            Ok(config)
+        }
+    }
+
     pub fn load_config(app_handle: &tauri::AppHandle) -> Result<(), String> {
         let storage = app_handle.state::<Storage>();
         let mut app_data = storage.app_data.lock().map_err(|e| e.to_string())?;
@@ -220,48 +463,194 @@ impl ConfigManager {
         let instance_id = app_data.instance_id.clone().unwrap_or_else(|| "main".to_string());
         
         let global_path = Self::get_global_config_path(app_handle)?;
-        let global_config = if global_path.exists() {
-            GlobalConfig::from_file(&global_path)?
-        } else {
-            let config = GlobalConfig::default();
-            config.save_to_file(&global_path)?;
-            config
-        };
+        let global_config = Self::load_global_config(app_handle, &mut app_data, &global_path)?;
+        if let Err(e) = validation::write_schemas(&Self::get_notepad_md_dir(app_handle)?) {
+            println!("Failed to write config schemas: {}", e);
+        }
         
+        // The lock is taken before the file is first written, so other
+        // instances never see it unlocked and without a lifecycle.
+        let first_load = app_data.lifecycle.started_at.is_none();
+        if first_load {
+            let instances_dir = Self::get_instances_dir(app_handle)?;
+            app_data.instance_lock = instances::InstanceLock::acquire(&instances_dir, &instance_id)?;
+        }
+
         let instance_path = Self::get_instance_config_path(app_handle, &instance_id)?;
-        let instance_config = if instance_path.exists() {
-            InstanceConfig::from_file(&instance_path)?
+        let mut instance_config = if instance_path.exists() {
+            let (config, errors, recovery) = recovery::load_or_recover(&instance_path, migration::load_instance)?;
+            Self::report_recovery(app_handle, &mut app_data, recovery);
+            Self::report_validation(app_handle, &mut app_data, &instance_path, errors);
+            config
         } else {
             let config = InstanceConfig::default();
             config.save_to_file(&instance_path)?;
             config
         };
         
+        // The baseline is what the file holds, so the next save writes the
+        // corrected values over invalid ones.
+        app_data.global_baseline = global_config.to_map()?;
+        let global_config = Self::sanitize_global_config(app_handle, &mut app_data, &global_path, global_config);
+        let errors = validation::sanitize_instance(&mut instance_config);
+        Self::report_validation(app_handle, &mut app_data, &instance_path, errors);
+        for path in [&global_path, &instance_path] {
+            if let Err(message) = migration::check_writable(path) {
+                let error = FieldError { field: "schema_version".to_string(), message };
+                Self::report_validation(app_handle, &mut app_data, path, vec![error]);
+            }
+        }
+        app_data.watched_folders = instance_config.watched_folders.clone().unwrap_or_default();
+        app_data.file_states = instance_config.file_states.clone().unwrap_or_default();
+        app_data.recent_entries = match instance_config.recent_entries.clone() {
+            Some(entries) => entries,
+            None => recent::from_list(
+                instance_config.recent_files.as_deref().unwrap_or_default(),
+                chrono::Local::now().timestamp_millis(),
+            ),
+        };
+        app_data.instance_extra_keys = instance_config.extra.keys().cloned().collect();
         app_data.app_config = AppConfig::from_global_and_instance(global_config, instance_config);
-        app_data.instance_id = Some(instance_id);
+        app_data.sync_recent_files();
+        
+        // The first load marks the instance as running; the lifecycle on disk
+        // then stays unclosed until a clean exit.
+        app_data.instance_id = Some(instance_id.clone());
+        if first_load {
+            app_data.lifecycle = InstanceLifecycle {
+                pid: Some(std::process::id()),
+                started_at: Some(chrono::Local::now().timestamp_millis()),
+                closed_at: None,
+            };
+            if let Err(e) = app_data.instance_config().save_to_file(&instance_path) {
+                println!("Failed to record instance start: {}", e);
+            }
+        }
+
+        Ok(())
+    }
 
+    // Other instances may have written the global file since we last read it,
+    // so only the keys this instance changed are merged into the file on disk.
+    fn write_global_config(global_path: &Path, app_data: &mut AppData) -> Result<(), String> {
+        let updated = app_data.global_config().to_map()?;
+        let _lock = GlobalConfigLock::acquire(global_path)?;
+        
+        let exists = global_path.exists();
+        let mut on_disk = if exists {
+            GlobalConfig::from_file(global_path)
+                .and_then(|config| config.to_map())
+                .unwrap_or_else(|_| app_data.global_baseline.clone())
+        } else {
+            app_data.global_baseline.clone()
+        };
+        
+        if sync::merge_changed_keys(&app_data.global_baseline, &updated, &mut on_disk) || !exists {
+            migration::check_writable(global_path)?;
+            let config_str = serde_json::to_string_pretty(&on_disk).map_err(|e| e.to_string())?;
+            write_atomic(global_path, &config_str)?;
+        }
+        
+        let mut merged = GlobalConfig::from_map(on_disk.clone())?;
+        validation::sanitize_global(&mut merged);
+        app_data.global_baseline = on_disk;
+        app_data.app_config = AppConfig::from_global_and_instance(merged, app_data.instance_config());
+        
+        Ok(())
+    }
+
+    fn reload_global_config(app_handle: &tauri::AppHandle, global_path: &Path) -> Result<(), String> {
+        if !global_path.exists() {
+            return Ok(());
+        }
+        
+        let global_config = GlobalConfig::from_file(global_path)?;
+        let on_disk = global_config.to_map()?;
+        
+        let storage = app_handle.state::<Storage>();
+        let mut app_data = storage.app_data.lock().map_err(|e| e.to_string())?;
+        
+        if on_disk == app_data.global_baseline {
+            return Ok(());
+        }
+        
+        app_data.global_baseline = on_disk;
+        let global_config = Self::sanitize_global_config(app_handle, &mut app_data, global_path, global_config);
+        app_data.app_config = AppConfig::from_global_and_instance(global_config, app_data.instance_config());
+        let config = app_data.app_config.clone();
+        drop(app_data);
+        
+        app_handle.emit("config-changed", config).map_err(|e| e.to_string())
+    }
+
+    // Also called after a profile switch; replacing the watcher stops the one
+    // on the previous profile.
+    pub fn watch_global_config(app_handle: &tauri::AppHandle) -> Result<(), String> {
+        let profile_dir = Self::get_profile_dir(app_handle)?;
+        let global_path = Self::get_global_config_path(app_handle)?;
+        let keybindings_path = profile_dir.join(keybindings::KEYBINDINGS_FILE);
+        
+        let handle = app_handle.clone();
+        let mut watcher = notify::recommended_watcher(move |res: Result<notify::Event, notify::Error>| {
+            match res {
+                Ok(event) => {
+                    if event.paths.iter().any(|p| p == &global_path) {
+                        if let Err(e) = Self::reload_global_config(&handle, &global_path) {
+                            println!("Failed to reload global config: {}", e);
+                        }
+                    }
+                    if event.paths.iter().any(|p| p == &keybindings_path) {
+                        let _ = handle.emit("keybindings-changed", ());
+                    }
+                }
+                Err(e) => println!("Watch error: {:?}", e),
+            }
+        }).map_err(|e| e.to_string())?;
+        
+        watcher.watch(&profile_dir, RecursiveMode::NonRecursive)
+            .map_err(|e| e.to_string())?;
+        
+        // The previous watcher's callback reloads the config under the same
+        // lock, so it is dropped only once the lock is released.
+        let storage = app_handle.state::<Storage>();
+        let previous = storage.app_data.lock().map_err(|e| e.to_string())?.global_watcher.replace(watcher);
+        drop(previous);
+        
         Ok(())
     }
 
     pub fn save_config(app_handle: &tauri::AppHandle, config: AppConfig) -> Result<(), String> {
+        let errors = Self::validate_config(&config);
+        if !errors.is_empty() {
+            return Err(format!("Invalid config: {}", validation::describe(&errors)));
+        }
+        
         let storage = app_handle.state::<Storage>();
         let mut app_data = storage.app_data.lock().map_err(|e| e.to_string())?;
         
         let instance_id = app_data.instance_id.clone().unwrap_or_else(|| "main".to_string());
         
-        let global_config = config.to_global();
+        // Recent files come from the entries, which only the recent-file
+        // commands change, not from the config the frontend sends.
+        app_data.app_config = config;
+        app_data.sync_recent_files();
+        
         let global_path = Self::get_global_config_path(app_handle)?;
-        global_config.save_to_file(&global_path)?;
+        Self::write_global_config(&global_path, &mut app_data)?;
         
-        let instance_config = config.to_instance();
+        let instance_config = app_data.instance_config();
         let instance_path = Self::get_instance_config_path(app_handle, &instance_id)?;
         instance_config.save_to_file(&instance_path)?;
-        
-        app_data.app_config = config;
 
         Ok(())
     }
 
+    pub fn validate_config(config: &AppConfig) -> Vec<FieldError> {
+        let mut errors = validation::validate_global(&config.to_global());
+        errors.extend(validation::validate_instance(&config.to_instance()));
+        errors
+    }
+
     pub fn get_config(app_handle: &tauri::AppHandle) -> Result<AppConfig, String> {
         let storage = app_handle.state::<Storage>();
         let app_data = storage.app_data.lock().map_err(|e| e.to_string())?;
@@ -279,7 +668,7 @@ impl ConfigManager {
             app_data.app_config.opened_files = Some(opened_files);
             
             let instance_id = app_data.instance_id.clone().unwrap_or_else(|| "main".to_string());
-            let instance_config = app_data.app_config.to_instance();
+            let instance_config = app_data.instance_config();
             drop(app_data);
             
             let instance_path = Self::get_instance_config_path(app_handle, &instance_id)?;
@@ -293,33 +682,72 @@ impl ConfigManager {
         Ok(())
     }
 
+    fn save_recent_entries(app_handle: &tauri::AppHandle, mut app_data: std::sync::MutexGuard<AppData>) -> Result<(), String> {
+        app_data.sync_recent_files();
+        
+        let instance_id = app_data.instance_id.clone().unwrap_or_else(|| "main".to_string());
+        let instance_config = app_data.instance_config();
+        drop(app_data);
+        
+        let instance_path = Self::get_instance_config_path(app_handle, &instance_id)?;
+        instance_config.save_to_file(&instance_path)
+    }
+
     pub fn add_to_recent_files(app_handle: &tauri::AppHandle, path: String) -> Result<(), String> {
         let storage = app_handle.state::<Storage>();
         let mut app_data = storage.app_data.lock().map_err(|e| e.to_string())?;
         
-        let mut recent_files = app_data.app_config.recent_files.take().unwrap_or_default();
-        
-        recent_files.retain(|p| p != &path);
-        recent_files.insert(0, path);
+        recent::touch(&mut app_data.recent_entries, &path, chrono::Local::now().timestamp_millis());
+        Self::save_recent_entries(app_handle, app_data)
+    }
+
+    pub fn remove_from_recent_files(app_handle: &tauri::AppHandle, path: &str) -> Result<(), String> {
+        let storage = app_handle.state::<Storage>();
+        let mut app_data = storage.app_data.lock().map_err(|e| e.to_string())?;
         
-        if recent_files.len() > 100 {
-            recent_files.truncate(100);
+        if app_data.recent_entries.remove(path).is_none() {
+            return Ok(());
         }
+        Self::save_recent_entries(app_handle, app_data)
+    }
+
+    pub fn pin_recent_file(app_handle: &tauri::AppHandle, path: String, pinned: bool) -> Result<(), String> {
+        let storage = app_handle.state::<Storage>();
+        let mut app_data = storage.app_data.lock().map_err(|e| e.to_string())?;
         
-        app_data.app_config.recent_files = Some(recent_files);
-        
-        let instance_id = app_data.instance_id.clone().unwrap_or_else(|| "main".to_string());
-        let instance_config = app_data.app_config.to_instance();
-        drop(app_data);
-        
-        let instance_path = Self::get_instance_config_path(app_handle, &instance_id)?;
-        instance_config.save_to_file(&instance_path)?;
+        let now = chrono::Local::now().timestamp_millis();
+        let entry = app_data.recent_entries.entry(path).or_insert_with(|| RecentEntry {
+            open_count: 0,
+            last_used: now,
+            pinned: false,
+        });
+        entry.pinned = pinned;
+        Self::save_recent_entries(app_handle, app_data)
+    }
+
+    // Clearing keeps pinned entries; unpin them first to remove them.
+    pub fn clear_recent_files(app_handle: &tauri::AppHandle) -> Result<(), String> {
+        let storage = app_handle.state::<Storage>();
+        let mut app_data = storage.app_data.lock().map_err(|e| e.to_string())?;
         
+        app_data.recent_entries.retain(|_, entry| entry.pinned);
+        Self::save_recent_entries(app_handle, app_data)
+    }
+
+    pub fn get_recent_files(app_handle: &tauri::AppHandle, filter: Option<&str>, limit: Option<usize>) -> Result<Vec<RecentFile>, String> {
         let storage = app_handle.state::<Storage>();
         let mut app_data = storage.app_data.lock().map_err(|e| e.to_string())?;
-        app_data.app_config.recent_files = instance_config.recent_files;
         
-        Ok(())
+        let pruned = recent::prune_missing(&mut app_data.recent_entries);
+        let mut files = recent::ranked(&app_data.recent_entries, filter, chrono::Local::now().timestamp_millis());
+        if let Some(limit) = limit {
+            files.truncate(limit);
+        }
+        
+        if pruned {
+            Self::save_recent_entries(app_handle, app_data)?;
+        }
+        Ok(files)
     }
 
     pub fn remove_from_opened_files(app_handle: &tauri::AppHandle, path: &str) -> Result<(), String> {
@@ -331,7 +759,7 @@ impl ConfigManager {
             app_data.app_config.opened_files = Some(opened_files);
             
             let instance_id = app_data.instance_id.clone().unwrap_or_else(|| "main".to_string());
-            let instance_config = app_data.app_config.to_instance();
+            let instance_config = app_data.instance_config();
             drop(app_data);
             
             let instance_path = Self::get_instance_config_path(app_handle, &instance_id)?;
@@ -352,7 +780,7 @@ impl ConfigManager {
         app_data.app_config.opened_files = Some(vec![]);
         
         let instance_id = app_data.instance_id.clone().unwrap_or_else(|| "main".to_string());
-        let instance_config = app_data.app_config.to_instance();
+        let instance_config = app_data.instance_config();
         drop(app_data);
         
         let instance_path = Self::get_instance_config_path(app_handle, &instance_id)?;
@@ -365,6 +793,428 @@ impl ConfigManager {
         Ok(())
     }
     
+    pub fn resolve_editor_settings(app_handle: &tauri::AppHandle, path: &str, language: Option<&str>) -> Result<EditorSettings, String> {
+        let storage = app_handle.state::<Storage>();
+        let mut app_data = storage.app_data.lock().map_err(|e| e.to_string())?;
+        let AppData { app_config: config, glob_cache, .. } = &mut *app_data;
+        
+        Ok(languages::resolve(
+            config.languages.as_ref().unwrap_or(&HashMap::new()),
+            glob_cache,
+            path,
+            language,
+            config.word_wrap.unwrap_or(false),
+            config.font_size.unwrap_or(14),
+        ))
+    }
+
+    pub fn take_config_recoveries(app_handle: &tauri::AppHandle) -> Result<Vec<ConfigRecovery>, String> {
+        let storage = app_handle.state::<Storage>();
+        let mut app_data = storage.app_data.lock().map_err(|e| e.to_string())?;
+        Ok(std::mem::take(&mut app_data.recoveries))
+    }
+
+    pub fn take_config_validations(app_handle: &tauri::AppHandle) -> Result<Vec<ConfigValidation>, String> {
+        let storage = app_handle.state::<Storage>();
+        let mut app_data = storage.app_data.lock().map_err(|e| e.to_string())?;
+        Ok(std::mem::take(&mut app_data.validations))
+    }
+
+    pub fn get_watched_folders(app_handle: &tauri::AppHandle) -> Result<Vec<WatchedFolder>, String> {
+        let storage = app_handle.state::<Storage>();
+        let app_data = storage.app_data.lock().map_err(|e| e.to_string())?;
+        Ok(app_data.watched_folders.clone())
+    }
+
+    pub fn set_watched_folders(app_handle: &tauri::AppHandle, folders: Vec<WatchedFolder>) -> Result<(), String> {
+        let storage = app_handle.state::<Storage>();
+        let mut app_data = storage.app_data.lock().map_err(|e| e.to_string())?;
+        
+        app_data.watched_folders = folders;
+        
+        let instance_id = app_data.instance_id.clone().unwrap_or_else(|| "main".to_string());
+        let instance_config = app_data.instance_config();
+        drop(app_data);
+        
+        let instance_path = Self::get_instance_config_path(app_handle, &instance_id)?;
+        instance_config.save_to_file(&instance_path)
+    }
+    
+    pub fn get_file_view_state(app_handle: &tauri::AppHandle, path: &str) -> Result<Option<FileViewState>, String> {
+        let storage = app_handle.state::<Storage>();
+        let app_data = storage.app_data.lock().map_err(|e| e.to_string())?;
+        Ok(app_data.file_states.get(&view_state::canonical_key(path)).cloned())
+    }
+
+    pub fn save_file_view_state(app_handle: &tauri::AppHandle, path: &str, mut state: FileViewState) -> Result<(), String> {
+        let storage = app_handle.state::<Storage>();
+        let mut app_data = storage.app_data.lock().map_err(|e| e.to_string())?;
+        
+        state.last_used = Some(chrono::Local::now().timestamp_millis());
+        app_data.file_states.insert(view_state::canonical_key(path), state);
+        view_state::prune(&mut app_data.file_states);
+        
+        let instance_id = app_data.instance_id.clone().unwrap_or_else(|| "main".to_string());
+        let instance_config = app_data.instance_config();
+        drop(app_data);
+        
+        let instance_path = Self::get_instance_config_path(app_handle, &instance_id)?;
+        instance_config.save_to_file(&instance_path)
+    }
+
+    pub fn forget_file_view_state(app_handle: &tauri::AppHandle, path: &str) -> Result<(), String> {
+        let storage = app_handle.state::<Storage>();
+        let mut app_data = storage.app_data.lock().map_err(|e| e.to_string())?;
+        
+        if app_data.file_states.remove(&view_state::canonical_key(path)).is_none() {
+            return Ok(());
+        }
+        
+        let instance_id = app_data.instance_id.clone().unwrap_or_else(|| "main".to_string());
+        let instance_config = app_data.instance_config();
+        drop(app_data);
+        
+        let instance_path = Self::get_instance_config_path(app_handle, &instance_id)?;
+        instance_config.save_to_file(&instance_path)
+    }
+    
+    pub fn mark_instance_closed(app_handle: &tauri::AppHandle) -> Result<(), String> {
+        let storage = app_handle.state::<Storage>();
+        let mut app_data = storage.app_data.lock().map_err(|e| e.to_string())?;
+        
+        if app_data.lifecycle.started_at.is_none() {
+            return Ok(());
+        }
+        app_data.lifecycle.closed_at = Some(chrono::Local::now().timestamp_millis());
+        
+        let instance_id = app_data.instance_id.clone().unwrap_or_else(|| "main".to_string());
+        let instance_path = Self::get_instance_config_path(app_handle, &instance_id)?;
+        app_data.instance_config().save_to_file(&instance_path)?;
+        app_data.instance_lock = None;
+        Ok(())
+    }
+
+    pub fn autosave_settings(app_handle: &tauri::AppHandle) -> Result<(crate::autosave::AutosaveMode, u64), String> {
+        let storage = app_handle.state::<Storage>();
+        let app_data = storage.app_data.lock().map_err(|e| e.to_string())?;
+        let config = &app_data.app_config;
+        
+        Ok((
+            crate::autosave::AutosaveMode::parse(config.autosave.as_deref()),
+            config.autosave_delay_ms.unwrap_or(1000),
+        ))
+    }
+
+    // Identifies this run of this instance: its id and when it started.
+    pub fn current_run(app_handle: &tauri::AppHandle) -> Result<(String, i64), String> {
+        let storage = app_handle.state::<Storage>();
+        let app_data = storage.app_data.lock().map_err(|e| e.to_string())?;
+        
+        let started_at = app_data.lifecycle.started_at
+            .ok_or_else(|| "Config has not been loaded yet".to_string())?;
+        Ok((app_data.instance_id.clone().unwrap_or_else(|| "main".to_string()), started_at))
+    }
+
+    pub fn is_instance_running(app_handle: &tauri::AppHandle, instance_id: &str) -> bool {
+        Self::get_instances_dir(app_handle)
+            .map(|instances_dir| instances::is_running(&instances_dir, instance_id))
+            .unwrap_or(false)
+    }
+
+    fn current_instance_id(app_handle: &tauri::AppHandle) -> Result<String, String> {
+        let storage = app_handle.state::<Storage>();
+        let app_data = storage.app_data.lock().map_err(|e| e.to_string())?;
+        Ok(app_data.instance_id.clone().unwrap_or_else(|| "main".to_string()))
+    }
+
+    pub fn list_instances(app_handle: &tauri::AppHandle) -> Result<Vec<InstanceInfo>, String> {
+        let current_id = Self::current_instance_id(app_handle)?;
+        let instances_dir = Self::get_instances_dir(app_handle)?;
+        
+        let mut instances = instances::list(&instances_dir);
+        instances.retain(|info| info.id != current_id);
+        Ok(instances)
+    }
+
+    fn removable_instance(app_handle: &tauri::AppHandle, instance_id: &str) -> Result<InstanceInfo, String> {
+        let info = Self::list_instances(app_handle)?
+            .into_iter()
+            .find(|info| info.id == instance_id)
+            .ok_or_else(|| format!("Unknown instance: {}", instance_id))?;
+        
+        if info.status == instances::InstanceStatus::Running {
+            return Err(format!("Instance {} is still running", instance_id));
+        }
+        Ok(info)
+    }
+
+    pub fn delete_instance(app_handle: &tauri::AppHandle, instance_id: &str) -> Result<(), String> {
+        if instance_id == instances::MAIN_INSTANCE_ID {
+            return Err("The main instance cannot be deleted".to_string());
+        }
+        Self::removable_instance(app_handle, instance_id)?;
+        
+        let instances_dir = Self::get_instances_dir(app_handle)?;
+        instances::delete_files(&instances_dir, instance_id)
+    }
+
+    // Brings another instance's opened files, recent files and view states
+    // into this one, then deletes it. Its watched folders are not carried
+    // over.
+    pub fn merge_instance(app_handle: &tauri::AppHandle, instance_id: &str) -> Result<(), String> {
+        Self::removable_instance(app_handle, instance_id)?;
+        
+        let other_path = Self::get_instance_config_path(app_handle, instance_id)?;
+        let mut other = InstanceConfig::from_file(&other_path)?;
+        let other_recent = match other.recent_entries.take() {
+            Some(entries) => entries,
+            None => recent::from_list(other.recent_files.as_deref().unwrap_or_default(), 0),
+        };
+        
+        let storage = app_handle.state::<Storage>();
+        let mut app_data = storage.app_data.lock().map_err(|e| e.to_string())?;
+        
+        let mut opened_files = app_data.app_config.opened_files.take().unwrap_or_default();
+        for path in other.opened_files.take().unwrap_or_default() {
+            if !opened_files.contains(&path) {
+                opened_files.push(path);
+            }
+        }
+        app_data.app_config.opened_files = Some(opened_files);
+        
+        recent::merge(&mut app_data.recent_entries, other_recent);
+        for (key, state) in other.file_states.take().unwrap_or_default() {
+            let newer = app_data.file_states.get(&key)
+                .is_none_or(|current| current.last_used < state.last_used);
+            if newer {
+                app_data.file_states.insert(key, state);
+            }
+        }
+        view_state::prune(&mut app_data.file_states);
+        app_data.sync_recent_files();
+        
+        let instance_id_current = app_data.instance_id.clone().unwrap_or_else(|| "main".to_string());
+        let instance_config = app_data.instance_config();
+        drop(app_data);
+        
+        let instance_path = Self::get_instance_config_path(app_handle, &instance_id_current)?;
+        instance_config.save_to_file(&instance_path)?;
+        
+        // The main instance file is never deleted; emptying it keeps its files
+        // from being reopened twice.
+        if instance_id == instances::MAIN_INSTANCE_ID {
+            other.opened_files = Some(vec![]);
+            other.recent_files = Some(vec![]);
+            other.recent_entries = Some(HashMap::new());
+            other.file_states = Some(HashMap::new());
+            other.save_to_file(&other_path)?;
+        } else {
+            let instances_dir = Self::get_instances_dir(app_handle)?;
+            instances::delete_files(&instances_dir, instance_id)?;
+        }
+        
+        let _ = app_handle.emit("files-updated", ());
+        Ok(())
+    }
+
+    pub fn cleanup_instances(app_handle: &tauri::AppHandle) -> Result<usize, String> {
+        let retention_days = {
+            let storage = app_handle.state::<Storage>();
+            let app_data = storage.app_data.lock().map_err(|e| e.to_string())?;
+            app_data.app_config.instance_retention_days
+                .unwrap_or(instances::DEFAULT_INSTANCE_RETENTION_DAYS)
+        };
+        
+        let now_secs = chrono::Local::now().timestamp().max(0) as u64;
+        let instances_dir = Self::get_instances_dir(app_handle)?;
+        let mut removed = 0;
+        
+        for info in Self::list_instances(app_handle)? {
+            if instances::is_expired(&info, now_secs, retention_days) {
+                instances::delete_files(&instances_dir, &info.id)?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+    
+    // View states come from the ones this instance has recorded, so the
+    // frontend should save the active tab's state before saving a session.
+    pub fn save_session(app_handle: &tauri::AppHandle, name: &str, files: Vec<String>, active_file: Option<String>) -> Result<(), String> {
+        sessions::validate_name(name)?;
+        
+        let session = {
+            let storage = app_handle.state::<Storage>();
+            let app_data = storage.app_data.lock().map_err(|e| e.to_string())?;
+            
+            Session {
+                name: name.to_string(),
+                files: files.into_iter()
+                    .map(|path| SessionFile {
+                        view_state: app_data.file_states.get(&view_state::canonical_key(&path)).cloned(),
+                        path,
+                    })
+                    .collect(),
+                active_file,
+                saved_at: chrono::Local::now().timestamp_millis(),
+            }
+        };
+        
+        let sessions_dir = Self::get_sessions_dir(app_handle)?;
+        sessions::save(&sessions_dir, &session)
+    }
+
+    pub fn list_sessions(app_handle: &tauri::AppHandle) -> Result<Vec<SessionSummary>, String> {
+        let sessions_dir = Self::get_sessions_dir(app_handle)?;
+        Ok(sessions::list(&sessions_dir))
+    }
+
+    // Hands the session's view states to this instance so they are restored
+    // as its files are opened. Opening and closing tabs is left to the
+    // frontend, which owns the tabs and their unsaved changes.
+    pub fn switch_session(app_handle: &tauri::AppHandle, name: &str) -> Result<Session, String> {
+        let sessions_dir = Self::get_sessions_dir(app_handle)?;
+        let session = sessions::load(&sessions_dir, name)?;
+        
+        let storage = app_handle.state::<Storage>();
+        let mut app_data = storage.app_data.lock().map_err(|e| e.to_string())?;
+        
+        for file in &session.files {
+            if let Some(state) = &file.view_state {
+                app_data.file_states.insert(view_state::canonical_key(&file.path), state.clone());
+            }
+        }
+        view_state::prune(&mut app_data.file_states);
+        
+        let instance_id = app_data.instance_id.clone().unwrap_or_else(|| "main".to_string());
+        let instance_config = app_data.instance_config();
+        drop(app_data);
+        
+        let instance_path = Self::get_instance_config_path(app_handle, &instance_id)?;
+        instance_config.save_to_file(&instance_path)?;
+        Ok(session)
+    }
+
+    pub fn rename_session(app_handle: &tauri::AppHandle, name: &str, new_name: &str) -> Result<(), String> {
+        let sessions_dir = Self::get_sessions_dir(app_handle)?;
+        sessions::rename(&sessions_dir, name, new_name)
+    }
+
+    pub fn delete_session(app_handle: &tauri::AppHandle, name: &str) -> Result<(), String> {
+        let sessions_dir = Self::get_sessions_dir(app_handle)?;
+        sessions::delete(&sessions_dir, name)
+    }
+    
+    pub fn list_profiles(app_handle: &tauri::AppHandle) -> Result<Vec<ProfileInfo>, String> {
+        let notepad_dir = Self::get_notepad_md_dir(app_handle)?;
+        let active = Self::active_profile(app_handle)?;
+        Ok(profiles::list(&notepad_dir, &active))
+    }
+
+    pub fn create_profile(app_handle: &tauri::AppHandle, name: &str) -> Result<(), String> {
+        let notepad_dir = Self::get_notepad_md_dir(app_handle)?;
+        profiles::create(&notepad_dir, name)
+    }
+
+    pub fn clone_profile(app_handle: &tauri::AppHandle, source: &str, name: &str) -> Result<(), String> {
+        let notepad_dir = Self::get_notepad_md_dir(app_handle)?;
+        profiles::clone(&notepad_dir, source, name)
+    }
+
+    // Makes `name` the profile of this instance and the one later starts
+    // use, then loads its settings and tells every window.
+    pub fn switch_profile(app_handle: &tauri::AppHandle, name: &str) -> Result<AppConfig, String> {
+        let notepad_dir = Self::get_notepad_md_dir(app_handle)?;
+        if !profiles::exists(&notepad_dir, name) {
+            return Err(format!("Profile not found: {}", name));
+        }
+        
+        let profile_state = app_handle.state::<ProfileState>();
+        profiles::set_active(&profile_state, &notepad_dir, name)?;
+        
+        let global_path = Self::get_global_config_path(app_handle)?;
+        let storage = app_handle.state::<Storage>();
+        let mut app_data = storage.app_data.lock().map_err(|e| e.to_string())?;
+        
+        let global_config = Self::load_global_config(app_handle, &mut app_data, &global_path)?;
+        app_data.global_baseline = global_config.to_map()?;
+        let global_config = Self::sanitize_global_config(app_handle, &mut app_data, &global_path, global_config);
+        app_data.app_config = AppConfig::from_global_and_instance(global_config, app_data.instance_config());
+        let config = app_data.app_config.clone();
+        drop(app_data);
+        
+        Self::watch_global_config(app_handle)?;
+        app_handle.emit("config-changed", config.clone()).map_err(|e| e.to_string())?;
+        Ok(config)
+    }
+
+    pub fn delete_profile(app_handle: &tauri::AppHandle, name: &str) -> Result<(), String> {
+        if Self::active_profile(app_handle)? == name {
+            return Err("The active profile cannot be deleted".to_string());
+        }
+        let notepad_dir = Self::get_notepad_md_dir(app_handle)?;
+        profiles::delete(&notepad_dir, name)
+    }
+
+    pub fn export_profile(app_handle: &tauri::AppHandle, name: &str, path: &str) -> Result<(), String> {
+        let notepad_dir = Self::get_notepad_md_dir(app_handle)?;
+        profiles::export(&notepad_dir, name, Path::new(path))
+    }
+
+    pub fn import_profile(app_handle: &tauri::AppHandle, path: &str, name: Option<&str>) -> Result<String, String> {
+        let notepad_dir = Self::get_notepad_md_dir(app_handle)?;
+        profiles::import(&notepad_dir, Path::new(path), name)
+    }
+    
+    pub fn export_settings_bundle(app_handle: &tauri::AppHandle, path: &str, options: &BundleOptions) -> Result<BundleContents, String> {
+        let notepad_dir = Self::get_notepad_md_dir(app_handle)?;
+        let profile_dir = Self::get_profile_dir(app_handle)?;
+        bundle::export(&notepad_dir, &profile_dir, options, Path::new(path))
+    }
+
+    pub fn preview_settings_bundle(app_handle: &tauri::AppHandle, path: &str, mode: ImportMode) -> Result<BundlePreview, String> {
+        let notepad_dir = Self::get_notepad_md_dir(app_handle)?;
+        let profile_dir = Self::get_profile_dir(app_handle)?;
+        let active_profile = Self::active_profile(app_handle)?;
+        bundle::preview(&notepad_dir, &profile_dir, &active_profile, Path::new(path), mode)
+    }
+
+    // The global config watcher picks up a changed global config and tells
+    // the windows.
+    pub fn import_settings_bundle(app_handle: &tauri::AppHandle, path: &str, mode: ImportMode) -> Result<Vec<BundleChange>, String> {
+        let notepad_dir = Self::get_notepad_md_dir(app_handle)?;
+        let profile_dir = Self::get_profile_dir(app_handle)?;
+        let active_profile = Self::active_profile(app_handle)?;
+        bundle::import(&notepad_dir, &profile_dir, &active_profile, Path::new(path), mode)
+    }
+    
+    // Effective bindings for the active profile, with what is wrong in the
+    // config and in keybindings.json.
+    pub fn get_keybindings(app_handle: &tauri::AppHandle) -> Result<KeybindingReport, String> {
+        let config_bindings = {
+            let storage = app_handle.state::<Storage>();
+            let app_data = storage.app_data.lock().map_err(|e| e.to_string())?;
+            app_data.app_config.keybindings.clone()
+        };
+        
+        let profile_dir = Self::get_profile_dir(app_handle)?;
+        let (file_bindings, file_error) = match keybindings::read_file(&profile_dir) {
+            Ok(bindings) => (bindings, None),
+            Err(error) => (None, Some(error)),
+        };
+        
+        let mut report = keybindings::resolve(config_bindings.as_ref(), file_bindings.as_ref(), keybindings::is_macos());
+        if let Some(message) = file_error {
+            report.errors.push(FieldError { field: keybindings::KEYBINDINGS_FILE.to_string(), message });
+        }
+        Ok(report)
+    }
+
+    // Checks bindings the user is about to save, on top of the defaults.
+    pub fn validate_keybindings(bindings: &HashMap<String, String>) -> KeybindingReport {
+        keybindings::resolve(Some(bindings), None, keybindings::is_macos())
+    }
+    
     pub fn set_instance_id(app_handle: &tauri::AppHandle, instance_id: String) -> Result<(), String> {
         let storage = app_handle.state::<Storage>();
         let mut app_data = storage.app_data.lock().map_err(|e| e.to_string())?;
@@ -373,6 +1223,27 @@ impl ConfigManager {
     }
 }
 
+#[derive(Serialize)]
+pub struct ConfigLocationInfo {
+    dir: String,
+    source: location::ConfigSource,
+    webview_dir: Option<String>,
+}
+
+#[tauri::command]
+pub fn get_config_location(app_handle: tauri::AppHandle) -> Result<ConfigLocationInfo, String> {
+    let dir = ConfigManager::get_notepad_md_dir(&app_handle)?;
+    let location = app_handle.state::<ConfigLocation>();
+    
+    Ok(ConfigLocationInfo {
+        dir: dir.to_string_lossy().into_owned(),
+        source: location.source.clone(),
+        webview_dir: location.dir.as_deref()
+            .and_then(location::webview_data_dir)
+            .map(|dir| dir.to_string_lossy().into_owned()),
+    })
+}
+
 #[tauri::command]
 pub fn load_config(app_handle: tauri::AppHandle) -> Result<(), String> {
     ConfigManager::load_config(&app_handle)
@@ -383,7 +1254,182 @@ pub fn save_config(app_handle: tauri::AppHandle, config: AppConfig) -> Result<()
     ConfigManager::save_config(&app_handle, config)
 }
 
+#[tauri::command]
+pub fn validate_config(config: AppConfig) -> Vec<FieldError> {
+    ConfigManager::validate_config(&config)
+}
+
 #[tauri::command]
 pub fn get_config(app_handle: tauri::AppHandle) -> Result<AppConfig, String> {
     ConfigManager::get_config(&app_handle)
 }
+
+#[tauri::command]
+pub fn add_to_recent_files(app_handle: tauri::AppHandle, path: String) -> Result<(), String> {
+    ConfigManager::add_to_recent_files(&app_handle, path)
+}
+
+#[tauri::command]
+pub fn remove_from_recent_files(app_handle: tauri::AppHandle, path: String) -> Result<(), String> {
+    ConfigManager::remove_from_recent_files(&app_handle, &path)
+}
+
+#[tauri::command]
+pub fn pin_recent_file(app_handle: tauri::AppHandle, path: String, pinned: bool) -> Result<(), String> {
+    ConfigManager::pin_recent_file(&app_handle, path, pinned)
+}
+
+#[tauri::command]
+pub fn clear_recent_files(app_handle: tauri::AppHandle) -> Result<(), String> {
+    ConfigManager::clear_recent_files(&app_handle)
+}
+
+#[tauri::command]
+pub fn get_recent_files(app_handle: tauri::AppHandle, filter: Option<String>, limit: Option<usize>) -> Result<Vec<RecentFile>, String> {
+    ConfigManager::get_recent_files(&app_handle, filter.as_deref(), limit)
+}
+
+#[tauri::command]
+pub fn remove_from_opened_files(app_handle: tauri::AppHandle, path: String) -> Result<(), String> {
+    ConfigManager::remove_from_opened_files(&app_handle, &path)
+}
+
+#[tauri::command]
+pub fn clear_opened_files(app_handle: tauri::AppHandle) -> Result<(), String> {
+    ConfigManager::clear_opened_files(&app_handle)
+}
+
+#[tauri::command]
+pub fn list_instances(app_handle: tauri::AppHandle) -> Result<Vec<InstanceInfo>, String> {
+    ConfigManager::list_instances(&app_handle)
+}
+
+#[tauri::command]
+pub fn merge_instance(app_handle: tauri::AppHandle, instance_id: String) -> Result<(), String> {
+    ConfigManager::merge_instance(&app_handle, &instance_id)
+}
+
+#[tauri::command]
+pub fn delete_instance(app_handle: tauri::AppHandle, instance_id: String) -> Result<(), String> {
+    ConfigManager::delete_instance(&app_handle, &instance_id)
+}
+
+#[tauri::command]
+pub fn cleanup_instances(app_handle: tauri::AppHandle) -> Result<usize, String> {
+    ConfigManager::cleanup_instances(&app_handle)
+}
+
+#[tauri::command]
+pub fn save_session(app_handle: tauri::AppHandle, name: String, files: Vec<String>, active_file: Option<String>) -> Result<(), String> {
+    ConfigManager::save_session(&app_handle, &name, files, active_file)
+}
+
+#[tauri::command]
+pub fn list_sessions(app_handle: tauri::AppHandle) -> Result<Vec<SessionSummary>, String> {
+    ConfigManager::list_sessions(&app_handle)
+}
+
+#[tauri::command]
+pub fn switch_session(app_handle: tauri::AppHandle, name: String) -> Result<Session, String> {
+    ConfigManager::switch_session(&app_handle, &name)
+}
+
+#[tauri::command]
+pub fn rename_session(app_handle: tauri::AppHandle, name: String, new_name: String) -> Result<(), String> {
+    ConfigManager::rename_session(&app_handle, &name, &new_name)
+}
+
+#[tauri::command]
+pub fn delete_session(app_handle: tauri::AppHandle, name: String) -> Result<(), String> {
+    ConfigManager::delete_session(&app_handle, &name)
+}
+
+#[tauri::command]
+pub fn list_profiles(app_handle: tauri::AppHandle) -> Result<Vec<ProfileInfo>, String> {
+    ConfigManager::list_profiles(&app_handle)
+}
+
+#[tauri::command]
+pub fn create_profile(app_handle: tauri::AppHandle, name: String) -> Result<(), String> {
+    ConfigManager::create_profile(&app_handle, &name)
+}
+
+#[tauri::command]
+pub fn clone_profile(app_handle: tauri::AppHandle, source: String, name: String) -> Result<(), String> {
+    ConfigManager::clone_profile(&app_handle, &source, &name)
+}
+
+#[tauri::command]
+pub fn switch_profile(app_handle: tauri::AppHandle, name: String) -> Result<AppConfig, String> {
+    ConfigManager::switch_profile(&app_handle, &name)
+}
+
+#[tauri::command]
+pub fn delete_profile(app_handle: tauri::AppHandle, name: String) -> Result<(), String> {
+    ConfigManager::delete_profile(&app_handle, &name)
+}
+
+#[tauri::command]
+pub fn export_profile(app_handle: tauri::AppHandle, name: String, path: String) -> Result<(), String> {
+    ConfigManager::export_profile(&app_handle, &name, &path)
+}
+
+#[tauri::command]
+pub fn import_profile(app_handle: tauri::AppHandle, path: String, name: Option<String>) -> Result<String, String> {
+    ConfigManager::import_profile(&app_handle, &path, name.as_deref())
+}
+
+#[tauri::command]
+pub fn export_settings_bundle(app_handle: tauri::AppHandle, path: String, options: BundleOptions) -> Result<BundleContents, String> {
+    ConfigManager::export_settings_bundle(&app_handle, &path, &options)
+}
+
+#[tauri::command]
+pub fn preview_settings_bundle(app_handle: tauri::AppHandle, path: String, mode: ImportMode) -> Result<BundlePreview, String> {
+    ConfigManager::preview_settings_bundle(&app_handle, &path, mode)
+}
+
+#[tauri::command]
+pub fn import_settings_bundle(app_handle: tauri::AppHandle, path: String, mode: ImportMode) -> Result<Vec<BundleChange>, String> {
+    ConfigManager::import_settings_bundle(&app_handle, &path, mode)
+}
+
+#[tauri::command]
+pub fn get_keybindings(app_handle: tauri::AppHandle) -> Result<KeybindingReport, String> {
+    ConfigManager::get_keybindings(&app_handle)
+}
+
+#[tauri::command]
+pub fn validate_keybindings(bindings: HashMap<String, String>) -> KeybindingReport {
+    ConfigManager::validate_keybindings(&bindings)
+}
+
+#[tauri::command]
+pub fn take_config_recoveries(app_handle: tauri::AppHandle) -> Result<Vec<ConfigRecovery>, String> {
+    ConfigManager::take_config_recoveries(&app_handle)
+}
+
+#[tauri::command]
+pub fn take_config_validations(app_handle: tauri::AppHandle) -> Result<Vec<ConfigValidation>, String> {
+    ConfigManager::take_config_validations(&app_handle)
+}
+
+#[tauri::command]
+pub fn resolve_editor_settings(app_handle: tauri::AppHandle, path: String, language: Option<String>) -> Result<EditorSettings, String> {
+    ConfigManager::resolve_editor_settings(&app_handle, &path, language.as_deref())
+}
+
+#[tauri::command]
+pub fn get_file_view_state(app_handle: tauri::AppHandle, path: String) -> Result<Option<FileViewState>, String> {
+    ConfigManager::get_file_view_state(&app_handle, &path)
+}
+
+#[tauri::command]
+pub fn save_file_view_state(app_handle: tauri::AppHandle, path: String, state: FileViewState) -> Result<(), String> {
+    ConfigManager::save_file_view_state(&app_handle, &path, state)
+}
+
+#[tauri::command]
+pub fn forget_file_view_state(app_handle: tauri::AppHandle, path: String) -> Result<(), String> {
+    ConfigManager::forget_file_view_state(&app_handle, &path)
+}
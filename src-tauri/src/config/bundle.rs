@@ -0,0 +1,484 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use super::profiles::{self, PROFILE_FILES};
+use super::keybindings::KEYBINDINGS_FILE;
+use super::sync::GlobalConfigLock;
+use super::{migration, validation, GLOBAL_CONFIG_FILE};
+
+pub const MONACO_THEMES_DIR: &str = "monaco-editor";
+pub const SNIPPETS_DIR: &str = "snippets";
+
+const MANIFEST_FILE: &str = "manifest.json";
+const BUNDLE_FORMAT_VERSION: u32 = 1;
+const PROFILES_PREFIX: &str = "profiles/";
+
+// Every file in a bundle is a small JSON document; anything bigger is not
+// one of ours.
+const MAX_ENTRY_SIZE: u64 = 10 * 1024 * 1024;
+
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct BundleOptions {
+    #[serde(default)]
+    pub global: bool,
+    #[serde(default)]
+    pub profiles: Vec<String>,
+    #[serde(default)]
+    pub themes: bool,
+    #[serde(default)]
+    pub snippets: bool,
+    #[serde(default)]
+    pub keybindings: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct BundleContents {
+    pub global: bool,
+    pub profiles: Vec<String>,
+    pub themes: Vec<String>,
+    pub snippets: Vec<String>,
+    pub keybindings: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct Manifest {
+    format_version: u32,
+    created_at: i64,
+    contents: BundleContents,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportMode {
+    // Adds and updates files; config files are merged key by key.
+    Merge,
+    // Makes each part the bundle contains identical to it, removing themes,
+    // snippets and profiles the bundle doesn't have.
+    Replace,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Added,
+    Modified,
+    Removed,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct BundleChange {
+    pub path: String,
+    pub change: ChangeKind,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct BundlePreview {
+    pub contents: BundleContents,
+    pub created_at: i64,
+    pub changes: Vec<BundleChange>,
+}
+
+// What a bundle file is, which decides where it goes, how it is checked and
+// how it is merged.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum EntryKind {
+    Global,
+    Keybindings,
+    ProfileFile,
+    Theme,
+    Snippet,
+}
+
+fn is_json_name(name: &str) -> bool {
+    name.strip_suffix(".json").is_some_and(super::is_safe_file_name)
+}
+
+// Bundle paths are checked against the layout we write, so an entry can never
+// land outside the FirowNotepad directory.
+fn entry_kind(path: &str) -> Option<EntryKind> {
+    if path == GLOBAL_CONFIG_FILE {
+        return Some(EntryKind::Global);
+    }
+    if path == KEYBINDINGS_FILE {
+        return Some(EntryKind::Keybindings);
+    }
+
+    let (dir, name) = path.split_once('/')?;
+    match dir {
+        MONACO_THEMES_DIR if is_json_name(name) => Some(EntryKind::Theme),
+        SNIPPETS_DIR if is_json_name(name) => Some(EntryKind::Snippet),
+        "profiles" => {
+            let (profile, file) = name.split_once('/')?;
+            let valid = profiles::validate_name(profile).is_ok()
+                && profile != profiles::DEFAULT_PROFILE
+                && PROFILE_FILES.contains(&file);
+            valid.then_some(EntryKind::ProfileFile)
+        }
+        _ => None,
+    }
+}
+
+fn validate_entry(path: &str, content: &str) -> Result<Value, String> {
+    let kind = entry_kind(path).ok_or_else(|| format!("Unexpected file in bundle: {}", path))?;
+    let value: Value = serde_json::from_str(content).map_err(|e| format!("{}: {}", path, e))?;
+
+    let is_global = kind == EntryKind::Global || path.ends_with(&format!("/{}", GLOBAL_CONFIG_FILE));
+    let is_keybindings = kind == EntryKind::Keybindings || path.ends_with(&format!("/{}", KEYBINDINGS_FILE));
+    if is_global {
+        validation::check_global_value(&value).map_err(|e| format!("{}: {}", path, e))?;
+    } else if is_keybindings {
+        validation::check_keybindings_value(&value).map_err(|e| format!("{}: {}", path, e))?;
+    } else if kind == EntryKind::Theme {
+        crate::themes::validate_monaco_theme(&value).map_err(|e| format!("{}: {}", path, e))?;
+    }
+    Ok(value)
+}
+
+fn json_files(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                .filter(|name| is_json_name(name))
+                .collect()
+        })
+        .unwrap_or_default();
+    names.sort();
+    names
+}
+
+// Bundle path to the file it stands for. `profile_dir` is the directory of
+// the active profile, which the bundle's global config and keybindings
+// belong to.
+fn target_path(notepad_dir: &Path, profile_dir: &Path, path: &str) -> PathBuf {
+    if path == GLOBAL_CONFIG_FILE || path == KEYBINDINGS_FILE {
+        profile_dir.join(path)
+    } else {
+        notepad_dir.join(path)
+    }
+}
+
+pub fn export(notepad_dir: &Path, profile_dir: &Path, options: &BundleOptions, path: &Path) -> Result<BundleContents, String> {
+    let mut files: BTreeMap<String, String> = BTreeMap::new();
+    let mut contents = BundleContents::default();
+
+    let add = |files: &mut BTreeMap<String, String>, bundle_path: String| -> Result<bool, String> {
+        let source = target_path(notepad_dir, profile_dir, &bundle_path);
+        if !source.exists() {
+            return Ok(false);
+        }
+        let content = fs::read_to_string(&source).map_err(|e| format!("{}: {}", source.display(), e))?;
+        validate_entry(&bundle_path, &content)?;
+        files.insert(bundle_path, content);
+        Ok(true)
+    };
+
+    if options.global {
+        contents.global = add(&mut files, GLOBAL_CONFIG_FILE.to_string())?;
+    }
+    if options.keybindings {
+        contents.keybindings = add(&mut files, KEYBINDINGS_FILE.to_string())?;
+    }
+    for profile in &options.profiles {
+        if profile == profiles::DEFAULT_PROFILE || !profiles::exists(notepad_dir, profile) {
+            return Err(format!("Profile cannot be exported: {}", profile));
+        }
+        for file in PROFILE_FILES {
+            add(&mut files, format!("{}{}/{}", PROFILES_PREFIX, profile, file))?;
+        }
+        contents.profiles.push(profile.clone());
+    }
+    if options.themes {
+        for name in json_files(&notepad_dir.join(MONACO_THEMES_DIR)) {
+            if add(&mut files, format!("{}/{}", MONACO_THEMES_DIR, name))? {
+                contents.themes.push(name);
+            }
+        }
+    }
+    if options.snippets {
+        for name in json_files(&notepad_dir.join(SNIPPETS_DIR)) {
+            if add(&mut files, format!("{}/{}", SNIPPETS_DIR, name))? {
+                contents.snippets.push(name);
+            }
+        }
+    }
+
+    let manifest = Manifest {
+        format_version: BUNDLE_FORMAT_VERSION,
+        created_at: chrono::Local::now().timestamp_millis(),
+        contents: contents.clone(),
+    };
+
+    let file = fs::File::create(path).map_err(|e| e.to_string())?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+
+    let manifest_str = serde_json::to_string_pretty(&manifest).map_err(|e| e.to_string())?;
+    for (name, content) in std::iter::once((MANIFEST_FILE.to_string(), manifest_str)).chain(files) {
+        zip.start_file(name, options).map_err(|e| e.to_string())?;
+        zip.write_all(content.as_bytes()).map_err(|e| e.to_string())?;
+    }
+    zip.finish().map_err(|e| e.to_string())?;
+
+    Ok(contents)
+}
+
+struct Bundle {
+    manifest: Manifest,
+    files: BTreeMap<String, Value>,
+}
+
+// Reads and checks the whole bundle up front; nothing is written unless
+// every file in it is valid.
+fn read(path: &Path) -> Result<Bundle, String> {
+    let file = fs::File::open(path).map_err(|e| e.to_string())?;
+    let mut archive = zip::ZipArchive::new(file).map_err(|e| format!("Not a settings bundle: {}", e))?;
+
+    let mut manifest: Option<Manifest> = None;
+    let mut files = BTreeMap::new();
+
+    for index in 0..archive.len() {
+        let entry = archive.by_index(index).map_err(|e| e.to_string())?;
+        if entry.is_dir() {
+            continue;
+        }
+        let name = entry.name().to_string();
+        if entry.size() > MAX_ENTRY_SIZE {
+            return Err(format!("{}: file is too large", name));
+        }
+
+        let mut content = String::new();
+        entry.take(MAX_ENTRY_SIZE + 1).read_to_string(&mut content).map_err(|e| format!("{}: {}", name, e))?;
+        if content.len() as u64 > MAX_ENTRY_SIZE {
+            return Err(format!("{}: file is too large", name));
+        }
+
+        if name == MANIFEST_FILE {
+            manifest = Some(serde_json::from_str(&content).map_err(|e| format!("{}: {}", name, e))?);
+        } else {
+            let value = validate_entry(&name, &content)?;
+            files.insert(name, value);
+        }
+    }
+
+    let manifest = manifest.ok_or_else(|| "Not a settings bundle: manifest.json is missing".to_string())?;
+    if manifest.format_version > BUNDLE_FORMAT_VERSION {
+        return Err(format!(
+            "The bundle was made by a newer version (format {}); this version reads format {}",
+            manifest.format_version, BUNDLE_FORMAT_VERSION
+        ));
+    }
+    Ok(Bundle { manifest, files })
+}
+
+fn merge_json(current: Option<Value>, incoming: &Value) -> Value {
+    match (current, incoming) {
+        (Some(Value::Object(mut current)), Value::Object(incoming)) => {
+            for (key, value) in incoming {
+                current.insert(key.clone(), value.clone());
+            }
+            Value::Object(current)
+        }
+        _ => incoming.clone(),
+    }
+}
+
+enum Action {
+    Write(String),
+    Remove,
+}
+
+struct PlannedChange {
+    path: String,
+    target: PathBuf,
+    change: ChangeKind,
+    action: Action,
+}
+
+fn plan(notepad_dir: &Path, profile_dir: &Path, active_profile: &str, bundle: &Bundle, mode: ImportMode) -> Result<Vec<PlannedChange>, String> {
+    let mut planned = Vec::new();
+
+    for (path, incoming) in &bundle.files {
+        let target = target_path(notepad_dir, profile_dir, path);
+        let current: Option<Value> = fs::read_to_string(&target)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok());
+
+        let merges = matches!(entry_kind(path), Some(EntryKind::Global | EntryKind::Keybindings | EntryKind::ProfileFile));
+        let updated = if mode == ImportMode::Merge && merges {
+            merge_json(current.clone(), incoming)
+        } else {
+            incoming.clone()
+        };
+
+        let change = match &current {
+            None if target.exists() => ChangeKind::Modified,
+            None => ChangeKind::Added,
+            Some(current) if current == &updated => continue,
+            Some(_) => ChangeKind::Modified,
+        };
+        let content = serde_json::to_string_pretty(&updated).map_err(|e| e.to_string())?;
+        planned.push(PlannedChange { path: path.clone(), target, change, action: Action::Write(content) });
+    }
+
+    if mode == ImportMode::Replace {
+        let contents = &bundle.manifest.contents;
+        let mut extra: Vec<String> = Vec::new();
+
+        if !contents.themes.is_empty() {
+            extra.extend(json_files(&notepad_dir.join(MONACO_THEMES_DIR)).into_iter().map(|name| format!("{}/{}", MONACO_THEMES_DIR, name)));
+        }
+        if !contents.snippets.is_empty() {
+            extra.extend(json_files(&notepad_dir.join(SNIPPETS_DIR)).into_iter().map(|name| format!("{}/{}", SNIPPETS_DIR, name)));
+        }
+        if !contents.profiles.is_empty() {
+            for profile in profiles::list(notepad_dir, active_profile) {
+                if profile.active || profile.name == profiles::DEFAULT_PROFILE || contents.profiles.contains(&profile.name) {
+                    continue;
+                }
+                for file in PROFILE_FILES {
+                    extra.push(format!("{}{}/{}", PROFILES_PREFIX, profile.name, file));
+                }
+            }
+        }
+
+        for path in extra {
+            let target = notepad_dir.join(&path);
+            if bundle.files.contains_key(&path) || !target.exists() {
+                continue;
+            }
+            planned.push(PlannedChange { path, target, change: ChangeKind::Removed, action: Action::Remove });
+        }
+    }
+
+    Ok(planned)
+}
+
+fn report(planned: &[PlannedChange]) -> Vec<BundleChange> {
+    planned
+        .iter()
+        .map(|change| BundleChange { path: change.path.clone(), change: change.change })
+        .collect()
+}
+
+pub fn preview(notepad_dir: &Path, profile_dir: &Path, active_profile: &str, path: &Path, mode: ImportMode) -> Result<BundlePreview, String> {
+    let bundle = read(path)?;
+    let planned = plan(notepad_dir, profile_dir, active_profile, &bundle, mode)?;
+
+    Ok(BundlePreview {
+        contents: bundle.manifest.contents,
+        created_at: bundle.manifest.created_at,
+        changes: report(&planned),
+    })
+}
+
+// Global configs are merged and written under the lock instances take to
+// save theirs, and nothing is written if any target comes from a newer
+// version.
+pub fn import(notepad_dir: &Path, profile_dir: &Path, active_profile: &str, path: &Path, mode: ImportMode) -> Result<Vec<BundleChange>, String> {
+    let bundle = read(path)?;
+    let _locks = bundle.files.keys()
+        .map(|path| target_path(notepad_dir, profile_dir, path))
+        .filter(|target| target.file_name().is_some_and(|name| name == GLOBAL_CONFIG_FILE))
+        .filter(|target| target.parent().is_some_and(Path::is_dir))
+        .map(|target| GlobalConfigLock::acquire(&target))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let planned = plan(notepad_dir, profile_dir, active_profile, &bundle, mode)?;
+    for change in &planned {
+        migration::check_writable(&change.target)?;
+    }
+
+    for change in &planned {
+        match &change.action {
+            Action::Write(content) => {
+                if let Some(parent) = change.target.parent() {
+                    fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+                }
+                super::write_atomic(&change.target, content)?;
+            }
+            Action::Remove => {
+                fs::remove_file(&change.target).map_err(|e| e.to_string())?;
+                // A removed profile goes away with its directory.
+                if change.path.starts_with(PROFILES_PREFIX) {
+                    if let Some(parent) = change.target.parent() {
+                        if fs::read_dir(parent).is_ok_and(|mut entries| entries.next().is_none()) {
+                            let _ = fs::remove_dir(parent);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(report(&planned))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entry_kind_accepts_only_the_bundle_layout() {
+        assert_eq!(entry_kind(GLOBAL_CONFIG_FILE), Some(EntryKind::Global));
+        assert_eq!(entry_kind(KEYBINDINGS_FILE), Some(EntryKind::Keybindings));
+        assert_eq!(entry_kind("monaco-editor/one-dark.json"), Some(EntryKind::Theme));
+        assert_eq!(entry_kind("snippets/rust.json"), Some(EntryKind::Snippet));
+        assert_eq!(entry_kind(&format!("profiles/work/{}", GLOBAL_CONFIG_FILE)), Some(EntryKind::ProfileFile));
+    }
+
+    #[test]
+    fn entry_kind_rejects_paths_outside_the_layout() {
+        for path in [
+            "../firow-notepad-global.json",
+            "monaco-editor/../../evil.json",
+            "monaco-editor/theme.txt",
+            "monaco-editor/sub/theme.json",
+            "snippets/.json",
+            "profiles/default/firow-notepad-global.json",
+            "profiles/../firow-notepad-global.json",
+            "profiles/work/other.json",
+            "instances/main.json",
+            "/etc/passwd",
+        ] {
+            assert_eq!(entry_kind(path), None, "{}", path);
+        }
+    }
+
+    #[test]
+    fn validate_entry_checks_the_content() {
+        assert!(validate_entry(GLOBAL_CONFIG_FILE, r#"{"font_size": 14}"#).is_ok());
+        assert!(validate_entry(GLOBAL_CONFIG_FILE, r#"{"font_size": 1000}"#).is_err());
+        assert!(validate_entry(GLOBAL_CONFIG_FILE, "not json").is_err());
+        assert!(validate_entry("other.json", "{}").is_err());
+    }
+
+    #[test]
+    fn import_writes_nothing_when_a_target_is_from_a_newer_version() {
+        let source = tempfile::tempdir().unwrap();
+        let work = source.path().join("profiles").join("work");
+        fs::create_dir_all(&work).unwrap();
+        fs::write(source.path().join(GLOBAL_CONFIG_FILE), r#"{"font_size": 14}"#).unwrap();
+        fs::write(work.join(GLOBAL_CONFIG_FILE), r#"{"font_size": 16}"#).unwrap();
+        let bundle_path = source.path().join("settings.zip");
+        let options = BundleOptions { global: true, profiles: vec!["work".to_string()], ..Default::default() };
+        export(source.path(), source.path(), &options, &bundle_path).unwrap();
+
+        let target = tempfile::tempdir().unwrap();
+        let work = target.path().join("profiles").join("work");
+        fs::create_dir_all(&work).unwrap();
+        let newer = format!(r#"{{"schema_version": {}}}"#, migration::CONFIG_SCHEMA_VERSION + 1);
+        fs::write(work.join(GLOBAL_CONFIG_FILE), &newer).unwrap();
+
+        let result = import(target.path(), target.path(), profiles::DEFAULT_PROFILE, &bundle_path, ImportMode::Merge);
+        assert!(result.is_err());
+        assert!(!target.path().join(GLOBAL_CONFIG_FILE).exists());
+        assert_eq!(fs::read_to_string(work.join(GLOBAL_CONFIG_FILE)).unwrap(), newer);
+    }
+}
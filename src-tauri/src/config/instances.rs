@@ -0,0 +1,254 @@
+use fs4::fs_std::FileExt;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const INSTANCES_DIR: &str = "firow-notepad-instances";
+pub const MAIN_INSTANCE_ID: &str = "main";
+pub const DEFAULT_INSTANCE_RETENTION_DAYS: u32 = 30;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct InstanceLifecycle {
+    pub pid: Option<u32>,
+    pub started_at: Option<i64>,
+    pub closed_at: Option<i64>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum InstanceStatus {
+    Running,
+    Closed,
+    Crashed,
+    Unknown,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct InstanceInfo {
+    pub id: String,
+    pub status: InstanceStatus,
+    pub pid: Option<u32>,
+    pub started_at: Option<i64>,
+    pub closed_at: Option<i64>,
+    pub modified: u64,
+    pub opened_files: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct InstanceSummary {
+    opened_files: Option<Vec<String>>,
+    lifecycle: Option<InstanceLifecycle>,
+}
+
+fn lock_path(instances_dir: &Path, instance_id: &str) -> PathBuf {
+    instances_dir.join(format!("{}.lock", instance_id))
+}
+
+// Held for as long as the instance runs. The OS releases it when the process
+// dies, so an unheld lock means the instance is gone whatever its file says.
+pub struct InstanceLock {
+    file: fs::File,
+}
+
+impl InstanceLock {
+    pub fn acquire(instances_dir: &Path, instance_id: &str) -> Result<Option<Self>, String> {
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(lock_path(instances_dir, instance_id))
+            .map_err(|e| e.to_string())?;
+
+        match FileExt::try_lock_exclusive(&file) {
+            Ok(true) => Ok(Some(Self { file })),
+            Ok(false) => Ok(None),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
+
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        let _ = FileExt::unlock(&self.file);
+    }
+}
+
+pub fn is_running(instances_dir: &Path, instance_id: &str) -> bool {
+    let file = match fs::OpenOptions::new().write(true).open(lock_path(instances_dir, instance_id)) {
+        Ok(file) => file,
+        Err(_) => return false,
+    };
+
+    match FileExt::try_lock_exclusive(&file) {
+        Ok(true) => {
+            let _ = FileExt::unlock(&file);
+            false
+        }
+        Ok(false) => true,
+        Err(_) => false,
+    }
+}
+
+fn read_info(instances_dir: &Path, instance_id: &str, path: &Path) -> Option<InstanceInfo> {
+    let config_str = fs::read_to_string(path).ok()?;
+    let summary: InstanceSummary = serde_json::from_str(&config_str).ok()?;
+    let modified = fs::metadata(path).ok()?
+        .modified().ok()?
+        .duration_since(std::time::UNIX_EPOCH).ok()?
+        .as_secs();
+    let lifecycle = summary.lifecycle.clone().unwrap_or_default();
+
+    // Files written before lifecycles were recorded can't be told apart from
+    // crashed ones, so their status is unknown.
+    let status = if is_running(instances_dir, instance_id) {
+        InstanceStatus::Running
+    } else if summary.lifecycle.is_none() {
+        InstanceStatus::Unknown
+    } else if lifecycle.closed_at.is_none() {
+        InstanceStatus::Crashed
+    } else {
+        InstanceStatus::Closed
+    };
+
+    Some(InstanceInfo {
+        id: instance_id.to_string(),
+        status,
+        pid: lifecycle.pid,
+        started_at: lifecycle.started_at,
+        closed_at: lifecycle.closed_at,
+        modified,
+        opened_files: summary.opened_files.unwrap_or_default(),
+    })
+}
+
+pub fn list(instances_dir: &Path) -> Vec<InstanceInfo> {
+    let entries = match fs::read_dir(instances_dir) {
+        Ok(entries) => entries,
+        Err(_) => return vec![],
+    };
+
+    let mut instances: Vec<InstanceInfo> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter_map(|path| {
+            let instance_id = path.file_name()?.to_str()?.strip_suffix(".json")?.to_string();
+            if instance_id.contains(".json") {
+                return None;
+            }
+            read_info(instances_dir, &instance_id, &path)
+        })
+        .collect();
+
+    instances.sort_by_key(|info| std::cmp::Reverse(info.modified));
+    instances
+}
+
+// The config plus everything written next to it: lock, backups, quarantined
+// copies and leftover temp files.
+pub fn delete_files(instances_dir: &Path, instance_id: &str) -> Result<(), String> {
+    let config_name = format!("{}.json", instance_id);
+    let related_prefix = format!("{}.", config_name);
+    let lock_name = format!("{}.lock", instance_id);
+
+    for entry in fs::read_dir(instances_dir).map_err(|e| e.to_string())?.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name == config_name || name == lock_name || name.starts_with(&related_prefix) {
+            fs::remove_file(entry.path()).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+// Closed instances with nothing open are removed right away; anything else
+// that isn't running is kept for `retention_days` so it can still be merged.
+pub fn is_expired(info: &InstanceInfo, now_secs: u64, retention_days: u32) -> bool {
+    if info.id == MAIN_INSTANCE_ID || info.status == InstanceStatus::Running || retention_days == 0 {
+        return false;
+    }
+    if info.status == InstanceStatus::Closed && info.opened_files.is_empty() {
+        return true;
+    }
+    now_secs.saturating_sub(info.modified) > retention_days as u64 * 24 * 60 * 60
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: u64 = 24 * 60 * 60;
+
+    fn info(id: &str, status: InstanceStatus, modified: u64, opened_files: &[&str]) -> InstanceInfo {
+        InstanceInfo {
+            id: id.to_string(),
+            status,
+            pid: None,
+            started_at: None,
+            closed_at: None,
+            modified,
+            opened_files: opened_files.iter().map(|file| file.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn expiry_depends_on_status_and_age() {
+        let now = 100 * DAY;
+        let old = now - 31 * DAY;
+        let recent = now - DAY;
+
+        assert!(is_expired(&info("a", InstanceStatus::Closed, recent, &[]), now, 30));
+        assert!(!is_expired(&info("a", InstanceStatus::Closed, recent, &["/a.txt"]), now, 30));
+        assert!(is_expired(&info("a", InstanceStatus::Closed, old, &["/a.txt"]), now, 30));
+        assert!(!is_expired(&info("a", InstanceStatus::Crashed, recent, &[]), now, 30));
+        assert!(is_expired(&info("a", InstanceStatus::Crashed, old, &[]), now, 30));
+        assert!(is_expired(&info("a", InstanceStatus::Unknown, old, &[]), now, 30));
+    }
+
+    #[test]
+    fn running_main_and_disabled_retention_never_expire() {
+        let now = 100 * DAY;
+        assert!(!is_expired(&info("a", InstanceStatus::Running, 0, &[]), now, 30));
+        assert!(!is_expired(&info(MAIN_INSTANCE_ID, InstanceStatus::Closed, 0, &[]), now, 30));
+        assert!(!is_expired(&info("a", InstanceStatus::Closed, 0, &[]), now, 0));
+    }
+
+    #[test]
+    fn list_reports_the_status_of_each_instance() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |id: &str, content: &str| fs::write(dir.path().join(format!("{}.json", id)), content).unwrap();
+        write("closed", r#"{"lifecycle": {"pid": 1, "started_at": 1, "closed_at": 2}}"#);
+        write("crashed", r#"{"lifecycle": {"pid": 1, "started_at": 1}, "opened_files": ["/a.txt"]}"#);
+        write("unknown", r#"{"opened_files": []}"#);
+        write("running", r#"{"lifecycle": {"pid": 1, "started_at": 1}}"#);
+        fs::write(dir.path().join("closed.json.v0.bak"), "{}").unwrap();
+        let _lock = InstanceLock::acquire(dir.path(), "running").unwrap().unwrap();
+
+        let mut statuses: Vec<(String, InstanceStatus)> = list(dir.path())
+            .into_iter()
+            .map(|info| (info.id, info.status))
+            .collect();
+        statuses.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(statuses, [
+            ("closed".to_string(), InstanceStatus::Closed),
+            ("crashed".to_string(), InstanceStatus::Crashed),
+            ("running".to_string(), InstanceStatus::Running),
+            ("unknown".to_string(), InstanceStatus::Unknown),
+        ]);
+        assert!(InstanceLock::acquire(dir.path(), "running").unwrap().is_none());
+    }
+
+    #[test]
+    fn delete_files_removes_only_that_instance() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["old.json", "old.lock", "old.json.v0.bak", "old.json.tmp", "old2.json", "older.json"] {
+            fs::write(dir.path().join(name), "").unwrap();
+        }
+
+        delete_files(dir.path(), "old").unwrap();
+        let mut left: Vec<String> = fs::read_dir(dir.path()).unwrap()
+            .flatten()
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .collect();
+        left.sort();
+        assert_eq!(left, ["old2.json", "older.json"]);
+    }
+}
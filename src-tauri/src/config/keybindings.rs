@@ -0,0 +1,264 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+use super::FieldError;
+
+pub const KEYBINDINGS_FILE: &str = "keybindings.json";
+
+// Commands that can be bound, with their default chords. `Mod` is Cmd on
+// macOS and Ctrl elsewhere.
+const COMMANDS: &[(&str, &str)] = &[
+    ("file.new", "Mod+N"),
+    ("file.open", "Mod+O"),
+    ("file.save", "Mod+S"),
+    ("file.save_as", "Mod+Shift+S"),
+    ("file.close", "Mod+W"),
+    ("file.restore", "Mod+Shift+T"),
+    ("file.rename", "F2"),
+    ("file.reveal", "Mod+E"),
+    ("recent.open", "Mod+R"),
+    ("view.toggle_word_wrap", "Alt+Z"),
+    ("view.toggle_side_panel", "Mod+B"),
+    ("search.find_in_files", "Mod+Shift+F"),
+    ("tabs.next", "Ctrl+Tab"),
+    ("tabs.previous", "Ctrl+Shift+Tab"),
+];
+
+const MODIFIERS: &[&str] = &["Ctrl", "Alt", "Shift", "Meta"];
+
+// Key names follow `KeyboardEvent.code` without the `Key`/`Digit` prefix, so
+// the frontend can build the same string from an event.
+const NAMED_KEYS: &[&str] = &[
+    "Tab", "Enter", "Escape", "Space", "Backspace", "Delete", "Insert",
+    "Home", "End", "PageUp", "PageDown", "ArrowUp", "ArrowDown", "ArrowLeft", "ArrowRight",
+    "Minus", "Equal", "Comma", "Period", "Slash", "Backslash", "Semicolon", "Quote",
+    "Backquote", "BracketLeft", "BracketRight",
+];
+
+#[derive(Serialize, Clone, Debug)]
+pub struct KeybindingConflict {
+    pub chord: String,
+    pub commands: Vec<String>,
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct KeybindingReport {
+    pub bindings: BTreeMap<String, String>,
+    pub errors: Vec<FieldError>,
+    pub conflicts: Vec<KeybindingConflict>,
+}
+
+pub fn is_command(command: &str) -> bool {
+    COMMANDS.iter().any(|(id, _)| *id == command)
+}
+
+fn modifier(name: &str, macos: bool) -> Option<&'static str> {
+    match name.to_ascii_lowercase().as_str() {
+        "ctrl" | "control" => Some("Ctrl"),
+        "alt" | "option" | "opt" => Some("Alt"),
+        "shift" => Some("Shift"),
+        "meta" | "cmd" | "command" | "super" | "win" => Some("Meta"),
+        "mod" => Some(if macos { "Meta" } else { "Ctrl" }),
+        _ => None,
+    }
+}
+
+fn key(name: &str) -> Option<String> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        if c.is_ascii_alphanumeric() {
+            return Some(c.to_ascii_uppercase().to_string());
+        }
+    }
+
+    let function_key = name.len() > 1
+        && name[..1].eq_ignore_ascii_case("f")
+        && name[1..].parse::<u8>().is_ok_and(|n| (1..=24).contains(&n));
+    if function_key {
+        return Some(name.to_ascii_uppercase());
+    }
+
+    let alias = match name.to_ascii_lowercase().as_str() {
+        "up" => "ArrowUp",
+        "down" => "ArrowDown",
+        "left" => "ArrowLeft",
+        "right" => "ArrowRight",
+        "esc" => "Escape",
+        "del" => "Delete",
+        "return" => "Enter",
+        _ => "",
+    };
+    if !alias.is_empty() {
+        return Some(alias.to_string());
+    }
+
+    NAMED_KEYS
+        .iter()
+        .find(|named| named.eq_ignore_ascii_case(name))
+        .map(|named| named.to_string())
+}
+
+// Parses "ctrl+shift+s" into the canonical "Ctrl+Shift+S": modifiers in a
+// fixed order followed by exactly one key.
+pub fn normalize_chord(chord: &str, macos: bool) -> Result<String, String> {
+    let parts: Vec<&str> = chord.split('+').map(str::trim).collect();
+    let (key_name, modifier_names) = parts.split_last().ok_or("empty chord")?;
+
+    let mut modifiers = Vec::new();
+    for name in modifier_names {
+        let modifier = modifier(name, macos).ok_or_else(|| format!("unknown modifier {}", name))?;
+        if modifiers.contains(&modifier) {
+            return Err(format!("{} is repeated", modifier));
+        }
+        modifiers.push(modifier);
+    }
+    let key = key(key_name).ok_or_else(|| format!("unknown key {}", key_name))?;
+
+    let mut canonical: Vec<String> = MODIFIERS
+        .iter()
+        .filter(|modifier| modifiers.contains(modifier))
+        .map(|modifier| modifier.to_string())
+        .collect();
+    canonical.push(key);
+    Ok(canonical.join("+"))
+}
+
+pub fn defaults(macos: bool) -> BTreeMap<String, String> {
+    COMMANDS
+        .iter()
+        .map(|(command, chord)| {
+            let chord = normalize_chord(chord, macos).unwrap_or_default();
+            (command.to_string(), chord)
+        })
+        .collect()
+}
+
+// Checks one binding. An empty chord unbinds the command.
+pub fn check_binding(command: &str, chord: &str, macos: bool) -> Result<String, String> {
+    if !is_command(command) {
+        return Err("unknown command".to_string());
+    }
+    if chord.trim().is_empty() {
+        return Ok(String::new());
+    }
+    normalize_chord(chord, macos)
+}
+
+// Applies `overrides` to `bindings`, recording the ones that are invalid
+// under `source` ("keybindings", "keybindings.json").
+fn apply(bindings: &mut BTreeMap<String, String>, overrides: &HashMap<String, String>, source: &str, macos: bool, errors: &mut Vec<FieldError>) {
+    let mut commands: Vec<&String> = overrides.keys().collect();
+    commands.sort();
+
+    for command in commands {
+        match check_binding(command, &overrides[command], macos) {
+            Ok(chord) => {
+                bindings.insert(command.clone(), chord);
+            }
+            Err(message) => errors.push(FieldError {
+                field: format!("{}.{}", source, command),
+                message,
+            }),
+        }
+    }
+}
+
+fn conflicts(bindings: &BTreeMap<String, String>) -> Vec<KeybindingConflict> {
+    let mut by_chord: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for (command, chord) in bindings {
+        if !chord.is_empty() {
+            by_chord.entry(chord).or_default().push(command.clone());
+        }
+    }
+
+    by_chord
+        .into_iter()
+        .filter(|(_, commands)| commands.len() > 1)
+        .map(|(chord, commands)| KeybindingConflict { chord: chord.to_string(), commands })
+        .collect()
+}
+
+pub fn read_file(profile_dir: &Path) -> Result<Option<HashMap<String, String>>, String> {
+    let path = profile_dir.join(KEYBINDINGS_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    serde_json::from_str(&content).map(Some).map_err(|e| format!("{}: {}", KEYBINDINGS_FILE, e))
+}
+
+// Defaults for the platform, then the `keybindings` section of the global
+// config, then `keybindings.json`.
+pub fn resolve(config: Option<&HashMap<String, String>>, file: Option<&HashMap<String, String>>, macos: bool) -> KeybindingReport {
+    let mut bindings = defaults(macos);
+    let mut errors = Vec::new();
+
+    if let Some(config) = config {
+        apply(&mut bindings, config, "keybindings", macos, &mut errors);
+    }
+    if let Some(file) = file {
+        apply(&mut bindings, file, KEYBINDINGS_FILE, macos, &mut errors);
+    }
+
+    KeybindingReport {
+        conflicts: conflicts(&bindings),
+        bindings,
+        errors,
+    }
+}
+
+pub fn is_macos() -> bool {
+    cfg!(target_os = "macos")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chords_are_normalized() {
+        assert_eq!(normalize_chord("shift+ctrl+s", false).unwrap(), "Ctrl+Shift+S");
+        assert_eq!(normalize_chord("Mod+Shift+F", false).unwrap(), "Ctrl+Shift+F");
+        assert_eq!(normalize_chord("Mod+Shift+F", true).unwrap(), "Shift+Meta+F");
+        assert_eq!(normalize_chord("cmd + f12", true).unwrap(), "Meta+F12");
+        assert_eq!(normalize_chord("Alt+esc", false).unwrap(), "Alt+Escape");
+    }
+
+    #[test]
+    fn invalid_chords_are_rejected() {
+        assert!(normalize_chord("Ctrl+Ctrl+S", false).is_err());
+        assert!(normalize_chord("Hyper+S", false).is_err());
+        assert!(normalize_chord("Ctrl+F25", false).is_err());
+        assert!(normalize_chord("Ctrl+", false).is_err());
+    }
+
+    #[test]
+    fn every_default_is_valid() {
+        for (command, chord) in defaults(false).into_iter().chain(defaults(true)) {
+            assert!(!chord.is_empty(), "{}", command);
+        }
+        assert!(resolve(None, None, false).conflicts.is_empty());
+        assert!(resolve(None, None, true).conflicts.is_empty());
+    }
+
+    #[test]
+    fn the_file_overrides_the_config_and_conflicts_are_reported() {
+        let config = HashMap::from([("file.save".to_string(), "Ctrl+Alt+S".to_string())]);
+        let file = HashMap::from([
+            ("file.save".to_string(), "Ctrl+N".to_string()),
+            ("file.close".to_string(), String::new()),
+            ("file.nope".to_string(), "Ctrl+J".to_string()),
+        ]);
+
+        let report = resolve(Some(&config), Some(&file), false);
+
+        assert_eq!(report.bindings["file.save"], "Ctrl+N");
+        assert_eq!(report.bindings["file.close"], "");
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.errors[0].field, "keybindings.json.file.nope");
+        assert_eq!(report.conflicts.len(), 1);
+        assert_eq!(report.conflicts[0].commands, ["file.new", "file.save"]);
+    }
+}
@@ -0,0 +1,177 @@
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct LanguageSettings {
+    pub tab_size: Option<u32>,
+    pub insert_spaces: Option<bool>,
+    pub word_wrap: Option<bool>,
+    pub rulers: Option<Vec<u32>>,
+    pub font_size: Option<i32>,
+    pub default_eol: Option<String>,
+    pub trim_on_save: Option<bool>,
+    pub format_on_save: Option<bool>,
+}
+
+impl LanguageSettings {
+    fn apply(&mut self, other: &LanguageSettings) {
+        if other.tab_size.is_some() { self.tab_size = other.tab_size; }
+        if other.insert_spaces.is_some() { self.insert_spaces = other.insert_spaces; }
+        if other.word_wrap.is_some() { self.word_wrap = other.word_wrap; }
+        if other.rulers.is_some() { self.rulers = other.rulers.clone(); }
+        if other.font_size.is_some() { self.font_size = other.font_size; }
+        if other.default_eol.is_some() { self.default_eol = other.default_eol.clone(); }
+        if other.trim_on_save.is_some() { self.trim_on_save = other.trim_on_save; }
+        if other.format_on_save.is_some() { self.format_on_save = other.format_on_save; }
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct EditorSettings {
+    pub tab_size: u32,
+    pub insert_spaces: bool,
+    pub word_wrap: bool,
+    pub rulers: Vec<u32>,
+    pub font_size: i32,
+    pub default_eol: Option<String>,
+    pub trim_on_save: bool,
+    pub format_on_save: bool,
+}
+
+// Keys of the `languages` map are either Monaco language ids ("markdown") or
+// file globs ("*.md", "**/docs/*.txt"). Only glob metacharacters make a glob.
+pub fn is_glob(key: &str) -> bool {
+    key.contains(['*', '?', '[', '{'])
+}
+
+// The glob keys of a `languages` map compiled into one set, which is only
+// rebuilt when the keys change. Invalid globs never match.
+#[derive(Default)]
+pub struct GlobCache {
+    keys: Vec<String>,
+    valid: Vec<String>,
+    set: GlobSet,
+}
+
+impl GlobCache {
+    fn update(&mut self, languages: &HashMap<String, LanguageSettings>) {
+        let mut keys: Vec<String> = languages.keys()
+            .filter(|key| is_glob(key))
+            .cloned()
+            .collect();
+        keys.sort();
+        if keys == self.keys {
+            return;
+        }
+
+        let mut builder = GlobSetBuilder::new();
+        let mut valid = Vec::new();
+        for key in keys.iter() {
+            if let Ok(glob) = Glob::new(key) {
+                builder.add(glob);
+                valid.push(key.clone());
+            }
+        }
+        self.set = builder.build().unwrap_or_default();
+        self.keys = keys;
+        self.valid = valid;
+    }
+}
+
+// Language id overrides are applied first, then matching globs from the least
+// to the most specific (longest pattern), each one overriding the previous.
+pub fn resolve(languages: &HashMap<String, LanguageSettings>, cache: &mut GlobCache, path: &str, language: Option<&str>, word_wrap: bool, font_size: i32) -> EditorSettings {
+    let path = Path::new(path);
+    let mut resolved = LanguageSettings::default();
+
+    if let Some(settings) = language.and_then(|id| languages.get(id)) {
+        resolved.apply(settings);
+    }
+
+    cache.update(languages);
+    let mut matches = cache.set.matches(path);
+    if let Some(name) = path.file_name() {
+        matches.extend(cache.set.matches(name));
+    }
+    let mut globs: Vec<&String> = matches.into_iter().map(|index| &cache.valid[index]).collect();
+    globs.sort_by(|a, b| a.len().cmp(&b.len()).then(a.cmp(b)));
+    globs.dedup();
+
+    for key in globs {
+        resolved.apply(&languages[key]);
+    }
+
+    EditorSettings {
+        tab_size: resolved.tab_size.unwrap_or(4),
+        insert_spaces: resolved.insert_spaces.unwrap_or(true),
+        word_wrap: resolved.word_wrap.unwrap_or(word_wrap),
+        rulers: resolved.rulers.unwrap_or_default(),
+        font_size: resolved.font_size.unwrap_or(font_size),
+        default_eol: resolved.default_eol,
+        trim_on_save: resolved.trim_on_save.unwrap_or(false),
+        format_on_save: resolved.format_on_save.unwrap_or(false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tab_size(size: u32) -> LanguageSettings {
+        LanguageSettings { tab_size: Some(size), ..LanguageSettings::default() }
+    }
+
+    #[test]
+    fn only_metacharacters_make_a_glob() {
+        assert!(is_glob("*.md"));
+        assert!(is_glob("**/docs/*.txt"));
+        assert!(is_glob("file?.txt"));
+        assert!(is_glob("*.{js,ts}"));
+        assert!(!is_glob("markdown"));
+        assert!(!is_glob("d.ts"));
+        assert!(!is_glob("docs/readme"));
+    }
+
+    #[test]
+    fn globs_override_the_language_from_least_to_most_specific() {
+        let languages = HashMap::from([
+            ("markdown".to_string(), tab_size(2)),
+            ("*.md".to_string(), tab_size(3)),
+            ("**/docs/*.md".to_string(), tab_size(8)),
+        ]);
+        let mut cache = GlobCache::default();
+
+        let resolve_tab_size = |cache: &mut GlobCache, path: &str| {
+            resolve(&languages, cache, path, Some("markdown"), false, 14).tab_size
+        };
+
+        assert_eq!(resolve_tab_size(&mut cache, "/project/docs/guide.md"), 8);
+        assert_eq!(resolve_tab_size(&mut cache, "/project/README.md"), 3);
+        assert_eq!(resolve_tab_size(&mut cache, "/project/notes.txt"), 2);
+    }
+
+    #[test]
+    fn unset_values_fall_back_to_the_editor_defaults() {
+        let settings = resolve(&HashMap::new(), &mut GlobCache::default(), "/a.rs", Some("rust"), true, 16);
+
+        assert_eq!(settings.tab_size, 4);
+        assert!(settings.insert_spaces);
+        assert!(settings.word_wrap);
+        assert_eq!(settings.font_size, 16);
+    }
+
+    #[test]
+    fn the_cache_follows_changed_keys() {
+        let mut cache = GlobCache::default();
+        let mut languages = HashMap::from([("*.txt".to_string(), tab_size(2))]);
+        assert_eq!(resolve(&languages, &mut cache, "/a.txt", None, false, 14).tab_size, 2);
+
+        languages.insert("*.log".to_string(), tab_size(6));
+        assert_eq!(resolve(&languages, &mut cache, "/a.log", None, false, 14).tab_size, 6);
+
+        languages.insert("[".to_string(), tab_size(7));
+        assert_eq!(resolve(&languages, &mut cache, "/a.log", None, false, 14).tab_size, 6);
+    }
+}
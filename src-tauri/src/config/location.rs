@@ -0,0 +1,148 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+pub const CONFIG_DIR_FLAG: &str = "--config-dir";
+pub const CONFIG_DIR_ENV: &str = "FIROW_NOTEPAD_CONFIG_DIR";
+
+// Either of these next to the executable switches to portable mode. The
+// directory is used as is; the marker file puts the data in `FirowNotepad`
+// beside it.
+const PORTABLE_DIR: &str = "portable";
+const PORTABLE_MARKER: &str = "firow-notepad.portable";
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigSource {
+    Default,
+    Flag,
+    Env,
+    Portable,
+}
+
+// Where the FirowNotepad directory lives. Resolved once at startup and
+// managed as state; `dir` is `None` for the default under the user's config
+// directory.
+pub struct ConfigLocation {
+    pub dir: Option<PathBuf>,
+    pub source: ConfigSource,
+}
+
+// Startup flags that take a value.
+const VALUE_FLAGS: &[&str] = &[CONFIG_DIR_FLAG, super::profiles::PROFILE_FLAG];
+
+// Returns the value of `<flag> <value>` or `<flag>=<value>`.
+pub fn flag_value(args: &[String], flag: &str) -> Option<String> {
+    args.iter().enumerate().find_map(|(index, arg)| {
+        if arg == flag {
+            args.get(index + 1).cloned()
+        } else {
+            arg.strip_prefix(flag)
+                .and_then(|rest| rest.strip_prefix('='))
+                .map(|value| value.to_string())
+        }
+    })
+}
+
+// Whether `args[index]` is one of the value flags or its value, so it isn't
+// taken for a file to open.
+pub fn is_flag_arg(args: &[String], index: usize) -> bool {
+    let arg = &args[index];
+    VALUE_FLAGS.iter().any(|flag| {
+        arg == flag
+            || arg.starts_with(&format!("{}=", flag))
+            || (index > 0 && args[index - 1] == *flag)
+    })
+}
+
+fn absolute(dir: &str) -> PathBuf {
+    let path = Path::new(dir);
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir().map(|cwd| cwd.join(path)).unwrap_or_else(|_| path.to_path_buf())
+    }
+}
+
+fn portable_dir() -> Option<PathBuf> {
+    let exe_dir = std::env::current_exe().ok()?.parent()?.to_path_buf();
+
+    let dir = exe_dir.join(PORTABLE_DIR);
+    if dir.is_dir() {
+        return Some(dir);
+    }
+    if exe_dir.join(PORTABLE_MARKER).is_file() {
+        return Some(exe_dir.join("FirowNotepad"));
+    }
+    None
+}
+
+// Where the webview keeps its cache and storage for a custom config
+// directory. Only WebView2 is redirected, through its environment variable;
+// on Linux and macOS that data stays in the user profile.
+pub fn webview_data_dir(dir: &Path) -> Option<PathBuf> {
+    if cfg!(target_os = "windows") {
+        Some(dir.join("webview"))
+    } else {
+        None
+    }
+}
+
+// The flag wins over the environment variable, which wins over portable mode.
+pub fn resolve(args: &[String]) -> ConfigLocation {
+    resolve_from(args, std::env::var(CONFIG_DIR_ENV).ok(), portable_dir())
+}
+
+fn resolve_from(args: &[String], env_dir: Option<String>, portable: Option<PathBuf>) -> ConfigLocation {
+    if let Some(dir) = flag_value(args, CONFIG_DIR_FLAG).filter(|dir| !dir.is_empty()) {
+        return ConfigLocation { dir: Some(absolute(&dir)), source: ConfigSource::Flag };
+    }
+    if let Some(dir) = env_dir.filter(|dir| !dir.is_empty()) {
+        return ConfigLocation { dir: Some(absolute(&dir)), source: ConfigSource::Env };
+    }
+    if let Some(dir) = portable {
+        return ConfigLocation { dir: Some(dir), source: ConfigSource::Portable };
+    }
+    ConfigLocation { dir: None, source: ConfigSource::Default }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn flag_wins_over_env_and_portable() {
+        let portable = Some(PathBuf::from("/opt/app/portable"));
+
+        let location = resolve_from(&args(&["app", "--config-dir", "/flag"]), Some("/env".to_string()), portable.clone());
+        assert_eq!((location.dir, location.source), (Some(PathBuf::from("/flag")), ConfigSource::Flag));
+
+        let location = resolve_from(&args(&["app"]), Some("/env".to_string()), portable.clone());
+        assert_eq!((location.dir, location.source), (Some(PathBuf::from("/env")), ConfigSource::Env));
+
+        let location = resolve_from(&args(&["app", "--config-dir="]), Some(String::new()), portable.clone());
+        assert_eq!((location.dir, location.source), (portable, ConfigSource::Portable));
+
+        let location = resolve_from(&args(&["app"]), None, None);
+        assert_eq!((location.dir, location.source), (None, ConfigSource::Default));
+    }
+
+    #[test]
+    fn relative_dirs_are_made_absolute() {
+        let location = resolve_from(&args(&["app", "--config-dir=settings"]), None, None);
+        assert_eq!(location.dir, Some(std::env::current_dir().unwrap().join("settings")));
+    }
+
+    #[test]
+    fn flag_values_and_their_args_are_recognized() {
+        let args = args(&["app", "--config-dir", "/dir", "--profile=work", "file.txt"]);
+        assert_eq!(flag_value(&args, CONFIG_DIR_FLAG).as_deref(), Some("/dir"));
+        assert_eq!(flag_value(&args, crate::config::profiles::PROFILE_FLAG).as_deref(), Some("work"));
+
+        let flags: Vec<bool> = (0..args.len()).map(|index| is_flag_arg(&args, index)).collect();
+        assert_eq!(flags, [false, true, true, true, false]);
+    }
+}
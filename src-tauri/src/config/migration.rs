@@ -0,0 +1,161 @@
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::FieldError;
+
+pub const CONFIG_SCHEMA_VERSION: u32 = 1;
+
+type Migration = fn(&mut Map<String, Value>);
+
+// Entry `n` upgrades a file from schema version `n` to `n + 1`. Files written
+// before `schema_version` existed are treated as version 0.
+const GLOBAL_MIGRATIONS: &[Migration] = &[global_v0_to_v1];
+const INSTANCE_MIGRATIONS: &[Migration] = &[instance_v0_to_v1];
+
+fn global_v0_to_v1(config: &mut Map<String, Value>) {
+    config.entry("default_encoding").or_insert_with(|| Value::from("utf-8"));
+}
+
+fn instance_v0_to_v1(config: &mut Map<String, Value>) {
+    config.entry("watched_folders").or_insert_with(|| Value::Array(vec![]));
+}
+
+pub fn backup_path(path: &Path, version: u32) -> PathBuf {
+    let file_name = path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!("{}.v{}.bak", file_name, version))
+}
+
+fn file_version(path: &Path) -> Option<u32> {
+    let config_str = fs::read_to_string(path).ok()?;
+    let value: Value = serde_json::from_str(&config_str).ok()?;
+    value.get("schema_version")?.as_u64().map(|version| version as u32)
+}
+
+// A file written by a newer version may hold fields this one drops, so it is
+// left as it is rather than saved back with the older schema.
+pub fn check_writable(path: &Path) -> Result<(), String> {
+    match file_version(path) {
+        Some(version) if version > CONFIG_SCHEMA_VERSION => Err(format!(
+            "{} was written by a newer version (schema {}), changes are not saved",
+            path.display(),
+            version
+        )),
+        _ => Ok(()),
+    }
+}
+
+// Keys whose values don't deserialize are dropped one at a time, so a single
+// bad setting falls back to its default instead of failing the whole file.
+fn from_map_lenient<T: DeserializeOwned>(mut config: Map<String, Value>) -> Result<(T, Vec<FieldError>), String> {
+    if let Ok(parsed) = serde_json::from_value(Value::Object(config.clone())) {
+        return Ok((parsed, vec![]));
+    }
+
+    let mut errors = Vec::new();
+    let keys: Vec<String> = config.keys().cloned().collect();
+    for key in keys {
+        let single = Map::from_iter([(key.clone(), config[&key].clone())]);
+        if let Err(e) = serde_json::from_value::<T>(Value::Object(single)) {
+            config.remove(&key);
+            errors.push(FieldError { field: key, message: e.to_string() });
+        }
+    }
+
+    let parsed = serde_json::from_value(Value::Object(config)).map_err(|e| e.to_string())?;
+    Ok((parsed, errors))
+}
+
+fn load_migrated<T: DeserializeOwned>(path: &Path, migrations: &[Migration]) -> Result<(T, Vec<FieldError>), String> {
+    let config_str = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let value: Value = serde_json::from_str(&config_str).map_err(|e| e.to_string())?;
+    let Value::Object(mut config) = value else {
+        return Err(format!("{} does not contain a JSON object", path.display()));
+    };
+
+    let version = config.get("schema_version")
+        .and_then(Value::as_u64)
+        .unwrap_or(0) as u32;
+
+    if (version as usize) < migrations.len() {
+        fs::copy(path, backup_path(path, version)).map_err(|e| e.to_string())?;
+
+        for migration in &migrations[version as usize..] {
+            migration(&mut config);
+        }
+        config.insert("schema_version".to_string(), Value::from(CONFIG_SCHEMA_VERSION));
+
+        let config_str = serde_json::to_string_pretty(&config).map_err(|e| e.to_string())?;
+        super::write_atomic(path, &config_str)?;
+    }
+
+    from_map_lenient(config)
+}
+
+pub fn load_global<T: DeserializeOwned>(path: &Path) -> Result<(T, Vec<FieldError>), String> {
+    load_migrated(path, GLOBAL_MIGRATIONS)
+}
+
+pub fn load_instance<T: DeserializeOwned>(path: &Path) -> Result<(T, Vec<FieldError>), String> {
+    load_migrated(path, INSTANCE_MIGRATIONS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::GlobalConfig;
+
+    #[test]
+    fn upgrades_old_files_and_keeps_a_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("global.json");
+        fs::write(&path, r#"{"font_size": 16}"#).unwrap();
+
+        let (config, errors): (GlobalConfig, _) = load_global(&path).unwrap();
+
+        assert!(errors.is_empty());
+        assert_eq!(config.font_size, Some(16));
+        assert_eq!(config.default_encoding.as_deref(), Some("utf-8"));
+        assert_eq!(file_version(&path), Some(CONFIG_SCHEMA_VERSION));
+        assert_eq!(fs::read_to_string(backup_path(&path, 0)).unwrap(), r#"{"font_size": 16}"#);
+    }
+
+    #[test]
+    fn drops_only_the_keys_that_do_not_deserialize() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("global.json");
+        fs::write(&path, r#"{"schema_version": 1, "font_size": "big", "word_wrap": true}"#).unwrap();
+
+        let (config, errors): (GlobalConfig, _) = load_global(&path).unwrap();
+
+        assert_eq!(config.font_size, None);
+        assert_eq!(config.word_wrap, Some(true));
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field, "font_size");
+    }
+
+    #[test]
+    fn rejects_files_that_are_not_objects() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("global.json");
+        fs::write(&path, "[1, 2]").unwrap();
+
+        assert!(load_global::<GlobalConfig>(&path).is_err());
+    }
+
+    #[test]
+    fn files_from_newer_versions_are_not_writable() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("global.json");
+        assert!(check_writable(&path).is_ok());
+
+        fs::write(&path, format!(r#"{{"schema_version": {}}}"#, CONFIG_SCHEMA_VERSION)).unwrap();
+        assert!(check_writable(&path).is_ok());
+
+        fs::write(&path, format!(r#"{{"schema_version": {}}}"#, CONFIG_SCHEMA_VERSION + 1)).unwrap();
+        assert!(check_writable(&path).is_err());
+    }
+}
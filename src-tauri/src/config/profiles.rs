@@ -0,0 +1,277 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use super::keybindings::KEYBINDINGS_FILE;
+use super::{GlobalConfig, GLOBAL_CONFIG_FILE};
+
+pub const PROFILE_FLAG: &str = "--profile";
+pub const PROFILE_ENV: &str = "FIROW_NOTEPAD_PROFILE";
+pub const DEFAULT_PROFILE: &str = "default";
+
+const PROFILES_DIR: &str = "profiles";
+// Kept next to the profiles directory rather than in it, where it could
+// clash with a profile of the same name.
+const ACTIVE_PROFILE_FILE: &str = "active-profile";
+
+// Files that make up a profile, relative to its directory. The default
+// profile keeps them in the FirowNotepad directory itself, so configs from
+// before profiles existed become the default profile as they are.
+pub const PROFILE_FILES: &[&str] = &[GLOBAL_CONFIG_FILE, KEYBINDINGS_FILE];
+
+// The profile this process uses. `requested` comes from `--profile` or the
+// environment and wins over the profile last switched to; `active` is
+// resolved on first use, once the config directory is known.
+pub struct ProfileState {
+    requested: Option<String>,
+    active: Mutex<Option<String>>,
+}
+
+impl ProfileState {
+    pub fn new(requested: Option<String>) -> Self {
+        Self {
+            requested,
+            active: Mutex::new(None),
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ProfileInfo {
+    pub name: String,
+    pub active: bool,
+}
+
+// A profile as written by `export_profile`. `files` maps the names in
+// `PROFILE_FILES` to their JSON content.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ProfileExport {
+    pub name: String,
+    pub files: Map<String, Value>,
+}
+
+// `--profile` wins over the environment variable. A requested profile that
+// doesn't exist yet is created with default settings.
+pub fn requested(args: &[String]) -> Option<String> {
+    super::location::flag_value(args, PROFILE_FLAG)
+        .or_else(|| std::env::var(PROFILE_ENV).ok())
+        .filter(|name| !name.is_empty())
+}
+
+pub fn validate_name(name: &str) -> Result<(), String> {
+    if !super::is_safe_file_name(name) {
+        return Err(format!("Invalid profile name: {}", name));
+    }
+    Ok(())
+}
+
+pub fn profile_dir(notepad_dir: &Path, name: &str) -> Result<PathBuf, String> {
+    validate_name(name)?;
+    if name == DEFAULT_PROFILE {
+        Ok(notepad_dir.to_path_buf())
+    } else {
+        Ok(notepad_dir.join(PROFILES_DIR).join(name))
+    }
+}
+
+pub fn exists(notepad_dir: &Path, name: &str) -> bool {
+    name == DEFAULT_PROFILE || profile_dir(notepad_dir, name).is_ok_and(|dir| dir.is_dir())
+}
+
+// The requested profile if there is one, else the one last switched to.
+// A pointer to a profile that was removed by hand falls back to the default.
+pub fn resolve(state: &ProfileState, notepad_dir: &Path) -> String {
+    let mut active = state.active.lock().unwrap();
+    if let Some(name) = active.as_ref() {
+        return name.clone();
+    }
+
+    let name = state.requested.clone()
+        .filter(|name| validate_name(name).is_ok())
+        .or_else(|| {
+            fs::read_to_string(notepad_dir.join(ACTIVE_PROFILE_FILE))
+                .ok()
+                .map(|name| name.trim().to_string())
+                .filter(|name| exists(notepad_dir, name))
+        })
+        .unwrap_or_else(|| DEFAULT_PROFILE.to_string());
+
+    *active = Some(name.clone());
+    name
+}
+
+pub fn set_active(state: &ProfileState, notepad_dir: &Path, name: &str) -> Result<(), String> {
+    super::write_atomic(&notepad_dir.join(ACTIVE_PROFILE_FILE), name)?;
+
+    *state.active.lock().unwrap() = Some(name.to_string());
+    Ok(())
+}
+
+pub fn list(notepad_dir: &Path, active: &str) -> Vec<ProfileInfo> {
+    let mut names: Vec<String> = fs::read_dir(notepad_dir.join(PROFILES_DIR))
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter(|entry| entry.path().is_dir())
+                .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                .filter(|name| name != DEFAULT_PROFILE && validate_name(name).is_ok())
+                .collect()
+        })
+        .unwrap_or_default();
+    names.sort_by_key(|name| name.to_lowercase());
+    names.insert(0, DEFAULT_PROFILE.to_string());
+
+    names
+        .into_iter()
+        .map(|name| ProfileInfo { active: name == active, name })
+        .collect()
+}
+
+fn create_dir(notepad_dir: &Path, name: &str) -> Result<PathBuf, String> {
+    if exists(notepad_dir, name) {
+        return Err(format!("Profile already exists: {}", name));
+    }
+    let dir = profile_dir(notepad_dir, name)?;
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir)
+}
+
+pub fn create(notepad_dir: &Path, name: &str) -> Result<(), String> {
+    let dir = create_dir(notepad_dir, name)?;
+    GlobalConfig::default().save_to_file(&dir.join(GLOBAL_CONFIG_FILE))
+}
+
+pub fn clone(notepad_dir: &Path, source: &str, name: &str) -> Result<(), String> {
+    let source_dir = profile_dir(notepad_dir, source)?;
+    if !exists(notepad_dir, source) {
+        return Err(format!("Profile not found: {}", source));
+    }
+
+    let dir = create_dir(notepad_dir, name)?;
+    for file in PROFILE_FILES {
+        let source_path = source_dir.join(file);
+        if source_path.exists() {
+            fs::copy(&source_path, dir.join(file)).map_err(|e| e.to_string())?;
+        }
+    }
+    if !dir.join(GLOBAL_CONFIG_FILE).exists() {
+        GlobalConfig::default().save_to_file(&dir.join(GLOBAL_CONFIG_FILE))?;
+    }
+    Ok(())
+}
+
+pub fn delete(notepad_dir: &Path, name: &str) -> Result<(), String> {
+    if name == DEFAULT_PROFILE {
+        return Err("The default profile cannot be deleted".to_string());
+    }
+    if !exists(notepad_dir, name) {
+        return Err(format!("Profile not found: {}", name));
+    }
+    fs::remove_dir_all(profile_dir(notepad_dir, name)?).map_err(|e| e.to_string())
+}
+
+pub fn export(notepad_dir: &Path, name: &str, path: &Path) -> Result<(), String> {
+    let dir = profile_dir(notepad_dir, name)?;
+    if !exists(notepad_dir, name) {
+        return Err(format!("Profile not found: {}", name));
+    }
+
+    let mut files = Map::new();
+    for file in PROFILE_FILES {
+        if let Ok(content) = fs::read_to_string(dir.join(file)) {
+            let value: Value = serde_json::from_str(&content)
+                .map_err(|e| format!("{}: {}", file, e))?;
+            files.insert(file.to_string(), value);
+        }
+    }
+
+    let export = ProfileExport { name: name.to_string(), files };
+    let export_str = serde_json::to_string_pretty(&export).map_err(|e| e.to_string())?;
+    super::write_atomic(path, &export_str)
+}
+
+// Imports an exported profile under `name`, or under the name it was
+// exported with. Everything is checked before the profile is created.
+pub fn import(notepad_dir: &Path, path: &Path, name: Option<&str>) -> Result<String, String> {
+    let export_str = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let export: ProfileExport = serde_json::from_str(&export_str)
+        .map_err(|e| format!("Not a profile export: {}", e))?;
+
+    let name = name.unwrap_or(&export.name).to_string();
+    validate_name(&name)?;
+
+    for (file, value) in &export.files {
+        if !PROFILE_FILES.contains(&file.as_str()) {
+            return Err(format!("Unexpected file in profile export: {}", file));
+        }
+        if file == GLOBAL_CONFIG_FILE {
+            super::validation::check_global_value(value)
+                .map_err(|e| format!("{}: {}", file, e))?;
+        }
+    }
+
+    let dir = create_dir(notepad_dir, &name)?;
+    for (file, value) in &export.files {
+        let content = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+        super::write_atomic(&dir.join(file), &content)?;
+    }
+    if !dir.join(GLOBAL_CONFIG_FILE).exists() {
+        GlobalConfig::default().save_to_file(&dir.join(GLOBAL_CONFIG_FILE))?;
+    }
+    Ok(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requested_profile_wins_over_the_last_switched_to() {
+        let dir = tempfile::tempdir().unwrap();
+        create(dir.path(), "work").unwrap();
+        create(dir.path(), "home").unwrap();
+        set_active(&ProfileState::new(None), dir.path(), "home").unwrap();
+
+        assert_eq!(resolve(&ProfileState::new(Some("work".to_string())), dir.path()), "work");
+        assert_eq!(resolve(&ProfileState::new(None), dir.path()), "home");
+        assert_eq!(resolve(&ProfileState::new(Some("../work".to_string())), dir.path()), "home");
+    }
+
+    #[test]
+    fn missing_profiles_fall_back_to_the_default() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(resolve(&ProfileState::new(None), dir.path()), DEFAULT_PROFILE);
+
+        fs::write(dir.path().join(ACTIVE_PROFILE_FILE), "removed").unwrap();
+        assert_eq!(resolve(&ProfileState::new(None), dir.path()), DEFAULT_PROFILE);
+    }
+
+    #[test]
+    fn resolved_profile_is_kept_until_switched() {
+        let dir = tempfile::tempdir().unwrap();
+        create(dir.path(), "work").unwrap();
+        let state = ProfileState::new(None);
+        assert_eq!(resolve(&state, dir.path()), DEFAULT_PROFILE);
+
+        fs::write(dir.path().join(ACTIVE_PROFILE_FILE), "work").unwrap();
+        assert_eq!(resolve(&state, dir.path()), DEFAULT_PROFILE);
+        set_active(&state, dir.path(), "work").unwrap();
+        assert_eq!(resolve(&state, dir.path()), "work");
+    }
+
+    #[test]
+    fn requested_reads_the_flag() {
+        let args: Vec<String> = ["app", "--profile", "work"].iter().map(|arg| arg.to_string()).collect();
+        assert_eq!(requested(&args).as_deref(), Some("work"));
+    }
+
+    #[test]
+    fn default_profile_lives_in_the_config_dir() {
+        let dir = Path::new("/config");
+        assert_eq!(profile_dir(dir, DEFAULT_PROFILE).unwrap(), dir);
+        assert_eq!(profile_dir(dir, "work").unwrap(), dir.join(PROFILES_DIR).join("work"));
+        assert!(profile_dir(dir, "../work").is_err());
+    }
+}
@@ -0,0 +1,175 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+pub const MAX_RECENT_FILES: usize = 100;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct RecentEntry {
+    pub open_count: u32,
+    pub last_used: i64,
+    pub pinned: bool,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct RecentFile {
+    pub path: String,
+    pub open_count: u32,
+    pub last_used: i64,
+    pub pinned: bool,
+    pub score: f64,
+}
+
+// Frecency in the style of Firefox's URL bar: every use counts, but recent
+// uses weigh more than old ones.
+fn frecency(entry: &RecentEntry, now: i64) -> f64 {
+    let age_hours = (now - entry.last_used).max(0) as f64 / 3_600_000.0;
+    let weight = match age_hours {
+        h if h < 4.0 => 100.0,
+        h if h < 24.0 => 80.0,
+        h if h < 24.0 * 7.0 => 60.0,
+        h if h < 24.0 * 30.0 => 40.0,
+        h if h < 24.0 * 90.0 => 20.0,
+        _ => 10.0,
+    };
+    entry.open_count.max(1) as f64 * weight
+}
+
+pub fn touch(entries: &mut HashMap<String, RecentEntry>, path: &str, now: i64) {
+    let entry = entries.entry(path.to_string()).or_default();
+    entry.open_count += 1;
+    entry.last_used = now;
+    trim(entries);
+}
+
+// Drops the oldest unpinned entries beyond `MAX_RECENT_FILES`.
+fn trim(entries: &mut HashMap<String, RecentEntry>) {
+    let unpinned = entries.values().filter(|entry| !entry.pinned).count();
+    if unpinned > MAX_RECENT_FILES {
+        let mut by_age: Vec<(String, i64)> = entries.iter()
+            .filter(|(_, entry)| !entry.pinned)
+            .map(|(path, entry)| (path.clone(), entry.last_used))
+            .collect();
+        by_age.sort_by_key(|(_, last_used)| *last_used);
+
+        for (path, _) in by_age.into_iter().take(unpinned - MAX_RECENT_FILES) {
+            entries.remove(&path);
+        }
+    }
+}
+
+// Removes unpinned entries whose file no longer exists. Returns whether
+// anything was removed.
+pub fn prune_missing(entries: &mut HashMap<String, RecentEntry>) -> bool {
+    let before = entries.len();
+    entries.retain(|path, entry| entry.pinned || Path::new(path).exists());
+    entries.len() != before
+}
+
+// Most recently used first; this is the order `recent_files` is stored in.
+pub fn by_recency(entries: &HashMap<String, RecentEntry>) -> Vec<String> {
+    let mut paths: Vec<(&String, &RecentEntry)> = entries.iter().collect();
+    paths.sort_by(|a, b| b.1.last_used.cmp(&a.1.last_used).then(a.0.cmp(b.0)));
+    paths.into_iter().map(|(path, _)| path.clone()).collect()
+}
+
+pub fn ranked(entries: &HashMap<String, RecentEntry>, filter: Option<&str>, now: i64) -> Vec<RecentFile> {
+    let filter = filter.map(|f| f.to_lowercase()).filter(|f| !f.is_empty());
+
+    let mut files: Vec<RecentFile> = entries.iter()
+        .filter(|(path, _)| filter.as_ref().is_none_or(|f| path.to_lowercase().contains(f)))
+        .map(|(path, entry)| RecentFile {
+            path: path.clone(),
+            open_count: entry.open_count,
+            last_used: entry.last_used,
+            pinned: entry.pinned,
+            score: frecency(entry, now),
+        })
+        .collect();
+
+    files.sort_by(|a, b| {
+        b.pinned.cmp(&a.pinned)
+            .then(b.score.total_cmp(&a.score))
+            .then(b.last_used.cmp(&a.last_used))
+    });
+    files
+}
+
+pub fn from_list(recent_files: &[String], now: i64) -> HashMap<String, RecentEntry> {
+    recent_files.iter()
+        .enumerate()
+        .map(|(index, path)| (path.clone(), RecentEntry {
+            open_count: 1,
+            last_used: now - index as i64,
+            pinned: false,
+        }))
+        .collect()
+}
+
+pub fn merge(entries: &mut HashMap<String, RecentEntry>, other: HashMap<String, RecentEntry>) {
+    for (path, theirs) in other {
+        let entry = entries.entry(path).or_default();
+        entry.open_count += theirs.open_count;
+        entry.last_used = entry.last_used.max(theirs.last_used);
+        entry.pinned |= theirs.pinned;
+    }
+    trim(entries);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: i64 = 3_600_000;
+
+    fn entry(open_count: u32, last_used: i64, pinned: bool) -> RecentEntry {
+        RecentEntry { open_count, last_used, pinned }
+    }
+
+    #[test]
+    fn recent_uses_weigh_more_than_old_ones() {
+        let now = 1000 * HOUR;
+        assert!(frecency(&entry(1, now - HOUR, false), now) > frecency(&entry(1, now - 48 * HOUR, false), now));
+        assert!(frecency(&entry(3, now - 48 * HOUR, false), now) > frecency(&entry(1, now - HOUR, false), now));
+    }
+
+    #[test]
+    fn ranked_puts_pinned_first_then_by_score() {
+        let now = 1000 * HOUR;
+        let entries = HashMap::from([
+            ("/a.txt".to_string(), entry(1, now - 200 * HOUR, true)),
+            ("/b.txt".to_string(), entry(5, now - HOUR, false)),
+            ("/c.txt".to_string(), entry(1, now - HOUR, false)),
+        ]);
+
+        let paths: Vec<String> = ranked(&entries, None, now).into_iter().map(|file| file.path).collect();
+        assert_eq!(paths, ["/a.txt", "/b.txt", "/c.txt"]);
+
+        let filtered: Vec<String> = ranked(&entries, Some("B.TXT"), now).into_iter().map(|file| file.path).collect();
+        assert_eq!(filtered, ["/b.txt"]);
+    }
+
+    #[test]
+    fn touch_trims_the_oldest_unpinned_entries() {
+        let mut entries = HashMap::from([("/pinned".to_string(), entry(1, 0, true))]);
+        for index in 0..=MAX_RECENT_FILES as i64 {
+            touch(&mut entries, &format!("/file{}", index), index + 1);
+        }
+
+        assert_eq!(entries.len(), MAX_RECENT_FILES + 1);
+        assert!(entries.contains_key("/pinned"));
+        assert!(!entries.contains_key("/file0"));
+        assert_eq!(entries["/file1"].open_count, 1);
+    }
+
+    #[test]
+    fn from_list_keeps_the_order_and_merge_adds_counts() {
+        let mut entries = from_list(&["/a".to_string(), "/b".to_string()], 100);
+        assert_eq!(by_recency(&entries), ["/a", "/b"]);
+
+        merge(&mut entries, HashMap::from([("/b".to_string(), entry(2, 200, true))]));
+        assert_eq!(by_recency(&entries), ["/b", "/a"]);
+        assert_eq!(entries["/b"].open_count, 3);
+        assert!(entries["/b"].pinned);
+    }
+}
@@ -0,0 +1,193 @@
+use chrono::Local;
+use serde::Serialize;
+use serde_json::Value;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use super::FieldError;
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ConfigRecovery {
+    pub file: String,
+    pub error: String,
+    pub quarantined_to: String,
+    pub restored_from: Option<String>,
+}
+
+// Writes to a sibling temp file and renames it over the target, so a crash
+// mid-write leaves either the old or the new file, never a truncated one.
+pub fn write_atomic(path: &Path, contents: &str) -> Result<(), String> {
+    let file_name = path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let tmp_path = path.with_file_name(format!("{}.tmp", file_name));
+
+    let mut file = fs::File::create(&tmp_path).map_err(|e| e.to_string())?;
+    file.write_all(contents.as_bytes()).map_err(|e| e.to_string())?;
+    file.sync_all().map_err(|e| e.to_string())?;
+    drop(file);
+
+    fs::rename(&tmp_path, path).map_err(|e| {
+        let _ = fs::remove_file(&tmp_path);
+        e.to_string()
+    })
+}
+
+fn known_good_path(path: &Path) -> PathBuf {
+    let file_name = path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!("{}.bak", file_name))
+}
+
+fn quarantine(path: &Path) -> Result<PathBuf, String> {
+    let file_name = path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let timestamp = Local::now().format("%Y%m%d_%H%M%S_%3f").to_string();
+    let mut quarantined = path.with_file_name(format!("{}.corrupt-{}", file_name, timestamp));
+    let mut counter = 1;
+    while quarantined.exists() {
+        quarantined = path.with_file_name(format!("{}.corrupt-{}-{}", file_name, timestamp, counter));
+        counter += 1;
+    }
+
+    fs::rename(path, &quarantined).map_err(|e| e.to_string())?;
+    Ok(quarantined)
+}
+
+// Loads a config file, which drops the keys it can't use and returns them as
+// field errors. Only a file that isn't a JSON object at all is quarantined.
+pub fn load_or_recover<T, F>(path: &Path, load: F) -> Result<(T, Vec<FieldError>, Option<ConfigRecovery>), String>
+where
+    T: Serialize + Default,
+    F: Fn(&Path) -> Result<(T, Vec<FieldError>), String>,
+{
+    let error = match load(path) {
+        Ok((config, errors)) => {
+            let _ = fs::copy(path, known_good_path(path));
+            return Ok((config, errors, None));
+        }
+        Err(error) => error,
+    };
+
+    // An unreadable file is an I/O problem, and a well-formed one failed for
+    // some other reason; neither is corruption, so leave it alone.
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(_) => return Err(error),
+    };
+    if serde_json::from_slice::<Value>(&bytes).is_ok_and(|value| value.is_object()) {
+        return Err(error);
+    }
+
+    let quarantined = quarantine(path)?;
+    let backup = known_good_path(path);
+
+    let restored = if backup.exists() {
+        fs::copy(&backup, path).ok().and_then(|_| load(path).ok())
+    } else {
+        None
+    };
+
+    let (config, errors, restored_from) = match restored {
+        Some((config, errors)) => (config, errors, Some(backup.to_string_lossy().into_owned())),
+        None => {
+            let config = T::default();
+            let config_str = serde_json::to_string_pretty(&config).map_err(|e| e.to_string())?;
+            write_atomic(path, &config_str)?;
+            (config, vec![], None)
+        }
+    };
+
+    Ok((config, errors, Some(ConfigRecovery {
+        file: path.to_string_lossy().into_owned(),
+        error,
+        quarantined_to: quarantined.to_string_lossy().into_owned(),
+        restored_from,
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{migration, GlobalConfig};
+
+    fn quarantined_files(dir: &Path) -> Vec<String> {
+        fs::read_dir(dir).unwrap()
+            .flatten()
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .filter(|name| name.contains(".corrupt-"))
+            .collect()
+    }
+
+    #[test]
+    fn write_atomic_replaces_the_file_without_leaving_a_temp_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        fs::write(&path, "old").unwrap();
+
+        write_atomic(&path, "new").unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        assert!(!dir.path().join("config.json.tmp").exists());
+    }
+
+    #[test]
+    fn malformed_json_is_quarantined_and_the_backup_restored() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("global.json");
+        fs::write(&path, r#"{"schema_version": 1, "font_size": 18}"#).unwrap();
+        load_or_recover::<GlobalConfig, _>(&path, migration::load_global).unwrap();
+
+        fs::write(&path, r#"{"font_size": 1"#).unwrap();
+        let (config, errors, recovery) = load_or_recover::<GlobalConfig, _>(&path, migration::load_global).unwrap();
+
+        assert_eq!(config.font_size, Some(18));
+        assert!(errors.is_empty());
+        let recovery = recovery.unwrap();
+        assert!(recovery.restored_from.is_some());
+        assert_eq!(fs::read_to_string(&recovery.quarantined_to).unwrap(), r#"{"font_size": 1"#);
+    }
+
+    #[test]
+    fn malformed_json_without_backup_falls_back_to_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("global.json");
+        fs::write(&path, "not json").unwrap();
+
+        let (config, _, recovery) = load_or_recover::<GlobalConfig, _>(&path, migration::load_global).unwrap();
+
+        assert_eq!(config.font_size, GlobalConfig::default().font_size);
+        assert!(recovery.unwrap().restored_from.is_none());
+        assert!(GlobalConfig::from_file(&path).is_ok());
+    }
+
+    #[test]
+    fn bad_values_are_dropped_without_quarantine() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("global.json");
+        fs::write(&path, r#"{"schema_version": 1, "font_size": "big", "word_wrap": true}"#).unwrap();
+
+        let (config, errors, recovery) = load_or_recover::<GlobalConfig, _>(&path, migration::load_global).unwrap();
+
+        assert!(recovery.is_none());
+        assert_eq!(config.word_wrap, Some(true));
+        assert_eq!(errors.len(), 1);
+        assert!(quarantined_files(dir.path()).is_empty());
+    }
+
+    #[test]
+    fn quarantined_names_do_not_collide() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("global.json");
+
+        for _ in 0..3 {
+            fs::write(&path, "not json").unwrap();
+            quarantine(&path).unwrap();
+        }
+
+        assert_eq!(quarantined_files(dir.path()).len(), 3);
+    }
+}
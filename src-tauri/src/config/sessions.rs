@@ -0,0 +1,177 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::FileViewState;
+
+pub const SESSIONS_DIR: &str = "sessions";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SessionFile {
+    pub path: String,
+    pub view_state: Option<FileViewState>,
+}
+
+// `files` is in tab order.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Session {
+    pub name: String,
+    pub files: Vec<SessionFile>,
+    pub active_file: Option<String>,
+    pub saved_at: i64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct SessionSummary {
+    pub name: String,
+    pub file_count: usize,
+    pub active_file: Option<String>,
+    pub saved_at: i64,
+}
+
+pub fn validate_name(name: &str) -> Result<(), String> {
+    if !super::is_safe_file_name(name) {
+        return Err(format!("Invalid session name: {}", name));
+    }
+    Ok(())
+}
+
+fn session_path(sessions_dir: &Path, name: &str) -> Result<PathBuf, String> {
+    validate_name(name)?;
+    Ok(sessions_dir.join(format!("{}.json", name)))
+}
+
+pub fn load(sessions_dir: &Path, name: &str) -> Result<Session, String> {
+    let path = session_path(sessions_dir, name)?;
+    if !path.exists() {
+        return Err(format!("Session not found: {}", name));
+    }
+
+    let session_str = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    serde_json::from_str(&session_str).map_err(|e| e.to_string())
+}
+
+pub fn save(sessions_dir: &Path, session: &Session) -> Result<(), String> {
+    let path = session_path(sessions_dir, &session.name)?;
+    let session_str = serde_json::to_string_pretty(session).map_err(|e| e.to_string())?;
+    super::write_atomic(&path, &session_str)
+}
+
+pub fn list(sessions_dir: &Path) -> Vec<SessionSummary> {
+    let entries = match fs::read_dir(sessions_dir) {
+        Ok(entries) => entries,
+        Err(_) => return vec![],
+    };
+
+    let mut sessions: Vec<SessionSummary> = entries
+        .flatten()
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|entry| {
+            let session_str = fs::read_to_string(entry.path()).ok()?;
+            let session: Session = serde_json::from_str(&session_str).ok()?;
+            Some(SessionSummary {
+                name: session.name,
+                file_count: session.files.len(),
+                active_file: session.active_file,
+                saved_at: session.saved_at,
+            })
+        })
+        .collect();
+
+    sessions.sort_by_key(|session| session.name.to_lowercase());
+    sessions
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+pub fn rename(sessions_dir: &Path, name: &str, new_name: &str) -> Result<(), String> {
+    if name == new_name {
+        return Ok(());
+    }
+
+    let mut session = load(sessions_dir, name)?;
+    let old_path = session_path(sessions_dir, name)?;
+    let new_path = session_path(sessions_dir, new_name)?;
+
+    // A change of case only has to rename the file, which on case-insensitive
+    // filesystems already "exists" under the new name. On case-sensitive ones
+    // both names are separate sessions, and the paths resolve differently.
+    let case_only = name.eq_ignore_ascii_case(new_name) && same_file(&old_path, &new_path);
+    if case_only {
+        fs::rename(&old_path, &new_path).map_err(|e| e.to_string())?;
+    } else if new_path.exists() {
+        return Err(format!("Session already exists: {}", new_name));
+    }
+
+    session.name = new_name.to_string();
+    save(sessions_dir, &session)?;
+
+    if !case_only {
+        fs::remove_file(&old_path).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+pub fn delete(sessions_dir: &Path, name: &str) -> Result<(), String> {
+    let path = session_path(sessions_dir, name)?;
+    fs::remove_file(&path).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(name: &str) -> Session {
+        Session { name: name.to_string(), files: vec![], active_file: None, saved_at: 0 }
+    }
+
+    #[test]
+    fn rename_moves_the_session() {
+        let dir = tempfile::tempdir().unwrap();
+        save(dir.path(), &session("work")).unwrap();
+
+        rename(dir.path(), "work", "home").unwrap();
+
+        assert!(load(dir.path(), "work").is_err());
+        assert_eq!(load(dir.path(), "home").unwrap().name, "home");
+    }
+
+    #[test]
+    fn rename_does_not_overwrite_another_session() {
+        let dir = tempfile::tempdir().unwrap();
+        save(dir.path(), &session("work")).unwrap();
+        save(dir.path(), &session("home")).unwrap();
+
+        assert!(rename(dir.path(), "work", "home").is_err());
+        assert_eq!(list(dir.path()).len(), 2);
+    }
+
+    #[test]
+    fn case_only_rename_keeps_a_session_differing_in_case() {
+        let dir = tempfile::tempdir().unwrap();
+        save(dir.path(), &session("Work")).unwrap();
+        let case_sensitive = !dir.path().join("work.json").exists();
+
+        if case_sensitive {
+            save(dir.path(), &session("work")).unwrap();
+            assert!(rename(dir.path(), "Work", "work").is_err());
+            assert_eq!(list(dir.path()).len(), 2);
+        } else {
+            rename(dir.path(), "Work", "work").unwrap();
+            assert_eq!(load(dir.path(), "work").unwrap().name, "work");
+        }
+    }
+
+    #[test]
+    fn names_that_escape_the_directory_are_rejected() {
+        for name in ["", "../evil", "a/b", ".hidden", "trailing.", "con:"] {
+            assert!(validate_name(name).is_err(), "{}", name);
+        }
+        assert!(validate_name("Work 2").is_ok());
+    }
+}
@@ -0,0 +1,96 @@
+use fs4::fs_std::FileExt;
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::Path;
+
+// Advisory lock shared by every instance writing the global config. It lives
+// in a separate file because the config itself is replaced on each write.
+pub struct GlobalConfigLock {
+    file: fs::File,
+}
+
+impl GlobalConfigLock {
+    pub fn acquire(config_path: &Path) -> Result<Self, String> {
+        let file_name = config_path.file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let lock_path = config_path.with_file_name(format!("{}.lock", file_name));
+
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)
+            .map_err(|e| e.to_string())?;
+        file.lock_exclusive().map_err(|e| e.to_string())?;
+
+        Ok(Self { file })
+    }
+}
+
+impl Drop for GlobalConfigLock {
+    fn drop(&mut self) {
+        let _ = FileExt::unlock(&self.file);
+    }
+}
+
+// Applies to `on_disk` only the keys that differ between `baseline` (what this
+// instance last read or wrote) and `updated`, so concurrent edits of other
+// keys by other instances survive.
+pub fn merge_changed_keys(baseline: &Map<String, Value>, updated: &Map<String, Value>, on_disk: &mut Map<String, Value>) -> bool {
+    let mut changed = false;
+
+    for (key, value) in updated {
+        if baseline.get(key) != Some(value) {
+            on_disk.insert(key.clone(), value.clone());
+            changed = true;
+        }
+    }
+
+    for key in baseline.keys() {
+        if !updated.contains_key(key) && on_disk.remove(key).is_some() {
+            changed = true;
+        }
+    }
+
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn map(value: Value) -> Map<String, Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn only_changed_keys_overwrite_the_file() {
+        let baseline = map(json!({"font_size": 14, "word_wrap": false}));
+        let updated = map(json!({"font_size": 16, "word_wrap": false}));
+        let mut on_disk = map(json!({"font_size": 14, "word_wrap": true}));
+
+        assert!(merge_changed_keys(&baseline, &updated, &mut on_disk));
+        assert_eq!(on_disk, map(json!({"font_size": 16, "word_wrap": true})));
+    }
+
+    #[test]
+    fn removed_keys_are_removed_from_the_file() {
+        let baseline = map(json!({"font_size": 14, "colorscheme": "Firow"}));
+        let updated = map(json!({"font_size": 14}));
+        let mut on_disk = map(json!({"font_size": 14, "colorscheme": "Firow", "autosave": "off"}));
+
+        assert!(merge_changed_keys(&baseline, &updated, &mut on_disk));
+        assert_eq!(on_disk, map(json!({"font_size": 14, "autosave": "off"})));
+    }
+
+    #[test]
+    fn nothing_changed_leaves_the_file_alone() {
+        let baseline = map(json!({"font_size": 14}));
+        let mut on_disk = map(json!({"font_size": 20}));
+
+        assert!(!merge_changed_keys(&baseline, &baseline.clone(), &mut on_disk));
+        assert_eq!(on_disk, map(json!({"font_size": 20})));
+    }
+}
@@ -0,0 +1,358 @@
+use globset::Glob;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs;
+use std::path::Path;
+
+use super::{keybindings, languages, FileViewState, GlobalConfig, InstanceConfig, LanguageSettings, WatchedFolder};
+
+pub const MIN_FONT_SIZE: i32 = 6;
+pub const MAX_FONT_SIZE: i32 = 72;
+pub const AUTOSAVE_MODES: &[&str] = &["off", "delay", "focus_loss", "window_close"];
+
+// The JSON Schemas describing the same rules, written next to the config
+// files so editors can complete and lint them.
+pub const GLOBAL_SCHEMA_FILE: &str = "firow-notepad-global.schema.json";
+pub const INSTANCE_SCHEMA_FILE: &str = "firow-notepad-instance.schema.json";
+pub const GLOBAL_SCHEMA: &str = include_str!("../../schemas/firow-notepad-global.schema.json");
+pub const INSTANCE_SCHEMA: &str = include_str!("../../schemas/firow-notepad-instance.schema.json");
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+// Errors found in a config file on load. The offending values are replaced
+// by their defaults in memory; the file keeps them until the next save.
+#[derive(Serialize, Clone, Debug)]
+pub struct ConfigValidation {
+    pub file: String,
+    pub errors: Vec<FieldError>,
+}
+
+pub fn write_schemas(dir: &Path) -> Result<(), String> {
+    for (file, schema) in [(GLOBAL_SCHEMA_FILE, GLOBAL_SCHEMA), (INSTANCE_SCHEMA_FILE, INSTANCE_SCHEMA)] {
+        let path = dir.join(file);
+        if fs::read_to_string(&path).ok().as_deref() != Some(schema) {
+            super::write_atomic(&path, schema)?;
+        }
+    }
+    Ok(())
+}
+
+// For global configs that come from outside, such as imported profiles.
+pub fn check_global_value(value: &Value) -> Result<(), String> {
+    let config: GlobalConfig = serde_json::from_value(value.clone()).map_err(|e| e.to_string())?;
+    let errors = validate_global(&config);
+    if !errors.is_empty() {
+        return Err(describe(&errors));
+    }
+    Ok(())
+}
+
+pub fn check_keybindings_value(value: &Value) -> Result<(), String> {
+    let bindings: HashMap<String, String> = serde_json::from_value(value.clone()).map_err(|e| e.to_string())?;
+    let report = keybindings::resolve(None, Some(&bindings), keybindings::is_macos());
+    if !report.errors.is_empty() {
+        return Err(describe(&report.errors));
+    }
+    Ok(())
+}
+
+pub fn describe(errors: &[FieldError]) -> String {
+    errors
+        .iter()
+        .map(|error| format!("{}: {}", error.field, error.message))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+// Collects errors and, when `fix` is set, resets each invalid value to
+// `default` as it goes.
+struct Checker {
+    fix: bool,
+    errors: Vec<FieldError>,
+}
+
+impl Checker {
+    fn fail<T>(&mut self, field: &str, message: String, value: &mut Option<T>, default: Option<T>) {
+        self.errors.push(FieldError { field: field.to_string(), message });
+        if self.fix {
+            *value = default;
+        }
+    }
+
+    fn range<T: PartialOrd + Copy + Display>(&mut self, field: &str, value: &mut Option<T>, min: T, max: T, default: Option<T>) {
+        if let Some(current) = *value {
+            if !(min..=max).contains(&current) {
+                self.fail(field, format!("must be between {} and {}", min, max), value, default);
+            }
+        }
+    }
+
+    fn one_of(&mut self, field: &str, value: &mut Option<String>, allowed: &[&str], default: Option<String>) {
+        if let Some(current) = value.as_deref() {
+            if !allowed.contains(&current) {
+                self.fail(field, format!("must be one of {}", allowed.join(", ")), value, default);
+            }
+        }
+    }
+
+    fn non_empty(&mut self, field: &str, value: &mut Option<String>, default: Option<String>) {
+        if value.as_deref().is_some_and(|current| current.trim().is_empty()) {
+            self.fail(field, "must not be empty".to_string(), value, default);
+        }
+    }
+
+    fn encoding(&mut self, field: &str, value: &mut Option<String>, default: Option<String>) {
+        if let Some(current) = value.as_deref() {
+            if encoding_rs::Encoding::for_label(current.as_bytes()).is_none() {
+                self.fail(field, format!("unknown encoding {}", current), value, default);
+            }
+        }
+    }
+
+    fn paths(&mut self, field: &str, value: &mut Option<Vec<String>>) {
+        if let Some(paths) = value {
+            if paths.iter().any(|path| path.trim().is_empty()) {
+                self.errors.push(FieldError { field: field.to_string(), message: "must not contain empty paths".to_string() });
+                if self.fix {
+                    paths.retain(|path| !path.trim().is_empty());
+                }
+            }
+        }
+    }
+}
+
+fn check_language(checker: &mut Checker, key: &str, settings: &mut LanguageSettings) {
+    let field = |name: &str| format!("languages.{}.{}", key, name);
+
+    checker.range(&field("tab_size"), &mut settings.tab_size, 1, 16, None);
+    checker.range(&field("font_size"), &mut settings.font_size, MIN_FONT_SIZE, MAX_FONT_SIZE, None);
+    checker.one_of(&field("default_eol"), &mut settings.default_eol, &["LF", "CRLF"], None);
+
+    if let Some(rulers) = &mut settings.rulers {
+        if rulers.iter().any(|column| !(1..=500).contains(column)) {
+            checker.errors.push(FieldError { field: field("rulers"), message: "columns must be between 1 and 500".to_string() });
+            if checker.fix {
+                rulers.retain(|column| (1..=500).contains(column));
+            }
+        }
+    }
+}
+
+fn check_global(config: &mut GlobalConfig, fix: bool) -> Vec<FieldError> {
+    let defaults = GlobalConfig::default();
+    let mut checker = Checker { fix, errors: Vec::new() };
+
+    checker.non_empty("colorscheme", &mut config.colorscheme, defaults.colorscheme);
+    checker.non_empty("monaco_editor_theme", &mut config.monaco_editor_theme, defaults.monaco_editor_theme);
+    checker.range("font_size", &mut config.font_size, MIN_FONT_SIZE, MAX_FONT_SIZE, defaults.font_size);
+    checker.range("window_opacity", &mut config.window_opacity, 0.1, 1.0, defaults.window_opacity);
+    checker.encoding("default_encoding", &mut config.default_encoding, defaults.default_encoding);
+    checker.range("instance_retention_days", &mut config.instance_retention_days, 0, 3650, defaults.instance_retention_days);
+    checker.one_of("autosave", &mut config.autosave, AUTOSAVE_MODES, defaults.autosave);
+    checker.range("autosave_delay_ms", &mut config.autosave_delay_ms, 100, 600_000, defaults.autosave_delay_ms);
+
+    if let Some(languages) = &mut config.languages {
+        let mut invalid_globs = Vec::new();
+        for (key, settings) in languages.iter_mut() {
+            if key.trim().is_empty() || (languages::is_glob(key) && Glob::new(key).is_err()) {
+                invalid_globs.push(key.clone());
+                continue;
+            }
+            check_language(&mut checker, key, settings);
+        }
+        for key in invalid_globs {
+            checker.errors.push(FieldError { field: format!("languages.{}", key), message: "is not a language id or a valid glob".to_string() });
+            if fix {
+                languages.remove(&key);
+            }
+        }
+    }
+
+    if let Some(bindings) = &mut config.keybindings {
+        let mut commands: Vec<String> = bindings.keys().cloned().collect();
+        commands.sort();
+        for command in commands {
+            if let Err(message) = keybindings::check_binding(&command, &bindings[&command], keybindings::is_macos()) {
+                checker.errors.push(FieldError { field: format!("keybindings.{}", command), message });
+                if fix {
+                    bindings.remove(&command);
+                }
+            }
+        }
+    }
+
+    checker.errors
+}
+
+fn check_watched_folder(checker: &mut Checker, index: usize, folder: &WatchedFolder) -> bool {
+    let field = |name: &str| format!("watched_folders[{}].{}", index, name);
+
+    if folder.path.trim().is_empty() {
+        checker.errors.push(FieldError { field: field("path"), message: "must not be empty".to_string() });
+        return false;
+    }
+    if let Some(pattern) = folder.pattern.as_deref().filter(|pattern| !pattern.is_empty()) {
+        if let Err(e) = Glob::new(pattern) {
+            checker.errors.push(FieldError { field: field("pattern"), message: e.to_string() });
+            return false;
+        }
+    }
+    true
+}
+
+fn check_file_state(checker: &mut Checker, path: &str, state: &mut FileViewState) {
+    let field = |name: &str| format!("file_states.{}.{}", path, name);
+
+    checker.encoding(&field("encoding"), &mut state.encoding, None);
+    checker.one_of(&field("eol"), &mut state.eol, &["LF", "CRLF", "CR"], None);
+
+    if state.scroll_top.is_some_and(|top| !top.is_finite() || top < 0.0) {
+        checker.fail(&field("scroll_top"), "must be a non-negative number".to_string(), &mut state.scroll_top, None);
+    }
+    if state.cursor.as_ref().is_some_and(|cursor| cursor.line == 0 || cursor.column == 0) {
+        checker.fail(&field("cursor"), "line and column start at 1".to_string(), &mut state.cursor, None);
+    }
+}
+
+fn check_instance(config: &mut InstanceConfig, fix: bool) -> Vec<FieldError> {
+    let mut checker = Checker { fix, errors: Vec::new() };
+
+    checker.paths("recent_files", &mut config.recent_files);
+    checker.paths("opened_files", &mut config.opened_files);
+
+    if let Some(folders) = &mut config.watched_folders {
+        let valid: Vec<bool> = folders
+            .iter()
+            .enumerate()
+            .map(|(index, folder)| check_watched_folder(&mut checker, index, folder))
+            .collect();
+        if fix {
+            let mut valid = valid.into_iter();
+            folders.retain(|_| valid.next().unwrap_or(true));
+        }
+    }
+
+    if let Some(states) = &mut config.file_states {
+        for (path, state) in states.iter_mut() {
+            check_file_state(&mut checker, path, state);
+        }
+    }
+
+    if let Some(entries) = &mut config.recent_entries {
+        if entries.keys().any(|path| path.trim().is_empty()) {
+            checker.errors.push(FieldError { field: "recent_entries".to_string(), message: "must not contain empty paths".to_string() });
+            if fix {
+                entries.retain(|path, _| !path.trim().is_empty());
+            }
+        }
+    }
+
+    checker.errors
+}
+
+pub fn validate_global(config: &GlobalConfig) -> Vec<FieldError> {
+    check_global(&mut config.clone(), false)
+}
+
+pub fn validate_instance(config: &InstanceConfig) -> Vec<FieldError> {
+    check_instance(&mut config.clone(), false)
+}
+
+// Replaces invalid values by their defaults and returns what was wrong.
+pub fn sanitize_global(config: &mut GlobalConfig) -> Vec<FieldError> {
+    check_global(config, true)
+}
+
+pub fn sanitize_instance(config: &mut InstanceConfig) -> Vec<FieldError> {
+    check_instance(config, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(errors: &[FieldError]) -> Vec<&str> {
+        errors.iter().map(|error| error.field.as_str()).collect()
+    }
+
+    #[test]
+    fn defaults_are_valid() {
+        assert!(validate_global(&GlobalConfig::default()).is_empty());
+        assert!(validate_instance(&InstanceConfig::default()).is_empty());
+    }
+
+    #[test]
+    fn sanitize_global_resets_invalid_values_to_defaults() {
+        let mut config = GlobalConfig {
+            font_size: Some(200),
+            window_opacity: Some(0.0),
+            default_encoding: Some("klingon".to_string()),
+            autosave: Some("sometimes".to_string()),
+            word_wrap: Some(true),
+            ..GlobalConfig::default()
+        };
+
+        let errors = sanitize_global(&mut config);
+
+        assert_eq!(fields(&errors), ["font_size", "window_opacity", "default_encoding", "autosave"]);
+        let defaults = GlobalConfig::default();
+        assert_eq!(config.font_size, defaults.font_size);
+        assert_eq!(config.window_opacity, defaults.window_opacity);
+        assert_eq!(config.default_encoding, defaults.default_encoding);
+        assert_eq!(config.autosave, defaults.autosave);
+        assert_eq!(config.word_wrap, Some(true));
+    }
+
+    #[test]
+    fn validate_global_leaves_the_config_alone() {
+        let config = GlobalConfig { font_size: Some(200), ..GlobalConfig::default() };
+        assert_eq!(fields(&validate_global(&config)), ["font_size"]);
+        assert_eq!(config.font_size, Some(200));
+    }
+
+    #[test]
+    fn invalid_language_globs_are_removed() {
+        let settings = LanguageSettings { tab_size: Some(2), ..LanguageSettings::default() };
+        let mut config = GlobalConfig {
+            languages: Some(HashMap::from([
+                ("markdown".to_string(), settings.clone()),
+                ("*.{md".to_string(), settings.clone()),
+                ("*.rs".to_string(), LanguageSettings { tab_size: Some(40), ..settings }),
+            ])),
+            ..GlobalConfig::default()
+        };
+
+        let mut errors = fields(&sanitize_global(&mut config)).into_iter().map(str::to_string).collect::<Vec<_>>();
+        errors.sort();
+
+        assert_eq!(errors, ["languages.*.rs.tab_size", "languages.*.{md"]);
+        let languages = config.languages.unwrap();
+        assert!(languages.contains_key("markdown"));
+        assert!(!languages.contains_key("*.{md"));
+        assert_eq!(languages["*.rs"].tab_size, None);
+    }
+
+    #[test]
+    fn sanitize_instance_drops_empty_paths_and_bad_folders() {
+        let mut config = InstanceConfig {
+            opened_files: Some(vec!["/a.txt".to_string(), " ".to_string()]),
+            watched_folders: Some(vec![
+                WatchedFolder { path: "/logs".to_string(), pattern: Some("*.log".to_string()), follow: None, window: None },
+                WatchedFolder { path: "/dumps".to_string(), pattern: Some("[".to_string()), follow: None, window: None },
+            ]),
+            ..InstanceConfig::default()
+        };
+
+        let errors = sanitize_instance(&mut config);
+
+        assert_eq!(fields(&errors), ["opened_files", "watched_folders[1].pattern"]);
+        assert_eq!(config.opened_files.unwrap(), ["/a.txt"]);
+        assert_eq!(config.watched_folders.unwrap().len(), 1);
+    }
+}
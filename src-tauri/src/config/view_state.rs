@@ -0,0 +1,103 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+pub const MAX_FILE_VIEW_STATES: usize = 500;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CursorPosition {
+    pub line: u32,
+    pub column: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SelectionRange {
+    pub start_line: u32,
+    pub start_column: u32,
+    pub end_line: u32,
+    pub end_column: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FoldedRange {
+    pub start_line: u32,
+    pub end_line: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct FileViewState {
+    pub cursor: Option<CursorPosition>,
+    pub selections: Option<Vec<SelectionRange>>,
+    pub scroll_top: Option<f64>,
+    pub folded_ranges: Option<Vec<FoldedRange>>,
+    pub encoding: Option<String>,
+    pub eol: Option<String>,
+    pub language: Option<String>,
+    pub read_only: Option<bool>,
+    pub last_used: Option<i64>,
+}
+
+pub fn canonical_key(path: &str) -> String {
+    std::fs::canonicalize(path)
+        .ok()
+        .and_then(|canonical| canonical.to_str().map(|s| s.to_string()))
+        .unwrap_or_else(|| path.to_string())
+}
+
+// Drops the least recently used entries once the cap is exceeded.
+pub fn prune(states: &mut HashMap<String, FileViewState>) {
+    if states.len() <= MAX_FILE_VIEW_STATES {
+        return;
+    }
+
+    let mut by_age: Vec<(String, i64)> = states.iter()
+        .map(|(path, state)| (path.clone(), state.last_used.unwrap_or(0)))
+        .collect();
+    by_age.sort_by_key(|(_, last_used)| *last_used);
+
+    let excess = states.len() - MAX_FILE_VIEW_STATES;
+    for (path, _) in by_age.into_iter().take(excess) {
+        states.remove(&path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn used_at(last_used: i64) -> FileViewState {
+        FileViewState { last_used: Some(last_used), ..Default::default() }
+    }
+
+    #[test]
+    fn prune_keeps_the_most_recently_used_states() {
+        let mut states: HashMap<String, FileViewState> = (0..MAX_FILE_VIEW_STATES + 3)
+            .map(|index| (format!("/file{}", index), used_at(index as i64 + 1)))
+            .collect();
+        states.insert("/never-used".to_string(), FileViewState::default());
+
+        prune(&mut states);
+        assert_eq!(states.len(), MAX_FILE_VIEW_STATES);
+        assert!(!states.contains_key("/never-used"));
+        assert!(!states.contains_key("/file2"));
+        assert!(states.contains_key("/file3"));
+    }
+
+    #[test]
+    fn prune_leaves_states_under_the_cap_alone() {
+        let mut states = HashMap::from([("/a".to_string(), FileViewState::default())]);
+        prune(&mut states);
+        assert_eq!(states.len(), 1);
+    }
+
+    #[test]
+    fn canonical_key_falls_back_to_the_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.txt");
+        std::fs::write(&path, "").unwrap();
+        let indirect = dir.path().join(".").join("a.txt");
+
+        let expected = std::fs::canonicalize(&path).unwrap();
+        assert_eq!(canonical_key(indirect.to_str().unwrap()), expected.to_str().unwrap());
+        assert_eq!(canonical_key("/does/not/exist.txt"), "/does/not/exist.txt");
+    }
+}
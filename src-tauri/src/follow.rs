@@ -0,0 +1,356 @@
+use encoding_rs::{Decoder, Encoding};
+use notify::{Event, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{Emitter, Manager};
+
+const MAX_FOLLOW_CHUNK: u64 = 100 * 1024 * 1024;
+// A truncated or replaced file is only sent from this many bytes before its
+// end, rather than all of it.
+const MAX_RESET_TAIL: u64 = 1024 * 1024;
+
+pub struct FollowState {
+    followers: HashMap<String, Follower>,
+}
+
+struct Follower {
+    _watcher: notify::RecommendedWatcher,
+    windows: HashSet<String>,
+}
+
+impl FollowState {
+    pub fn new() -> Self {
+        Self {
+            followers: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Clone)]
+struct FileAppended {
+    path: String,
+    content: String,
+    offset: u64,
+    reset: bool,
+}
+
+struct FollowCursor {
+    path: PathBuf,
+    offset: u64,
+    identity: Option<(u64, u64)>,
+    encoding: &'static Encoding,
+    decoder: Decoder,
+}
+
+impl FollowCursor {
+    fn new(path: PathBuf, encoding: &'static Encoding) -> Result<Self, String> {
+        let metadata = fs::metadata(&path).map_err(|e| e.to_string())?;
+
+        Ok(Self {
+            identity: file_identity(&path, &metadata),
+            path,
+            offset: metadata.len(),
+            encoding,
+            decoder: encoding.new_decoder_without_bom_handling(),
+        })
+    }
+
+    // Returns the text appended since the last poll. A truncated or replaced
+    // file is read again from at most `MAX_RESET_TAIL` bytes before its end
+    // and reported with `reset` set.
+    fn poll(&mut self) -> Result<Option<FileAppended>, String> {
+        let metadata = match fs::metadata(&self.path) {
+            Ok(metadata) => metadata,
+            Err(_) => return Ok(None),
+        };
+
+        // An identity that can't be read, as while a writer holds the file
+        // without sharing it, is taken to be unchanged.
+        let identity = file_identity(&self.path, &metadata);
+        let replaced = identity.is_some() && self.identity.is_some() && identity != self.identity;
+        if identity.is_some() {
+            self.identity = identity;
+        }
+        let len = metadata.len();
+        let mut reset = false;
+
+        if replaced || len < self.offset {
+            self.offset = len.saturating_sub(MAX_RESET_TAIL);
+            self.decoder = if self.offset == 0 {
+                self.encoding.new_decoder()
+            } else {
+                self.encoding.new_decoder_without_bom_handling()
+            };
+            reset = true;
+        }
+
+        if len - self.offset > MAX_FOLLOW_CHUNK {
+            self.offset = len - MAX_FOLLOW_CHUNK;
+            self.decoder = self.encoding.new_decoder_without_bom_handling();
+            reset = true;
+        }
+
+        if len == self.offset && !reset {
+            return Ok(None);
+        }
+
+        let mut file = fs::File::open(&self.path).map_err(|e| e.to_string())?;
+        file.seek(SeekFrom::Start(self.offset)).map_err(|e| e.to_string())?;
+
+        let mut bytes = Vec::new();
+        file.take(len - self.offset)
+            .read_to_end(&mut bytes)
+            .map_err(|e| e.to_string())?;
+
+        let capacity = self.decoder
+            .max_utf8_buffer_length(bytes.len())
+            .unwrap_or(bytes.len() * 3);
+        let mut content = String::with_capacity(capacity);
+        let _ = self.decoder.decode_to_string(&bytes, &mut content, false);
+
+        self.offset += bytes.len() as u64;
+
+        Ok(Some(FileAppended {
+            path: self.path.to_string_lossy().into_owned(),
+            content,
+            offset: self.offset,
+            reset,
+        }))
+    }
+}
+
+#[cfg(unix)]
+fn file_identity(_path: &Path, metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+// The volume serial and file index play the part of device and inode. The
+// creation time can't be used: NTFS tunneling gives a file recreated under
+// the same name shortly after a rename the old file's creation time.
+#[cfg(windows)]
+fn file_identity(path: &Path, _metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::Storage::FileSystem::{GetFileInformationByHandle, BY_HANDLE_FILE_INFORMATION};
+
+    let file = fs::File::open(path).ok()?;
+    unsafe {
+        let mut info: BY_HANDLE_FILE_INFORMATION = std::mem::zeroed();
+        if GetFileInformationByHandle(file.as_raw_handle() as _, &mut info) == 0 {
+            return None;
+        }
+        let index = ((info.nFileIndexHigh as u64) << 32) | info.nFileIndexLow as u64;
+        Some((info.dwVolumeSerialNumber as u64, index))
+    }
+}
+
+fn following_windows(app_handle: &tauri::AppHandle, path: &str) -> Vec<String> {
+    let state = app_handle.state::<Mutex<FollowState>>();
+    let state = state.lock().unwrap();
+    state.followers.get(path)
+        .map(|follower| follower.windows.iter().cloned().collect())
+        .unwrap_or_default()
+}
+
+// Followers that are let go are dropped after the lock is released, since
+// dropping a watcher can wait for its callback, which takes the lock too.
+pub fn release_window(app_handle: &tauri::AppHandle, label: &str) {
+    let released: Vec<Follower> = {
+        let state = app_handle.state::<Mutex<FollowState>>();
+        let mut state = state.lock().unwrap();
+
+        for follower in state.followers.values_mut() {
+            follower.windows.remove(label);
+        }
+        let unused: Vec<String> = state.followers.iter()
+            .filter(|(_, follower)| follower.windows.is_empty())
+            .map(|(path, _)| path.clone())
+            .collect();
+        unused.iter().filter_map(|path| state.followers.remove(path)).collect()
+    };
+    drop(released);
+}
+
+// Followed paths with the labels of the windows following them.
+pub fn followed(app_handle: &tauri::AppHandle) -> Vec<(String, Vec<String>)> {
+    let state = app_handle.state::<Mutex<FollowState>>();
+    let state = state.lock().unwrap();
+    state.followers.iter()
+        .map(|(path, follower)| (path.clone(), follower.windows.iter().cloned().collect()))
+        .collect()
+}
+
+pub fn is_followed(app_handle: &tauri::AppHandle, path: &Path) -> bool {
+    let state = app_handle.state::<Mutex<FollowState>>();
+    let state = state.lock().unwrap();
+    state.followers.keys().any(|followed| Path::new(followed) == path)
+}
+
+#[tauri::command]
+pub fn follow_file(path: String, encoding: Option<String>, window: tauri::Window) -> Result<(), String> {
+    let app_handle = window.app_handle();
+    let state = app_handle.state::<Mutex<FollowState>>();
+    let mut state = state.lock().unwrap();
+
+    if let Some(follower) = state.followers.get_mut(&path) {
+        follower.windows.insert(window.label().to_string());
+        return Ok(());
+    }
+
+    let file_path = PathBuf::from(&path);
+    let parent = file_path.parent()
+        .ok_or_else(|| format!("Cannot follow {}: no parent directory", path))?
+        .to_path_buf();
+
+    let mut cursor = FollowCursor::new(file_path.clone(), crate::resolve_encoding(encoding.as_deref()))?;
+
+    // The parent directory is watched instead of the file itself so that a
+    // rotated log (renamed away and recreated) keeps being followed.
+    let handle = app_handle.clone();
+    let followed_path = path.clone();
+    let mut watcher = notify::recommended_watcher(move |res: Result<Event, notify::Error>| {
+        match res {
+            Ok(event) => {
+                if !event.paths.iter().any(|p| p == &file_path) {
+                    return;
+                }
+                match cursor.poll() {
+                    Ok(Some(appended)) => {
+                        crate::watcher::append_snapshot(&handle, &followed_path, &appended.content, appended.reset);
+                        for label in following_windows(&handle, &followed_path) {
+                            let _ = handle.emit_to(label.as_str(), "file-appended", appended.clone());
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(e) => println!("Follow error: {}", e),
+                }
+            }
+            Err(e) => println!("Follow error: {:?}", e),
+        }
+    }).map_err(|e| e.to_string())?;
+
+    watcher.watch(&parent, RecursiveMode::NonRecursive)
+        .map_err(|e| e.to_string())?;
+
+    let mut windows = HashSet::new();
+    windows.insert(window.label().to_string());
+    state.followers.insert(path, Follower { _watcher: watcher, windows });
+    Ok(())
+}
+
+#[tauri::command]
+pub fn unfollow_file(path: String, window: tauri::Window) -> Result<(), String> {
+    let app_handle = window.app_handle();
+    let released = {
+        let state = app_handle.state::<Mutex<FollowState>>();
+        let mut state = state.lock().unwrap();
+
+        match state.followers.get_mut(&path) {
+            Some(follower) => {
+                follower.windows.remove(window.label());
+                if follower.windows.is_empty() {
+                    state.followers.remove(&path)
+                } else {
+                    None
+                }
+            }
+            None => None,
+        }
+    };
+    drop(released);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cursor(path: &Path) -> FollowCursor {
+        FollowCursor::new(path.to_path_buf(), encoding_rs::UTF_8).unwrap()
+    }
+
+    fn append(path: &Path, text: &str) {
+        use std::io::Write;
+        let mut file = fs::OpenOptions::new().append(true).open(path).unwrap();
+        file.write_all(text.as_bytes()).unwrap();
+    }
+
+    #[test]
+    fn poll_returns_only_what_was_appended() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        fs::write(&path, "one\n").unwrap();
+        let mut cursor = cursor(&path);
+        assert!(cursor.poll().unwrap().is_none());
+
+        append(&path, "two\n");
+        let appended = cursor.poll().unwrap().unwrap();
+        assert_eq!(appended.content, "two\n");
+        assert_eq!(appended.offset, 8);
+        assert!(!appended.reset);
+        assert!(cursor.poll().unwrap().is_none());
+    }
+
+    #[test]
+    fn poll_keeps_characters_split_across_appends() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        fs::write(&path, "").unwrap();
+        let mut cursor = cursor(&path);
+
+        let bytes = "é".as_bytes();
+        fs::write(&path, &bytes[..1]).unwrap();
+        assert_eq!(cursor.poll().unwrap().unwrap().content, "");
+        fs::write(&path, bytes).unwrap();
+        assert_eq!(cursor.poll().unwrap().unwrap().content, "é");
+    }
+
+    #[test]
+    fn truncated_file_is_read_again_with_reset() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        fs::write(&path, "a long first line\n").unwrap();
+        let mut cursor = cursor(&path);
+
+        fs::write(&path, "new\n").unwrap();
+        let appended = cursor.poll().unwrap().unwrap();
+        assert_eq!(appended.content, "new\n");
+        assert!(appended.reset);
+    }
+
+    #[test]
+    fn replaced_file_is_read_again_with_reset() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        fs::write(&path, "old\n").unwrap();
+        let mut cursor = cursor(&path);
+
+        let rotated = dir.path().join("app.log.new");
+        fs::write(&rotated, "rotated and longer\n").unwrap();
+        fs::rename(&rotated, &path).unwrap();
+        let appended = cursor.poll().unwrap().unwrap();
+        assert_eq!(appended.content, "rotated and longer\n");
+        assert!(appended.reset);
+    }
+
+    #[test]
+    fn reset_only_sends_the_tail_of_a_large_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        fs::write(&path, "x".repeat(10)).unwrap();
+        let mut cursor = cursor(&path);
+
+        fs::write(&path, "").unwrap();
+        cursor.poll().unwrap();
+        let rotated = dir.path().join("app.log.new");
+        fs::write(&rotated, "y".repeat(MAX_RESET_TAIL as usize + 100)).unwrap();
+        fs::rename(&rotated, &path).unwrap();
+        let appended = cursor.poll().unwrap().unwrap();
+        assert_eq!(appended.content.len() as u64, MAX_RESET_TAIL);
+        assert!(appended.reset);
+    }
+}
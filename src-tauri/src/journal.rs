@@ -0,0 +1,169 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::Manager;
+
+use crate::config::{write_atomic, ConfigManager};
+
+const JOURNAL_DIR: &str = "recovery";
+
+// Each run journals into its own directory, named `<instance id>@<start ms>`,
+// so a restarted instance never overwrites what the previous run left behind.
+pub struct JournalState {
+    run_dir: Option<PathBuf>,
+}
+
+impl JournalState {
+    pub fn new() -> Self {
+        Self {
+            run_dir: None,
+        }
+    }
+}
+
+// A dirty or untitled buffer. `path` keeps the association with the file the
+// buffer was opened from; `saved_hash` is the hash of that file's content
+// when the buffer was last in sync with it.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct JournalEntry {
+    pub buffer_id: String,
+    pub path: Option<String>,
+    pub name: String,
+    pub content: String,
+    pub encoding: Option<String>,
+    pub language: Option<String>,
+    pub saved_hash: Option<String>,
+    #[serde(default)]
+    pub updated_at: i64,
+}
+
+fn journal_root(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let journal_root = ConfigManager::get_notepad_md_dir(app_handle)?.join(JOURNAL_DIR);
+    if !journal_root.exists() {
+        fs::create_dir_all(&journal_root).map_err(|e| e.to_string())?;
+    }
+    Ok(journal_root)
+}
+
+fn run_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let state = app_handle.state::<Mutex<JournalState>>();
+    let mut state = state.lock().unwrap();
+
+    if let Some(run_dir) = &state.run_dir {
+        return Ok(run_dir.clone());
+    }
+
+    let (instance_id, started_at) = ConfigManager::current_run(app_handle)?;
+    let run_dir = journal_root(app_handle)?.join(format!("{}@{}", instance_id, started_at));
+    fs::create_dir_all(&run_dir).map_err(|e| e.to_string())?;
+
+    state.run_dir = Some(run_dir.clone());
+    Ok(run_dir)
+}
+
+// Buffer ids come from the frontend and end up in file names.
+fn entry_file_name(buffer_id: &str) -> Result<String, String> {
+    if buffer_id.is_empty() || !buffer_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(format!("Invalid buffer id: {}", buffer_id));
+    }
+    Ok(format!("{}.json", buffer_id))
+}
+
+// Journals left by runs that are over: earlier runs of this instance and
+// instances that are no longer running.
+fn orphaned_run_dirs(app_handle: &tauri::AppHandle) -> Result<Vec<PathBuf>, String> {
+    let own_run_dir = run_dir(app_handle)?;
+    let (own_instance_id, _) = ConfigManager::current_run(app_handle)?;
+    let entries = fs::read_dir(journal_root(app_handle)?).map_err(|e| e.to_string())?;
+
+    Ok(entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir() && path != &own_run_dir)
+        .filter(|path| {
+            let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+            let instance_id = name.rsplit_once('@').map(|(id, _)| id).unwrap_or(&name);
+            instance_id == own_instance_id || !ConfigManager::is_instance_running(app_handle, instance_id)
+        })
+        .collect())
+}
+
+// Removes this run's journal if nothing is left in it. Dirty buffers that are
+// still journaled at exit are kept so they can be offered on the next start.
+pub fn finish_run(app_handle: &tauri::AppHandle) {
+    let state = app_handle.state::<Mutex<JournalState>>();
+    let state = state.lock().unwrap();
+
+    if let Some(run_dir) = &state.run_dir {
+        let _ = fs::remove_dir(run_dir);
+    }
+}
+
+#[tauri::command]
+pub fn journal_buffer(app_handle: tauri::AppHandle, mut entry: JournalEntry) -> Result<(), String> {
+    let file_name = entry_file_name(&entry.buffer_id)?;
+    entry.updated_at = chrono::Local::now().timestamp_millis();
+
+    let entry_str = serde_json::to_string(&entry).map_err(|e| e.to_string())?;
+    write_atomic(&run_dir(&app_handle)?.join(file_name), &entry_str)
+}
+
+#[tauri::command]
+pub fn clear_journal_buffer(app_handle: tauri::AppHandle, buffer_id: String) -> Result<(), String> {
+    let entry_path = run_dir(&app_handle)?.join(entry_file_name(&buffer_id)?);
+    if entry_path.exists() {
+        fs::remove_file(&entry_path).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn list_recovered_buffers(app_handle: tauri::AppHandle) -> Result<Vec<JournalEntry>, String> {
+    let mut recovered = Vec::new();
+
+    for run_dir in orphaned_run_dirs(&app_handle)? {
+        let entries = match fs::read_dir(&run_dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        for entry in entries.flatten() {
+            if entry.path().extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            let parsed = fs::read_to_string(entry.path())
+                .ok()
+                .and_then(|entry_str| serde_json::from_str::<JournalEntry>(&entry_str).ok());
+            match parsed {
+                Some(parsed) => recovered.push(parsed),
+                None => println!("Skipping unreadable journal entry {}", entry.path().display()),
+            }
+        }
+    }
+
+    recovered.sort_by_key(|entry| entry.updated_at);
+    Ok(recovered)
+}
+
+// Called once the user has restored or declined the recovered buffers.
+// Restored buffers are journaled again under this run.
+#[tauri::command]
+pub fn discard_recovered_buffers(app_handle: tauri::AppHandle) -> Result<(), String> {
+    for run_dir in orphaned_run_dirs(&app_handle)? {
+        fs::remove_dir_all(&run_dir).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entry_file_names_only_come_from_safe_buffer_ids() {
+        assert_eq!(entry_file_name("untitled-1_a").unwrap(), "untitled-1_a.json");
+        for buffer_id in ["", "../escape", "a/b", "a\\b", "a.b", "é"] {
+            assert!(entry_file_name(buffer_id).is_err(), "{}", buffer_id);
+        }
+    }
+}
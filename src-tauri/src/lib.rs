@@ -2,37 +2,46 @@
 use std::fs;
 use std::process::Command;
 use std::sync::Mutex;
-use notify::{Watcher, RecursiveMode, Event};
-use std::collections::HashMap;
-use std::path::PathBuf;
 use tauri::Manager;
 use tauri::Emitter;
 use serde::{Deserialize, Serialize};
 use sha2::{Sha256, Digest};
 use std::io::ErrorKind;
 
+mod autosave;
+mod colorschemes;
 mod config;
-use config::{Storage, ConfigManager};
+mod follow;
+mod journal;
+mod replace;
+mod search;
+mod themes;
+mod watcher;
+use autosave::AutosaveState;
+use colorschemes::ColorSchemeState;
+use config::{Storage, ConfigManager, ProfileState};
+use follow::FollowState;
+use journal::JournalState;
+use search::SearchState;
+use themes::ThemeState;
+use watcher::{FolderWatchState, WatcherState};
 use chrono::Local;
 
-struct WatcherState {
-    watchers: HashMap<String, notify::RecommendedWatcher>,
-}
-
-impl WatcherState {
-    fn new() -> Self {
-        Self {
-            watchers: HashMap::new(),
-        }
-    }
-}
-
 fn calculate_file_hash(content: &str) -> String {
     let mut hasher = Sha256::new();
     hasher.update(content.as_bytes());
     format!("{:x}", hasher.finalize())
 }
 
+fn resolve_encoding(encoding: Option<&str>) -> &'static encoding_rs::Encoding {
+    match encoding.map(|enc| enc.to_uppercase()).as_deref() {
+        Some("UTF-16LE") => encoding_rs::UTF_16LE,
+        Some("UTF-16BE") => encoding_rs::UTF_16BE,
+        Some("WINDOWS-1252") => encoding_rs::WINDOWS_1252,
+        _ => encoding_rs::UTF_8,
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 struct FileData {
     content: String,
@@ -40,8 +49,7 @@ struct FileData {
 }
 
 
-#[tauri::command]
-fn read_file(path: &str, encoding: Option<String>) -> Result<FileData, String> {
+fn read_file_content(path: &str, encoding: Option<&str>) -> Result<String, String> {
     let metadata = fs::metadata(path).map_err(|e| {
         if e.kind() == ErrorKind::PermissionDenied {
             return format!("PERMISSION_DENIED: {}", e);
@@ -61,28 +69,35 @@ fn read_file(path: &str, encoding: Option<String>) -> Result<FileData, String> {
         e.to_string()
     })?;
     
-    let content = if let Some(enc) = encoding {
-        match enc.to_uppercase().as_str() {
-            "UTF-8" | "UTF8" => String::from_utf8_lossy(&bytes).into_owned(),
-            "UTF-16LE" => {
-                let (decoded, _, _) = encoding_rs::UTF_16LE.decode(&bytes);
-                decoded.into_owned()
-            },
-            "UTF-16BE" => {
-                let (decoded, _, _) = encoding_rs::UTF_16BE.decode(&bytes);
-                decoded.into_owned()
-            },
-            "WINDOWS-1252" => {
-                let (decoded, _, _) = encoding_rs::WINDOWS_1252.decode(&bytes);
-                decoded.into_owned()
-            },
-            _ => String::from_utf8_lossy(&bytes).into_owned(),
+    let content = match resolve_encoding(encoding) {
+        enc if enc == encoding_rs::UTF_8 => String::from_utf8_lossy(&bytes).into_owned(),
+        enc => {
+            let (decoded, _, _) = enc.decode(&bytes);
+            decoded.into_owned()
         }
-    } else {
-        String::from_utf8_lossy(&bytes).into_owned()
     };
 
+    Ok(content)
+}
+
+fn file_modified_secs(path: &str) -> Result<u64, String> {
+    let metadata = fs::metadata(path).map_err(|e| e.to_string())?;
+    
+    let modified = metadata.modified()
+        .map_err(|e| e.to_string())?
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_err(|e| e.to_string())?
+        .as_secs();
+    
+    Ok(modified)
+}
+
+#[tauri::command]
+fn read_file(path: &str, encoding: Option<String>, app_handle: tauri::AppHandle) -> Result<FileData, String> {
+    let content = read_file_content(path, encoding.as_deref())?;
     let hash = calculate_file_hash(&content);
+
+    watcher::remember_snapshot(&app_handle, path, encoding, &content, &hash);
     
     Ok(FileData { content, hash })
 }
@@ -92,14 +107,46 @@ fn calculate_file_hash_command(content: &str) -> String {
     calculate_file_hash(content)
 }
 
-#[tauri::command]
-fn save_file(path: &str, content: &str) -> Result<(), String> {
-    fs::write(path, content).map_err(|e| {
+fn encode_content(content: &str, encoding: Option<&str>) -> Vec<u8> {
+    // encoding_rs only decodes UTF-16, so it is encoded by hand, with the BOM
+    // that decoding relies on.
+    match resolve_encoding(encoding) {
+        enc if enc == encoding_rs::UTF_16LE => [0xFF, 0xFE].into_iter()
+            .chain(content.encode_utf16().flat_map(|unit| unit.to_le_bytes()))
+            .collect(),
+        enc if enc == encoding_rs::UTF_16BE => [0xFE, 0xFF].into_iter()
+            .chain(content.encode_utf16().flat_map(|unit| unit.to_be_bytes()))
+            .collect(),
+        enc => enc.encode(content).0.into_owned(),
+    }
+}
+
+// Shared by manual saves and autosave. With `expected_hash` set, the write is
+// refused if the file on disk no longer matches what the buffer was based on.
+fn write_file_content(app_handle: &tauri::AppHandle, path: &str, content: &str, encoding: Option<&str>, expected_hash: Option<&str>) -> Result<String, String> {
+    if let Some(expected_hash) = expected_hash.filter(|hash| !hash.is_empty()) {
+        if fs::metadata(path).is_ok() {
+            let on_disk = read_file_content(path, encoding)?;
+            if calculate_file_hash(&on_disk) != expected_hash {
+                return Err(format!("CONFLICT: {} was changed on disk", path));
+            }
+        }
+    }
+
+    fs::write(path, encode_content(content, encoding)).map_err(|e| {
         if e.kind() == ErrorKind::PermissionDenied {
             return format!("PERMISSION_DENIED: {}", e);
         }
         e.to_string()
-    })
+    })?;
+
+    watcher::update_snapshot(app_handle, path, content);
+    Ok(calculate_file_hash(content))
+}
+
+#[tauri::command]
+fn save_file(path: &str, content: &str, encoding: Option<String>, expected_hash: Option<String>, app_handle: tauri::AppHandle) -> Result<String, String> {
+    write_file_content(&app_handle, path, content, encoding.as_deref(), expected_hash.as_deref())
 }
 
 #[tauri::command]
@@ -148,15 +195,35 @@ fn run_explorer(path: &str) -> Result<(), String> {
     Ok(())
 }
 
+// New windows and elevated relaunches are separate processes, so the config
+// location and the active profile are passed to them on the command line.
+fn config_args(app_handle: &tauri::AppHandle) -> Vec<String> {
+    use config::location::ConfigSource;
+
+    let mut args = Vec::new();
+    let location = app_handle.state::<config::ConfigLocation>();
+    // A portable directory is found again by the new process.
+    let explicit = matches!(location.source, ConfigSource::Flag | ConfigSource::Env);
+    if let Some(dir) = location.dir.as_ref().filter(|_| explicit) {
+        args.push(format!("{}={}", config::location::CONFIG_DIR_FLAG, dir.display()));
+    }
+    if let Ok(profile) = ConfigManager::active_profile(app_handle) {
+        args.push(format!("{}={}", config::profiles::PROFILE_FLAG, profile));
+    }
+    args
+}
+
 #[tauri::command]
-fn open_in_new_window(path: &str) -> Result<(), String> {
+fn open_in_new_window(app_handle: tauri::AppHandle, path: &str) -> Result<(), String> {
     let current_exe = std::env::current_exe()
         .map_err(|e| format!("Failed to get current executable: {}", e))?;
+    let mut args = config_args(&app_handle);
+    args.extend(["--no-single-instance".to_string(), path.to_string()]);
     
     #[cfg(target_os = "windows")]
     {
         Command::new(current_exe)
-            .args(["--no-single-instance", path])
+            .args(&args)
             .spawn()
             .map_err(|e| format!("Failed to open new window: {}", e))?;
     }
@@ -164,7 +231,7 @@ fn open_in_new_window(path: &str) -> Result<(), String> {
     #[cfg(target_os = "macos")]
     {
         Command::new(current_exe)
-            .args(["--no-single-instance", path])
+            .args(&args)
             .spawn()
             .map_err(|e| format!("Failed to open new window: {}", e))?;
     }
@@ -172,7 +239,7 @@ fn open_in_new_window(path: &str) -> Result<(), String> {
     #[cfg(target_os = "linux")]
     {
         Command::new(current_exe)
-            .args(["--no-single-instance", path])
+            .args(&args)
             .spawn()
             .map_err(|e| format!("Failed to open new window: {}", e))?;
     }
@@ -180,93 +247,6 @@ fn open_in_new_window(path: &str) -> Result<(), String> {
     Ok(())
 }
 
-#[tauri::command]
-fn watch_file(path: String, window: tauri::Window) -> Result<(), String> {
-    let app_handle = window.app_handle();
-    let state = app_handle.state::<Mutex<WatcherState>>();
-    let mut state = state.lock().unwrap();
-
-    if state.watchers.contains_key(&path) {
-        return Ok(());
-    }
-
-    let window_clone = window.clone();
-    let mut watcher = notify::recommended_watcher(move |res: Result<Event, notify::Error>| {
-        match res {
-            Ok(event) => {
-                if let notify::EventKind::Modify(_) = event.kind {
-                    if let Some(path) = event.paths.first() {
-                        let _ = window_clone.emit("file-changed", path.to_str().unwrap_or(""));
-                    }
-                }
-            }
-            Err(e) => println!("Watch error: {:?}", e),
-        }
-    }).map_err(|e| e.to_string())?;
-
-    watcher.watch(&PathBuf::from(&path), RecursiveMode::NonRecursive)
-        .map_err(|e| e.to_string())?;
-
-    state.watchers.insert(path, watcher);
-    Ok(())
-}
-
-#[tauri::command]
-fn unwatch_file(path: String, window: tauri::Window) -> Result<(), String> {
-    let app_handle = window.app_handle();
-    let state = app_handle.state::<Mutex<WatcherState>>();
-    let mut state = state.lock().unwrap();
-    
-    state.watchers.remove(&path);
-    Ok(())
-}
-
-#[tauri::command]
-fn get_monaco_themes(app_handle: tauri::AppHandle) -> Result<Vec<String>, String> {
-    let notepad_dir = config::ConfigManager::get_notepad_md_dir(&app_handle)?;
-    let themes_dir = notepad_dir.join("monaco-editor");
-    
-    if !themes_dir.exists() {
-        fs::create_dir_all(&themes_dir).map_err(|e| e.to_string())?;
-    }
-    
-    let mut themes = vec!["vs".to_string(), "vs-dark".to_string(), "hc-black".to_string()];
-    
-    if themes_dir.exists() {
-        let entries = fs::read_dir(&themes_dir).map_err(|e| e.to_string())?;
-        for entry in entries {
-            if let Ok(entry) = entry {
-                let path = entry.path();
-                if path.extension().and_then(|s| s.to_str()) == Some("json") {
-                    if let Some(filename) = path.file_stem() {
-                        if let Some(theme_name) = filename.to_str() {
-                            themes.push(theme_name.to_string());
-                        }
-                    }
-                }
-            }
-        }
-    }
-    
-    Ok(themes)
-}
-
-#[tauri::command]
-fn read_monaco_theme(app_handle: tauri::AppHandle, theme_name: String) -> Result<String, String> {
-    if theme_name == "vs" || theme_name == "vs-dark" || theme_name == "hc-black" {
-        return Ok(String::new());
-    }
-    
-    let notepad_dir = config::ConfigManager::get_notepad_md_dir(&app_handle)?;
-    let theme_path = notepad_dir.join("monaco-editor").join(format!("{}.json", theme_name));
-    
-    if !theme_path.exists() {
-        return Err(format!("Theme file not found: {}", theme_name));
-    }
-    
-    fs::read_to_string(theme_path).map_err(|e| e.to_string())
-}
-
 #[tauri::command]
 fn check_admin_privileges() -> bool {
     #[cfg(target_os = "windows")]
@@ -302,22 +282,17 @@ fn check_admin_privileges() -> bool {
 
 #[tauri::command]
 fn get_file_metadata(path: &str) -> Result<u64, String> {
-    let metadata = fs::metadata(path).map_err(|e| e.to_string())?;
-    
-    let modified = metadata.modified()
-        .map_err(|e| e.to_string())?
-        .duration_since(std::time::UNIX_EPOCH)
-        .map_err(|e| e.to_string())?
-        .as_secs();
-    
-    Ok(modified)
+    file_modified_secs(path)
 }
 
 #[tauri::command]
-fn relaunch_as_admin(args: Vec<String>) -> Result<(), String> {
+fn relaunch_as_admin(app_handle: tauri::AppHandle, args: Vec<String>) -> Result<(), String> {
     let current_exe = std::env::current_exe()
         .map_err(|e| format!("Failed to get current executable: {}", e))?;
     
+    // Elevation doesn't keep the environment either.
+    let args: Vec<String> = config_args(&app_handle).into_iter().chain(args).collect();
+    
     #[cfg(target_os = "windows")]
     {
         use std::os::windows::process::CommandExt;
@@ -391,7 +366,18 @@ pub fn run() {
     let mut skip_single_instance = false;
     let mut instance_id = String::from("main");
 
-    for arg in cli_args.iter().skip(1) {
+    // Set before any thread is started, as WebView2 only reads it from the
+    // environment.
+    let config_location = config::location::resolve(&cli_args);
+    if let Some(webview_dir) = config_location.dir.as_deref().and_then(config::location::webview_data_dir) {
+        std::env::set_var("WEBVIEW2_USER_DATA_FOLDER", webview_dir);
+    }
+    let profile = config::profiles::requested(&cli_args);
+
+    for (index, arg) in cli_args.iter().enumerate().skip(1) {
+        if config::location::is_flag_arg(&cli_args, index) {
+            continue;
+        }
         if arg == "--no-single-instance" {
             skip_single_instance = true;
             let timestamp = Local::now().format("%Y%m%d_%H%M%S_%3f").to_string();
@@ -413,8 +399,14 @@ pub fn run() {
             builder = builder.plugin(tauri_plugin_single_instance::init(|app, argv, _cwd| {
                 let _ = ConfigManager::load_config(&app);
                 
-                for path in argv.iter().skip(1) {
-                    if path != "--no-single-instance" {
+                if let Some(profile) = config::location::flag_value(&argv, config::profiles::PROFILE_FLAG) {
+                    if let Err(e) = ConfigManager::switch_profile(app, &profile) {
+                        println!("Failed to switch profile: {}", e);
+                    }
+                }
+                
+                for (index, path) in argv.iter().enumerate().skip(1) {
+                    if path != "--no-single-instance" && !config::location::is_flag_arg(&argv, index) {
                         if let Ok(canonical_path) = std::fs::canonicalize(path) {
                             if canonical_path.exists() {
                                 if let Some(path_str) = canonical_path.to_str() {
@@ -433,12 +425,39 @@ pub fn run() {
 
     let app = builder
         .manage(Mutex::new(WatcherState::new()))
+        .manage(Mutex::new(FollowState::new()))
+        .manage(Mutex::new(FolderWatchState::new()))
+        .manage(Mutex::new(JournalState::new()))
+        .manage(Mutex::new(AutosaveState::new()))
+        .manage(Mutex::new(ThemeState::new()))
+        .manage(Mutex::new(ColorSchemeState::new()))
+        .manage(Mutex::new(SearchState::new()))
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_opener::init())
         .manage(Storage::with_instance_id(instance_id.clone()))
+        .manage(config_location)
+        .manage(ProfileState::new(profile))
+        .on_window_event(|window, event| {
+            autosave::handle_window_event(window, event);
+            if let tauri::WindowEvent::Destroyed = event {
+                watcher::release_window(window.app_handle(), window.label());
+                follow::release_window(window.app_handle(), window.label());
+            }
+        })
         .setup(move |app| {
             let _ = ConfigManager::set_instance_id(&app.handle(), instance_id);
             let _ = ConfigManager::load_config(&app.handle());
+            let _ = ConfigManager::watch_global_config(app.handle());
+            watcher::restore_watched_folders(app.handle());
+            if let Err(e) = themes::watch_themes(app.handle()) {
+                println!("Failed to watch Monaco themes: {}", e);
+            }
+            if let Err(e) = colorschemes::watch_colorschemes(app.handle()) {
+                println!("Failed to watch color schemes: {}", e);
+            }
+            if let Err(e) = ConfigManager::cleanup_instances(app.handle()) {
+                println!("Failed to clean up instance configs: {}", e);
+            }
             
             for file_path in files_to_open {
                 let _ = ConfigManager::add_to_opened_files(&app.handle(), file_path);
@@ -448,8 +467,44 @@ pub fn run() {
         })
         .invoke_handler(tauri::generate_handler![
             config::get_config,
+            config::get_config_location,
             config::load_config,
             config::save_config,
+            config::add_to_recent_files,
+            config::remove_from_recent_files,
+            config::pin_recent_file,
+            config::clear_recent_files,
+            config::get_recent_files,
+            config::remove_from_opened_files,
+            config::clear_opened_files,
+            config::list_instances,
+            config::merge_instance,
+            config::delete_instance,
+            config::cleanup_instances,
+            config::save_session,
+            config::list_sessions,
+            config::switch_session,
+            config::rename_session,
+            config::delete_session,
+            config::list_profiles,
+            config::create_profile,
+            config::clone_profile,
+            config::switch_profile,
+            config::delete_profile,
+            config::export_profile,
+            config::import_profile,
+            config::export_settings_bundle,
+            config::preview_settings_bundle,
+            config::import_settings_bundle,
+            config::get_keybindings,
+            config::validate_keybindings,
+            config::take_config_recoveries,
+            config::take_config_validations,
+            config::validate_config,
+            config::resolve_editor_settings,
+            config::get_file_view_state,
+            config::save_file_view_state,
+            config::forget_file_view_state,
             read_file,
             calculate_file_hash_command,
             run_explorer,
@@ -457,15 +512,43 @@ pub fn run() {
             save_file,
             rename_file,
             delete_file,
-            watch_file,
-            unwatch_file,
-            get_monaco_themes,
-            read_monaco_theme,
+            watcher::watch_file,
+            watcher::unwatch_file,
+            watcher::list_watches,
+            watcher::watch_folder,
+            watcher::unwatch_folder,
+            watcher::get_watched_folders,
+            follow::follow_file,
+            follow::unfollow_file,
+            autosave::update_autosave_buffer,
+            autosave::clear_autosave_buffer,
+            journal::journal_buffer,
+            journal::clear_journal_buffer,
+            journal::list_recovered_buffers,
+            journal::discard_recovered_buffers,
+            themes::get_monaco_themes,
+            themes::read_monaco_theme,
+            themes::import_monaco_theme,
+            colorschemes::get_colorschemes,
+            colorschemes::read_colorscheme,
+            colorschemes::derive_colorscheme,
+            search::search_in_folder,
+            search::cancel_search,
+            replace::preview_replace_in_folder,
+            replace::apply_replace_in_folder,
+            replace::get_replace_journals,
+            replace::undo_replace,
             check_admin_privileges,
             relaunch_as_admin,
             get_file_metadata
         ]);
     
-    app.run(tauri::generate_context!())
-        .expect("error while running tauri application");
+    app.build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app_handle, event| {
+            if let tauri::RunEvent::Exit = event {
+                journal::finish_run(app_handle);
+                let _ = ConfigManager::mark_instance_closed(app_handle);
+            }
+        });
 }
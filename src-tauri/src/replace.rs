@@ -0,0 +1,553 @@
+use ignore::{WalkBuilder, WalkState};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use similar::TextDiff;
+use std::collections::HashSet;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Instant;
+use tauri::ipc::Channel;
+
+use crate::config::{is_safe_file_name, write_atomic, ConfigManager};
+use crate::search::{self, SearchMatch, SearchOptions, SearchSummary, TextFile};
+
+const JOURNAL_DIR: &str = "replace-journal";
+const JOURNAL_FILE: &str = "journal.json";
+const STAGING_SUFFIX: &str = ".firow-replace.tmp";
+
+// Only the most recent operations can be undone.
+const MAX_JOURNALS: usize = 20;
+
+const DIFF_CONTEXT: usize = 2;
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct ReplaceOptions {
+    #[serde(flatten)]
+    pub search: SearchOptions,
+    // With `regex`, `$1`, `${1}` and `${name}` insert capture groups and `$$`
+    // a dollar sign. Otherwise the text is inserted as is.
+    pub replacement: String,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ReplaceMatch {
+    #[serde(flatten)]
+    pub found: SearchMatch,
+    pub replacement: String,
+}
+
+// A file the replace would change. `hash` is that of its content when the
+// preview was made; applying refuses to touch the file if it changed since.
+#[derive(Serialize, Clone, Debug)]
+pub struct FilePreview {
+    pub path: String,
+    pub encoding: String,
+    pub hash: String,
+    pub matches: Vec<ReplaceMatch>,
+    pub diff: String,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ReplaceEvent {
+    File(FilePreview),
+    Done(SearchSummary),
+}
+
+// The matches to replace in one file, by their index in its preview, or all
+// of them when `matches` is not given.
+#[derive(Deserialize, Clone, Debug)]
+pub struct ReplaceSelection {
+    pub path: String,
+    pub hash: String,
+    pub matches: Option<Vec<usize>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct JournalFile {
+    pub path: String,
+    // The file's bytes before the replace, saved next to the journal.
+    pub backup: String,
+    // The hash of its content after the replace, to notice later edits.
+    pub hash: String,
+}
+
+// Everything needed to revert one replace across many files.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ReplaceJournal {
+    pub id: String,
+    pub created_at: i64,
+    pub folder: String,
+    pub query: String,
+    pub replacement: String,
+    pub matches: usize,
+    pub files: Vec<JournalFile>,
+}
+
+struct FileChange {
+    path: PathBuf,
+    before: Vec<u8>,
+    after: Vec<u8>,
+}
+
+fn line_ending(content: &str) -> &'static str {
+    if content.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    }
+}
+
+// Replaces the matches of `regex` line by line, as the search finds them,
+// skipping those not in `selected`. Line endings are kept, and line breaks in
+// the replacement use the file's. Returns the new content and every match.
+fn replace_content(content: &str, regex: &Regex, options: &ReplaceOptions, selected: Option<&HashSet<usize>>) -> (String, Vec<ReplaceMatch>) {
+    let eol = line_ending(content);
+    let mut output = String::with_capacity(content.len());
+    let mut matches = Vec::new();
+
+    for (index, raw_line) in content.split_inclusive('\n').enumerate() {
+        let line = raw_line.strip_suffix('\n').unwrap_or(raw_line);
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        let mut last = 0;
+
+        for captures in regex.captures_iter(line) {
+            let Some(found) = captures.get(0).filter(|found| !found.is_empty()) else {
+                continue;
+            };
+            let mut replacement = String::new();
+            if options.search.regex {
+                captures.expand(&options.replacement, &mut replacement);
+            } else {
+                replacement.push_str(&options.replacement);
+            }
+            let replacement = replacement.replace("\r\n", "\n").replace('\n', eol);
+
+            if selected.is_none_or(|selected| selected.contains(&matches.len())) {
+                output.push_str(&line[last..found.start()]);
+                output.push_str(&replacement);
+                last = found.end();
+            }
+            matches.push(ReplaceMatch {
+                found: search::match_at(index, line, found.start(), found.end()),
+                replacement,
+            });
+        }
+
+        output.push_str(&line[last..]);
+        output.push_str(&raw_line[line.len()..]);
+    }
+
+    (output, matches)
+}
+
+fn unified_diff(path: &str, before: &str, after: &str) -> String {
+    TextDiff::from_lines(before, after)
+        .unified_diff()
+        .context_radius(DIFF_CONTEXT)
+        .header(path, path)
+        .to_string()
+}
+
+fn preview_file(path: &Path, text: &TextFile, regex: &Regex, options: &ReplaceOptions) -> Option<FilePreview> {
+    let (content, matches) = replace_content(&text.content, regex, options, None);
+    if matches.is_empty() {
+        return None;
+    }
+    let path = path.to_string_lossy().into_owned();
+    Some(FilePreview {
+        diff: unified_diff(&path, &text.content, &content),
+        path,
+        encoding: text.encoding.to_string(),
+        hash: crate::calculate_file_hash(&text.content),
+        matches,
+    })
+}
+
+fn run_preview(builder: WalkBuilder, regex: Regex, options: &ReplaceOptions, channel: &Channel<ReplaceEvent>, cancelled: &AtomicBool) -> SearchSummary {
+    let started = Instant::now();
+    let max_file_size = options.search.max_file_size.unwrap_or(search::DEFAULT_MAX_FILE_SIZE);
+
+    let found = AtomicUsize::new(0);
+    let (searched, skipped) = search::walk_text_files(builder, max_file_size, cancelled, |path, text| {
+        if let Some(preview) = preview_file(path, &text, &regex, options) {
+            found.fetch_add(preview.matches.len(), Ordering::Relaxed);
+            let _ = channel.send(ReplaceEvent::File(preview));
+        }
+        WalkState::Continue
+    });
+
+    SearchSummary {
+        files_searched: searched,
+        files_skipped: skipped,
+        matches: found.into_inner(),
+        truncated: false,
+        cancelled: cancelled.load(Ordering::Relaxed),
+        elapsed_ms: started.elapsed().as_millis() as u64,
+    }
+}
+
+fn staging_path(path: &Path) -> PathBuf {
+    let file_name = path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{}{}", file_name, STAGING_SUFFIX))
+}
+
+// Folder watches skip these, so a staged file is not taken for a new one.
+pub fn is_staging_path(path: &Path) -> bool {
+    path.file_name()
+        .map(|name| name.to_string_lossy())
+        .is_some_and(|name| name.starts_with('.') && name.ends_with(STAGING_SUFFIX))
+}
+
+fn stage(path: &Path, contents: &[u8]) -> Result<PathBuf, String> {
+    let tmp_path = staging_path(path);
+    let mut file = fs::File::create(&tmp_path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let written = file.write_all(contents).and_then(|_| file.sync_all());
+    drop(file);
+    if let Err(e) = written {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(format!("{}: {}", path.display(), e));
+    }
+    if let Ok(metadata) = fs::metadata(path) {
+        let _ = fs::set_permissions(&tmp_path, metadata.permissions());
+    }
+    Ok(tmp_path)
+}
+
+// Gives every file its `after` bytes, or none of them: the new versions are
+// all written next to their files first, and if moving one into place fails,
+// the files already moved get their `before` bytes back.
+fn write_changes(changes: &[FileChange]) -> Result<(), String> {
+    let mut staged = Vec::with_capacity(changes.len());
+    for change in changes {
+        match stage(&change.path, &change.after) {
+            Ok(tmp_path) => staged.push(tmp_path),
+            Err(e) => {
+                for tmp_path in &staged {
+                    let _ = fs::remove_file(tmp_path);
+                }
+                return Err(e);
+            }
+        }
+    }
+
+    for (index, (change, tmp_path)) in changes.iter().zip(&staged).enumerate() {
+        if let Err(e) = fs::rename(tmp_path, &change.path) {
+            for tmp_path in &staged[index..] {
+                let _ = fs::remove_file(tmp_path);
+            }
+            for done in &changes[..index] {
+                if let Ok(tmp_path) = stage(&done.path, &done.before) {
+                    let _ = fs::rename(&tmp_path, &done.path);
+                }
+            }
+            return Err(format!("{}: {}", change.path.display(), e));
+        }
+    }
+    Ok(())
+}
+
+fn journal_root(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let journal_root = ConfigManager::get_notepad_md_dir(app_handle)?.join(JOURNAL_DIR);
+    if !journal_root.exists() {
+        fs::create_dir_all(&journal_root).map_err(|e| e.to_string())?;
+    }
+    Ok(journal_root)
+}
+
+fn journal_dir(app_handle: &tauri::AppHandle, id: &str) -> Result<PathBuf, String> {
+    if !is_safe_file_name(id) {
+        return Err(format!("Invalid journal id: {}", id));
+    }
+    Ok(journal_root(app_handle)?.join(id))
+}
+
+fn read_journal(dir: &Path) -> Result<ReplaceJournal, String> {
+    let journal_str = fs::read_to_string(dir.join(JOURNAL_FILE)).map_err(|e| e.to_string())?;
+    serde_json::from_str(&journal_str).map_err(|e| e.to_string())
+}
+
+fn list_journals(app_handle: &tauri::AppHandle) -> Result<Vec<ReplaceJournal>, String> {
+    let entries = fs::read_dir(journal_root(app_handle)?).map_err(|e| e.to_string())?;
+    let mut journals: Vec<ReplaceJournal> = entries
+        .flatten()
+        .filter_map(|entry| read_journal(&entry.path()).ok())
+        .collect();
+    journals.sort_by_key(|journal| std::cmp::Reverse(journal.created_at));
+    Ok(journals)
+}
+
+// Saves the original bytes of every file before anything is written, so an
+// operation cut short can still be undone.
+fn write_journal(app_handle: &tauri::AppHandle, journal: &mut ReplaceJournal, changes: &[FileChange]) -> Result<PathBuf, String> {
+    let dir = journal_dir(app_handle, &journal.id)?;
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+
+    for (index, (change, file)) in changes.iter().zip(journal.files.iter_mut()).enumerate() {
+        file.backup = format!("{}.orig", index);
+        if let Err(e) = fs::write(dir.join(&file.backup), &change.before) {
+            let _ = fs::remove_dir_all(&dir);
+            return Err(e.to_string());
+        }
+    }
+    let journal_str = serde_json::to_string_pretty(journal).map_err(|e| e.to_string())?;
+    if let Err(e) = write_atomic(&dir.join(JOURNAL_FILE), &journal_str) {
+        let _ = fs::remove_dir_all(&dir);
+        return Err(e);
+    }
+    Ok(dir)
+}
+
+fn prune_journals(app_handle: &tauri::AppHandle) -> Result<(), String> {
+    for journal in list_journals(app_handle)?.into_iter().skip(MAX_JOURNALS) {
+        let _ = fs::remove_dir_all(journal_dir(app_handle, &journal.id)?);
+    }
+    Ok(())
+}
+
+// Recomputes the change to one file from what is on disk now, refusing files
+// that changed since the preview or cannot be written back as they were read.
+fn change_for(path: PathBuf, selection: &ReplaceSelection, regex: &Regex, options: &ReplaceOptions) -> Result<Option<(FileChange, JournalFile, usize)>, String> {
+    let before = fs::read(&path).map_err(|e| format!("{}: {}", selection.path, e))?;
+    let text = search::decode(&before).ok_or_else(|| format!("{} is not a text file", selection.path))?;
+    if crate::calculate_file_hash(&text.content) != selection.hash {
+        return Err(format!("CONFLICT: {} was changed since the preview", selection.path));
+    }
+    if text.encode(&text.content) != before {
+        return Err(format!("{} cannot be written back as {} without changing it", selection.path, text.encoding));
+    }
+
+    let selected: Option<HashSet<usize>> = selection.matches.as_ref().map(|matches| matches.iter().copied().collect());
+    let (content, matches) = replace_content(&text.content, regex, options, selected.as_ref());
+    if content == text.content {
+        return Ok(None);
+    }
+
+    let replaced = selected.map(|selected| selected.iter().filter(|index| **index < matches.len()).count()).unwrap_or(matches.len());
+    let journal_file = JournalFile {
+        path: path.to_string_lossy().into_owned(),
+        backup: String::new(),
+        hash: crate::calculate_file_hash(&content),
+    };
+    let change = FileChange { path, after: text.encode(&content), before };
+    Ok(Some((change, journal_file, replaced)))
+}
+
+// Previews a replace in the background, like `search_in_folder`. Each file
+// it would change comes in through `on_event` with its matches and a diff.
+// Returns an id for `cancel_search`.
+#[tauri::command]
+pub fn preview_replace_in_folder(app_handle: tauri::AppHandle, folder: String, options: ReplaceOptions, on_event: Channel<ReplaceEvent>) -> Result<u64, String> {
+    let root = PathBuf::from(&folder);
+    if !root.is_dir() {
+        return Err(format!("Not a folder: {}", folder));
+    }
+    let regex = search::build_regex(&options.search)?;
+    let builder = search::walker(&root, &options.search)?;
+    let (search_id, cancelled) = search::register(&app_handle)?;
+
+    std::thread::spawn(move || {
+        let summary = run_preview(builder, regex, &options, &on_event, &cancelled);
+        let _ = on_event.send(ReplaceEvent::Done(summary));
+        search::unregister(&app_handle, search_id);
+    });
+
+    Ok(search_id)
+}
+
+// Applies a previewed replace to the selected files, all or none, and records
+// it in a journal for `undo_replace`.
+#[tauri::command]
+pub fn apply_replace_in_folder(app_handle: tauri::AppHandle, folder: String, options: ReplaceOptions, files: Vec<ReplaceSelection>) -> Result<ReplaceJournal, String> {
+    let regex = search::build_regex(&options.search)?;
+    // Paths are compared once resolved, so `..` or links can't lead outside
+    // the folder, and files are written where links point to.
+    let root = fs::canonicalize(&folder).map_err(|e| format!("{}: {}", folder, e))?;
+
+    let mut changes = Vec::new();
+    let mut journal_files = Vec::new();
+    let mut matches = 0;
+    let mut seen = HashSet::new();
+    for selection in &files {
+        let path = fs::canonicalize(&selection.path).map_err(|e| format!("{}: {}", selection.path, e))?;
+        if !path.starts_with(&root) {
+            return Err(format!("{} is not in {}", selection.path, folder));
+        }
+        if !seen.insert(path.clone()) {
+            return Err(format!("{} is listed more than once", selection.path));
+        }
+        if let Some((change, journal_file, replaced)) = change_for(path, selection, &regex, &options)? {
+            changes.push(change);
+            journal_files.push(journal_file);
+            matches += replaced;
+        }
+    }
+    if changes.is_empty() {
+        return Err("Nothing to replace".to_string());
+    }
+
+    let created_at = chrono::Local::now().timestamp_millis();
+    let mut journal = ReplaceJournal {
+        id: created_at.to_string(),
+        created_at,
+        folder,
+        query: options.search.query.clone(),
+        replacement: options.replacement.clone(),
+        matches,
+        files: journal_files,
+    };
+    let dir = write_journal(&app_handle, &mut journal, &changes)?;
+
+    if let Err(e) = write_changes(&changes) {
+        let _ = fs::remove_dir_all(&dir);
+        return Err(e);
+    }
+    prune_journals(&app_handle)?;
+    Ok(journal)
+}
+
+#[tauri::command]
+pub fn get_replace_journals(app_handle: tauri::AppHandle) -> Result<Vec<ReplaceJournal>, String> {
+    list_journals(&app_handle)
+}
+
+// Reverts every file of a replace, or none of them if any was edited since.
+// Returns the paths of the restored files.
+#[tauri::command]
+pub fn undo_replace(app_handle: tauri::AppHandle, journal_id: String) -> Result<Vec<String>, String> {
+    let dir = journal_dir(&app_handle, &journal_id)?;
+    let journal = read_journal(&dir)?;
+
+    let mut changes = Vec::new();
+    let mut edited = Vec::new();
+    for file in &journal.files {
+        let path = PathBuf::from(&file.path);
+        let current = fs::read(&path).unwrap_or_default();
+        let unchanged = search::decode(&current)
+            .is_some_and(|text| crate::calculate_file_hash(&text.content) == file.hash);
+        if !unchanged {
+            edited.push(file.path.clone());
+            continue;
+        }
+        let before = fs::read(dir.join(&file.backup)).map_err(|e| e.to_string())?;
+        changes.push(FileChange { path, before: current, after: before });
+    }
+    if !edited.is_empty() {
+        return Err(format!("CONFLICT: changed since the replace: {}", edited.join(", ")));
+    }
+
+    write_changes(&changes)?;
+    fs::remove_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(journal.files.into_iter().map(|file| file.path).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(query: &str, replacement: &str, regex: bool) -> ReplaceOptions {
+        ReplaceOptions {
+            search: SearchOptions { query: query.to_string(), regex, case_sensitive: true, ..Default::default() },
+            replacement: replacement.to_string(),
+        }
+    }
+
+    fn replace(content: &str, options: &ReplaceOptions, selected: Option<&HashSet<usize>>) -> (String, Vec<ReplaceMatch>) {
+        let regex = search::build_regex(&options.search).unwrap();
+        replace_content(content, &regex, options, selected)
+    }
+
+    #[test]
+    fn replaces_every_match_and_keeps_line_endings() {
+        let (content, matches) = replace("foo bar\r\nfoo\r\n", &options("foo", "baz", false), None);
+        assert_eq!(content, "baz bar\r\nbaz\r\n");
+        assert_eq!(matches.len(), 2);
+        assert_eq!((matches[1].found.line, matches[1].found.column), (2, 1));
+    }
+
+    #[test]
+    fn plain_replacements_are_inserted_as_is() {
+        let (content, _) = replace("a.b", &options("a.b", "$1 $$", false), None);
+        assert_eq!(content, "$1 $$");
+    }
+
+    #[test]
+    fn regex_replacements_expand_captures() {
+        let options = options(r"(?P<key>\w+)=(\w+)", "${key}: $2 $$", true);
+        let (content, matches) = replace("a=1\nb=2", &options, None);
+        assert_eq!(content, "a: 1 $\nb: 2 $");
+        assert_eq!(matches[0].replacement, "a: 1 $");
+    }
+
+    #[test]
+    fn line_breaks_in_replacements_use_the_file_line_ending() {
+        let (content, _) = replace("a;b\r\n", &options(";", "\n", false), None);
+        assert_eq!(content, "a\r\nb\r\n");
+        let (content, _) = replace("a;b\n", &options(";", "\r\n", false), None);
+        assert_eq!(content, "a\nb\n");
+    }
+
+    #[test]
+    fn only_selected_matches_are_replaced() {
+        let selected = HashSet::from([1]);
+        let (content, matches) = replace("x x x", &options("x", "y", false), Some(&selected));
+        assert_eq!(content, "x y x");
+        assert_eq!(matches.len(), 3);
+    }
+
+    #[test]
+    fn unchanged_content_has_an_empty_diff() {
+        assert_eq!(unified_diff("a.txt", "same\n", "same\n"), "");
+        let diff = unified_diff("a.txt", "old\n", "new\n");
+        assert!(diff.contains("-old\n+new\n"));
+    }
+
+    #[test]
+    fn write_changes_writes_every_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("first.txt");
+        let second = dir.path().join("second.txt");
+        fs::write(&first, "a").unwrap();
+        fs::write(&second, "b").unwrap();
+
+        write_changes(&[
+            FileChange { path: first.clone(), before: b"a".to_vec(), after: b"A".to_vec() },
+            FileChange { path: second.clone(), before: b"b".to_vec(), after: b"B".to_vec() },
+        ]).unwrap();
+        assert_eq!(fs::read(&first).unwrap(), b"A");
+        assert_eq!(fs::read(&second).unwrap(), b"B");
+        assert!(!staging_path(&first).exists());
+    }
+
+    #[test]
+    fn write_changes_rolls_back_when_a_file_cannot_be_replaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("first.txt");
+        let second = dir.path().join("second");
+        fs::write(&first, "a").unwrap();
+        fs::create_dir(&second).unwrap();
+        fs::write(second.join("inside.txt"), "").unwrap();
+
+        let result = write_changes(&[
+            FileChange { path: first.clone(), before: b"a".to_vec(), after: b"A".to_vec() },
+            FileChange { path: second.clone(), before: Vec::new(), after: b"B".to_vec() },
+        ]);
+        assert!(result.is_err());
+        assert_eq!(fs::read(&first).unwrap(), b"a");
+        assert!(second.is_dir());
+        assert!(!staging_path(&first).exists());
+        assert!(!staging_path(&second).exists());
+    }
+
+    #[test]
+    fn staging_paths_are_recognized() {
+        let path = Path::new("/logs/app.log");
+        assert!(is_staging_path(&staging_path(path)));
+        assert!(!is_staging_path(path));
+        assert!(!is_staging_path(Path::new("/logs/app.firow-replace.tmp")));
+    }
+}
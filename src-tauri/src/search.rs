@@ -0,0 +1,469 @@
+use ignore::overrides::OverrideBuilder;
+use ignore::{WalkBuilder, WalkState};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tauri::ipc::Channel;
+use tauri::Manager;
+
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 10 * 1024 * 1024;
+const DEFAULT_MAX_RESULTS: usize = 10_000;
+
+// A NUL byte this early in a file that has no UTF-16 BOM marks it as binary.
+const BINARY_SNIFF_LEN: usize = 8192;
+
+// Previews of long lines start a little before the match and are cut off.
+const PREVIEW_LEN: usize = 200;
+const PREVIEW_CONTEXT: usize = 60;
+
+// Running searches by id, so they can be cancelled.
+pub struct SearchState {
+    next_id: u64,
+    searches: HashMap<u64, Arc<AtomicBool>>,
+}
+
+impl SearchState {
+    pub fn new() -> Self {
+        Self {
+            next_id: 0,
+            searches: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct SearchOptions {
+    pub query: String,
+    #[serde(default)]
+    pub regex: bool,
+    #[serde(default)]
+    pub case_sensitive: bool,
+    #[serde(default)]
+    pub whole_word: bool,
+    // Hidden files and folders are skipped unless this is set.
+    #[serde(default)]
+    pub include_hidden: bool,
+    // Files matched by .gitignore, .ignore and the global git excludes are
+    // skipped unless this is set.
+    #[serde(default)]
+    pub include_ignored: bool,
+    // Globs relative to the folder. With any `include` globs, only files
+    // matching one of them are searched.
+    #[serde(default)]
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    pub max_file_size: Option<u64>,
+    pub max_results: Option<usize>,
+}
+
+// Lines and columns start at 1. Columns, lengths and preview offsets count
+// UTF-16 code units, as the editor does.
+#[derive(Serialize, Clone, Debug)]
+pub struct SearchMatch {
+    pub line: usize,
+    pub column: usize,
+    pub length: usize,
+    pub preview: String,
+    pub preview_start: usize,
+    pub preview_end: usize,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct FileMatches {
+    pub path: String,
+    pub encoding: String,
+    pub matches: Vec<SearchMatch>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct SearchSummary {
+    pub files_searched: usize,
+    pub files_skipped: usize,
+    pub matches: usize,
+    pub truncated: bool,
+    pub cancelled: bool,
+    pub elapsed_ms: u64,
+}
+
+// Sent over the search's channel: the matches of each file as it is
+// searched, then a summary.
+#[derive(Serialize, Clone, Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SearchEvent {
+    File(FileMatches),
+    Done(SearchSummary),
+}
+
+pub struct TextFile {
+    pub content: String,
+    pub encoding: &'static str,
+    pub bom: bool,
+}
+
+impl TextFile {
+    // Encodes `content` the way this file was, BOM included.
+    pub fn encode(&self, content: &str) -> Vec<u8> {
+        let bytes = crate::encode_content(content, Some(self.encoding));
+        if self.bom && self.encoding == "utf-8" {
+            [0xEF, 0xBB, 0xBF].into_iter().chain(bytes).collect()
+        } else {
+            bytes
+        }
+    }
+}
+
+// Decodes a file in one of the encodings the editor supports: UTF-16 by its
+// BOM, UTF-8 when valid, else Windows-1252. Returns `None` for binary files.
+pub fn decode(bytes: &[u8]) -> Option<TextFile> {
+    if let Some((encoding, _)) = encoding_rs::Encoding::for_bom(bytes) {
+        let (content, _) = encoding.decode_with_bom_removal(bytes);
+        let label = if encoding == encoding_rs::UTF_16LE {
+            "utf-16le"
+        } else if encoding == encoding_rs::UTF_16BE {
+            "utf-16be"
+        } else {
+            "utf-8"
+        };
+        return Some(TextFile { content: content.into_owned(), encoding: label, bom: true });
+    }
+
+    if bytes[..bytes.len().min(BINARY_SNIFF_LEN)].contains(&0) {
+        return None;
+    }
+    match std::str::from_utf8(bytes) {
+        Ok(content) => Some(TextFile { content: content.to_string(), encoding: "utf-8", bom: false }),
+        Err(_) => {
+            let (content, _, _) = encoding_rs::WINDOWS_1252.decode(bytes);
+            Some(TextFile { content: content.into_owned(), encoding: "windows-1252", bom: false })
+        }
+    }
+}
+
+// Reads a text file no bigger than `max_size`. Returns `None` for files
+// that are too big or binary.
+pub fn read_text(path: &Path, max_size: u64) -> Result<Option<TextFile>, String> {
+    let metadata = fs::metadata(path).map_err(|e| e.to_string())?;
+    if metadata.len() > max_size {
+        return Ok(None);
+    }
+    let bytes = fs::read(path).map_err(|e| e.to_string())?;
+    Ok(decode(&bytes))
+}
+
+pub fn build_regex(options: &SearchOptions) -> Result<Regex, String> {
+    if options.query.is_empty() {
+        return Err("Nothing to search for".to_string());
+    }
+
+    let pattern = if options.regex {
+        options.query.clone()
+    } else {
+        regex::escape(&options.query)
+    };
+    let pattern = if options.whole_word {
+        format!(r"\b(?:{})\b", pattern)
+    } else {
+        pattern
+    };
+
+    RegexBuilder::new(&pattern)
+        .case_insensitive(!options.case_sensitive)
+        .build()
+        .map_err(|e| format!("Invalid regular expression: {}", e))
+}
+
+pub fn walker(root: &Path, options: &SearchOptions) -> Result<WalkBuilder, String> {
+    let mut overrides = OverrideBuilder::new(root);
+    for glob in options.include.iter().filter(|glob| !glob.trim().is_empty()) {
+        overrides.add(glob.trim()).map_err(|e| e.to_string())?;
+    }
+    for glob in options.exclude.iter().filter(|glob| !glob.trim().is_empty()) {
+        overrides.add(&format!("!{}", glob.trim())).map_err(|e| e.to_string())?;
+    }
+    let overrides = overrides.build().map_err(|e| e.to_string())?;
+
+    let respect_ignore = !options.include_ignored;
+    let mut builder = WalkBuilder::new(root);
+    builder
+        .hidden(!options.include_hidden)
+        .git_ignore(respect_ignore)
+        .git_global(respect_ignore)
+        .git_exclude(respect_ignore)
+        .ignore(respect_ignore)
+        .parents(respect_ignore)
+        .require_git(false)
+        .overrides(overrides)
+        .filter_entry(|entry| entry.file_name() != ".git");
+    Ok(builder)
+}
+
+fn utf16_len(text: &str) -> usize {
+    text.encode_utf16().count()
+}
+
+// The part of `line` to show for a match at `start..end`, with the match's
+// offsets in it.
+fn preview(line: &str, start: usize, end: usize) -> (String, usize, usize) {
+    let from = if line.len() > PREVIEW_LEN {
+        line[..start].char_indices().rev().nth(PREVIEW_CONTEXT - 1).map(|(index, _)| index).unwrap_or(0)
+    } else {
+        0
+    };
+    let to = line[from..].char_indices().nth(PREVIEW_LEN).map(|(index, _)| from + index).unwrap_or(line.len());
+
+    let preview_start = utf16_len(&line[from..start]);
+    let preview_end = preview_start + utf16_len(&line[start..end.min(to).max(start)]);
+    (line[from..to].to_string(), preview_start, preview_end)
+}
+
+// The match at `start..end` of `line`, the `index`th line of its file.
+pub fn match_at(index: usize, line: &str, start: usize, end: usize) -> SearchMatch {
+    let (preview, preview_start, preview_end) = preview(line, start, end);
+    SearchMatch {
+        line: index + 1,
+        column: utf16_len(&line[..start]) + 1,
+        length: utf16_len(&line[start..end]),
+        preview,
+        preview_start,
+        preview_end,
+    }
+}
+
+pub fn find_matches(content: &str, regex: &Regex) -> Vec<SearchMatch> {
+    let mut matches = Vec::new();
+    for (index, line) in content.split('\n').enumerate() {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        for found in regex.find_iter(line).filter(|found| !found.is_empty()) {
+            matches.push(match_at(index, line, found.start(), found.end()));
+        }
+    }
+    matches
+}
+
+// Hands every text file under `builder` to `visit`, from several threads,
+// until `visit` quits or the walk is cancelled. Returns how many files were
+// read and how many were skipped.
+pub fn walk_text_files<F>(builder: WalkBuilder, max_file_size: u64, cancelled: &AtomicBool, visit: F) -> (usize, usize)
+where
+    F: Fn(&Path, TextFile) -> WalkState + Sync,
+{
+    let searched = AtomicUsize::new(0);
+    let skipped = AtomicUsize::new(0);
+
+    builder.build_parallel().run(|| {
+        let (visit, searched, skipped) = (&visit, &searched, &skipped);
+        Box::new(move |entry| {
+            if cancelled.load(Ordering::Relaxed) {
+                return WalkState::Quit;
+            }
+            let Ok(entry) = entry else {
+                skipped.fetch_add(1, Ordering::Relaxed);
+                return WalkState::Continue;
+            };
+            if !entry.file_type().is_some_and(|file_type| file_type.is_file()) {
+                return WalkState::Continue;
+            }
+
+            match read_text(entry.path(), max_file_size) {
+                Ok(Some(text)) => {
+                    searched.fetch_add(1, Ordering::Relaxed);
+                    visit(entry.path(), text)
+                }
+                _ => {
+                    skipped.fetch_add(1, Ordering::Relaxed);
+                    WalkState::Continue
+                }
+            }
+        })
+    });
+
+    (searched.into_inner(), skipped.into_inner())
+}
+
+fn run_search(builder: WalkBuilder, regex: Regex, options: &SearchOptions, channel: &Channel<SearchEvent>, cancelled: &AtomicBool) -> SearchSummary {
+    let started = Instant::now();
+    let max_file_size = options.max_file_size.unwrap_or(DEFAULT_MAX_FILE_SIZE);
+    let max_results = options.max_results.unwrap_or(DEFAULT_MAX_RESULTS);
+
+    let found = AtomicUsize::new(0);
+    let truncated = AtomicBool::new(false);
+
+    let (searched, skipped) = walk_text_files(builder, max_file_size, cancelled, |path, text| {
+        let mut matches = find_matches(&text.content, &regex);
+        if matches.is_empty() {
+            return WalkState::Continue;
+        }
+
+        let before = found.fetch_add(matches.len(), Ordering::Relaxed);
+        let remaining = max_results.saturating_sub(before);
+        if matches.len() >= remaining {
+            matches.truncate(remaining);
+            truncated.store(true, Ordering::Relaxed);
+        }
+        if !matches.is_empty() {
+            let path = path.to_string_lossy().into_owned();
+            let encoding = text.encoding.to_string();
+            let _ = channel.send(SearchEvent::File(FileMatches { path, encoding, matches }));
+        }
+
+        if truncated.load(Ordering::Relaxed) {
+            WalkState::Quit
+        } else {
+            WalkState::Continue
+        }
+    });
+
+    SearchSummary {
+        files_searched: searched,
+        files_skipped: skipped,
+        matches: found.into_inner().min(max_results),
+        truncated: truncated.into_inner(),
+        cancelled: cancelled.load(Ordering::Relaxed),
+        elapsed_ms: started.elapsed().as_millis() as u64,
+    }
+}
+
+// Registers a background search, so `cancel_search` can stop it.
+pub fn register(app_handle: &tauri::AppHandle) -> Result<(u64, Arc<AtomicBool>), String> {
+    let state = app_handle.state::<Mutex<SearchState>>();
+    let mut state = state.lock().map_err(|e| e.to_string())?;
+    let cancelled = Arc::new(AtomicBool::new(false));
+    state.next_id += 1;
+    let search_id = state.next_id;
+    state.searches.insert(search_id, cancelled.clone());
+    Ok((search_id, cancelled))
+}
+
+pub fn unregister(app_handle: &tauri::AppHandle, search_id: u64) {
+    let state = app_handle.state::<Mutex<SearchState>>();
+    if let Ok(mut state) = state.lock() {
+        state.searches.remove(&search_id);
+    };
+}
+
+// Starts a search in the background and returns its id. Matches come in
+// through `on_event` as files are searched, followed by a summary once the
+// search finishes or is cancelled.
+#[tauri::command]
+pub fn search_in_folder(app_handle: tauri::AppHandle, folder: String, options: SearchOptions, on_event: Channel<SearchEvent>) -> Result<u64, String> {
+    let root = PathBuf::from(&folder);
+    if !root.is_dir() {
+        return Err(format!("Not a folder: {}", folder));
+    }
+    let regex = build_regex(&options)?;
+    let builder = walker(&root, &options)?;
+
+    let (search_id, cancelled) = register(&app_handle)?;
+
+    std::thread::spawn(move || {
+        let summary = run_search(builder, regex, &options, &on_event, &cancelled);
+        let _ = on_event.send(SearchEvent::Done(summary));
+        unregister(&app_handle, search_id);
+    });
+
+    Ok(search_id)
+}
+
+#[tauri::command]
+pub fn cancel_search(app_handle: tauri::AppHandle, search_id: u64) -> Result<(), String> {
+    let state = app_handle.state::<Mutex<SearchState>>();
+    let state = state.lock().map_err(|e| e.to_string())?;
+    if let Some(cancelled) = state.searches.get(&search_id) {
+        cancelled.store(true, Ordering::Relaxed);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(query: &str) -> SearchOptions {
+        SearchOptions { query: query.to_string(), ..Default::default() }
+    }
+
+    #[test]
+    fn decode_detects_encodings() {
+        let text = decode("héllo".as_bytes()).unwrap();
+        assert_eq!((text.content.as_str(), text.encoding, text.bom), ("héllo", "utf-8", false));
+
+        let text = decode(b"\xEF\xBB\xBFhi").unwrap();
+        assert_eq!((text.content.as_str(), text.encoding, text.bom), ("hi", "utf-8", true));
+
+        let text = decode(b"\xFF\xFEh\0i\0").unwrap();
+        assert_eq!((text.content.as_str(), text.encoding), ("hi", "utf-16le"));
+
+        let text = decode(b"\xFE\xFF\0h\0i").unwrap();
+        assert_eq!((text.content.as_str(), text.encoding), ("hi", "utf-16be"));
+
+        let text = decode(b"caf\xE9").unwrap();
+        assert_eq!((text.content.as_str(), text.encoding), ("café", "windows-1252"));
+    }
+
+    #[test]
+    fn decode_skips_binary_files() {
+        assert!(decode(b"PK\x03\x04\0\0").is_none());
+    }
+
+    #[test]
+    fn encode_keeps_the_bom() {
+        let text = decode(b"\xEF\xBB\xBFhi").unwrap();
+        assert_eq!(text.encode("ho"), b"\xEF\xBB\xBFho");
+        let text = decode(b"\xFF\xFEh\0i\0").unwrap();
+        assert_eq!(text.encode("ho"), b"\xFF\xFEh\0o\0");
+    }
+
+    #[test]
+    fn build_regex_escapes_plain_queries() {
+        let regex = build_regex(&options("a.b")).unwrap();
+        assert!(regex.is_match("a.b"));
+        assert!(!regex.is_match("axb"));
+        assert!(build_regex(&options("")).is_err());
+        assert!(build_regex(&SearchOptions { regex: true, ..options("(") }).is_err());
+    }
+
+    #[test]
+    fn build_regex_honours_case_and_whole_word() {
+        assert!(build_regex(&options("Foo")).unwrap().is_match("foo"));
+        let case_sensitive = build_regex(&SearchOptions { case_sensitive: true, ..options("Foo") }).unwrap();
+        assert!(!case_sensitive.is_match("foo"));
+
+        let whole_word = build_regex(&SearchOptions { whole_word: true, regex: true, ..options("a|b") }).unwrap();
+        assert!(whole_word.is_match("x a y"));
+        assert!(!whole_word.is_match("ab"));
+    }
+
+    #[test]
+    fn find_matches_counts_utf16_columns() {
+        let regex = build_regex(&options("x")).unwrap();
+        let matches = find_matches("ax\r\n😀x x\n", &regex);
+        let positions: Vec<_> = matches.iter().map(|found| (found.line, found.column, found.length)).collect();
+        assert_eq!(positions, [(1, 2, 1), (2, 3, 1), (2, 5, 1)]);
+        assert_eq!(matches[0].preview, "ax");
+        assert_eq!((matches[1].preview_start, matches[1].preview_end), (2, 3));
+    }
+
+    #[test]
+    fn find_matches_skips_empty_matches() {
+        let regex = build_regex(&SearchOptions { regex: true, ..options("x*") }).unwrap();
+        assert_eq!(find_matches("abc", &regex).len(), 0);
+    }
+
+    #[test]
+    fn long_lines_are_cut_around_the_match() {
+        let line = "a".repeat(300);
+        let (text, start, end) = preview(&line, 250, 253);
+        assert_eq!(text.len(), 110);
+        assert_eq!((start, end), (PREVIEW_CONTEXT, PREVIEW_CONTEXT + 3));
+
+        let (text, start, end) = preview(&line, 0, 3);
+        assert_eq!(text.len(), PREVIEW_LEN);
+        assert_eq!((start, end), (0, 3));
+    }
+}
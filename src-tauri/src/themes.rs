@@ -0,0 +1,816 @@
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+
+use crate::config::{self, ConfigManager};
+
+// Themes that ship with Monaco, with their display names.
+const BUILTIN_THEMES: &[(&str, &str)] = &[
+    ("vs", "Visual Studio Light"),
+    ("vs-dark", "Visual Studio Dark"),
+    ("hc-black", "High Contrast Dark"),
+];
+
+const BASES: &[&str] = &["vs", "vs-dark", "hc-black", "hc-light"];
+const FONT_STYLES: &[&str] = &["italic", "bold", "underline", "strikethrough"];
+
+// VS Code themes can `include` a parent theme, which can include another.
+const MAX_INCLUDE_DEPTH: usize = 8;
+
+// Editor colors in the unscoped settings of a TextMate theme and the Monaco
+// colors they become.
+const TEXTMATE_COLORS: &[(&str, &str)] = &[
+    ("background", "editor.background"),
+    ("foreground", "editor.foreground"),
+    ("caret", "editorCursor.foreground"),
+    ("selection", "editor.selectionBackground"),
+    ("lineHighlight", "editor.lineHighlightBackground"),
+    ("invisibles", "editorWhitespace.foreground"),
+    ("findHighlight", "editor.findMatchHighlightBackground"),
+    ("gutter", "editorGutter.background"),
+    ("gutterForeground", "editorLineNumber.foreground"),
+    ("guide", "editorIndentGuide.background"),
+    ("activeGuide", "editorIndentGuide.activeBackground"),
+];
+
+// Saving a file often comes as several events in a row, so the themes are
+// listed again once they stop for this long.
+const RELOAD_DELAY_MS: u64 = 250;
+
+pub struct ThemeState {
+    watcher: Option<notify::RecommendedWatcher>,
+    // Themes changed since the last `themes-changed`, and a counter that
+    // tells a delayed reload whether more changes came in after it.
+    pending: Vec<String>,
+    generation: u64,
+}
+
+impl ThemeState {
+    pub fn new() -> Self {
+        Self {
+            watcher: None,
+            pending: Vec::new(),
+            generation: 0,
+        }
+    }
+}
+
+// Colors for a swatch in the theme menu. The token colors are those of the
+// first rules for keywords, strings and comments, when the theme has them.
+#[derive(Serialize, Clone, Debug)]
+pub struct ThemePreview {
+    pub background: String,
+    pub foreground: String,
+    pub keyword: Option<String>,
+    pub string: Option<String>,
+    pub comment: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ThemeInfo {
+    pub name: String,
+    pub display_name: String,
+    pub base: String,
+    pub dark: bool,
+    pub builtin: bool,
+    pub preview: ThemePreview,
+    // Why the theme can't be used, for theme files that fail validation.
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+struct ThemesChanged {
+    themes: Vec<ThemeInfo>,
+    changed: Vec<String>,
+}
+
+pub fn is_builtin(name: &str) -> bool {
+    BUILTIN_THEMES.iter().any(|(builtin, _)| *builtin == name)
+}
+
+pub fn is_hex(digits: &str) -> bool {
+    matches!(digits.len(), 3 | 4 | 6 | 8) && digits.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+// Monaco takes token colors as six or eight hex digits, with or without `#`.
+fn is_token_color(color: &str) -> bool {
+    let digits = color.strip_prefix('#').unwrap_or(color);
+    is_hex(digits) && digits.len() >= 6
+}
+
+// Token colors are written as six digits without `#`: shorthand is expanded
+// and alpha, which Monaco ignores for tokens, is dropped.
+fn token_color(color: &str) -> Option<String> {
+    let digits = color.trim();
+    let digits = digits.strip_prefix('#').unwrap_or(digits);
+    if !is_hex(digits) {
+        return None;
+    }
+    let digits: String = if digits.len() <= 4 {
+        digits.chars().take(3).flat_map(|c| [c, c]).collect()
+    } else {
+        digits[..6].to_string()
+    };
+    Some(digits.to_lowercase())
+}
+
+// Workbench colors keep their alpha and need the `#`.
+fn workbench_color(color: &str) -> Option<String> {
+    let digits = color.trim().strip_prefix('#')?;
+    is_hex(digits).then(|| format!("#{}", digits.to_lowercase()))
+}
+
+fn font_style(style: &str) -> String {
+    style
+        .split_whitespace()
+        .filter(|style| FONT_STYLES.contains(style))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn check_rule(index: usize, rule: &Value, errors: &mut Vec<String>) {
+    let field = |name: &str| format!("rules[{}].{}", index, name);
+
+    let Some(rule) = rule.as_object() else {
+        errors.push(format!("rules[{}]: must be an object", index));
+        return;
+    };
+    if !rule.get("token").is_some_and(Value::is_string) {
+        errors.push(format!("{}: must be a string", field("token")));
+    }
+    for key in ["foreground", "background"] {
+        if let Some(color) = rule.get(key) {
+            if !color.as_str().is_some_and(is_token_color) {
+                errors.push(format!("{}: must be a RRGGBB or RRGGBBAA hex color", field(key)));
+            }
+        }
+    }
+    if rule.get("fontStyle").is_some_and(|style| !style.is_string()) {
+        errors.push(format!("{}: must be a string", field("fontStyle")));
+    }
+}
+
+// Checks a theme in Monaco's format before it reaches the editor, which
+// would otherwise fail to define it and show a blank editor.
+pub fn validate_monaco_theme(theme: &Value) -> Result<(), String> {
+    let theme = theme.as_object().ok_or("a theme must be a JSON object")?;
+    let mut errors = Vec::new();
+
+    if !theme.get("base").and_then(Value::as_str).is_some_and(|base| BASES.contains(&base)) {
+        errors.push(format!("base: must be one of {}", BASES.join(", ")));
+    }
+    if theme.get("inherit").is_some_and(|inherit| !inherit.is_boolean()) {
+        errors.push("inherit: must be true or false".to_string());
+    }
+
+    match theme.get("rules") {
+        None => {}
+        Some(Value::Array(rules)) => {
+            for (index, rule) in rules.iter().enumerate() {
+                check_rule(index, rule, &mut errors);
+            }
+        }
+        Some(_) => errors.push("rules: must be an array".to_string()),
+    }
+
+    match theme.get("colors") {
+        None => {}
+        Some(Value::Object(colors)) => {
+            for (id, color) in colors {
+                if color.as_str().and_then(workbench_color).is_none() {
+                    errors.push(format!("colors.{}: must be a #RGB, #RGBA, #RRGGBB or #RRGGBBAA color", id));
+                }
+            }
+        }
+        Some(_) => errors.push("colors: must be an object".to_string()),
+    }
+
+    if !errors.is_empty() {
+        return Err(errors.join("; "));
+    }
+    Ok(())
+}
+
+fn scopes(scope: Option<&Value>) -> Vec<String> {
+    let selectors: Vec<&str> = match scope {
+        Some(Value::String(scope)) => scope.split(',').collect(),
+        Some(Value::Array(scopes)) => scopes.iter().filter_map(Value::as_str).flat_map(|scope| scope.split(',')).collect(),
+        _ => Vec::new(),
+    };
+    selectors
+        .into_iter()
+        .map(str::trim)
+        .filter(|selector| !selector.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn token_rule(token: &str, settings: &Map<String, Value>) -> Option<Value> {
+    let mut rule = Map::new();
+    for key in ["foreground", "background"] {
+        if let Some(color) = settings.get(key).and_then(Value::as_str).and_then(token_color) {
+            rule.insert(key.to_string(), Value::String(color));
+        }
+    }
+    if let Some(style) = settings.get("fontStyle").and_then(Value::as_str) {
+        rule.insert("fontStyle".to_string(), Value::String(font_style(style)));
+    }
+    if rule.is_empty() {
+        return None;
+    }
+    rule.insert("token".to_string(), Value::String(token.to_string()));
+    Some(Value::Object(rule))
+}
+
+// TextMate `settings` and VS Code `tokenColors` share a shape: an entry with
+// a `scope` becomes one rule per selector, and an entry without one holds
+// editor colors. Colors already set are kept.
+fn convert_settings(entries: &[Value], rules: &mut Vec<Value>, colors: &mut Map<String, Value>) {
+    for entry in entries {
+        let Some(settings) = entry.get("settings").and_then(Value::as_object) else {
+            continue;
+        };
+
+        if entry.get("scope").is_none() {
+            for (key, id) in TEXTMATE_COLORS {
+                if let Some(color) = settings.get(*key).and_then(Value::as_str).and_then(workbench_color) {
+                    colors.entry(id.to_string()).or_insert(Value::String(color));
+                }
+            }
+            continue;
+        }
+
+        for scope in scopes(entry.get("scope")) {
+            rules.extend(token_rule(&scope, settings));
+        }
+    }
+}
+
+fn is_dark(color: &str) -> bool {
+    let Some(digits) = token_color(color) else {
+        return true;
+    };
+    let channel = |i: usize| u8::from_str_radix(&digits[i..i + 2], 16).unwrap_or(0) as f64 / 255.0;
+    0.2126 * channel(0) + 0.7152 * channel(2) + 0.0722 * channel(4) < 0.5
+}
+
+// Builds a Monaco theme. The default foreground and background go into an
+// empty-token rule as well, which is what Monaco uses for plain text.
+fn monaco_theme(name: Option<&str>, base: Option<&str>, mut rules: Vec<Value>, colors: Map<String, Value>) -> Value {
+    let color = |id: &str| colors.get(id).and_then(Value::as_str).map(str::to_string);
+    let background = color("editor.background");
+    let foreground = color("editor.foreground");
+
+    let base = base.unwrap_or_else(|| {
+        if background.as_deref().is_none_or(is_dark) { "vs-dark" } else { "vs" }
+    });
+
+    let mut default_rule = Map::new();
+    default_rule.insert("foreground".to_string(), Value::String(foreground.unwrap_or_default()));
+    default_rule.insert("background".to_string(), Value::String(background.unwrap_or_default()));
+    if let Some(rule) = token_rule("", &default_rule) {
+        rules.insert(0, rule);
+    }
+
+    let mut theme = Map::new();
+    if let Some(name) = name {
+        theme.insert("name".to_string(), Value::String(name.to_string()));
+    }
+    theme.insert("base".to_string(), Value::String(base.to_string()));
+    theme.insert("inherit".to_string(), Value::Bool(true));
+    theme.insert("rules".to_string(), Value::Array(rules));
+    theme.insert("colors".to_string(), Value::Object(colors));
+    Value::Object(theme)
+}
+
+fn read_plist(path: &Path) -> Result<Value, String> {
+    let plist = plist::Value::from_file(path).map_err(|e| format!("Not a TextMate theme: {}", e))?;
+    serde_json::to_value(plist).map_err(|e| e.to_string())
+}
+
+pub fn convert_textmate(theme: &Value) -> Result<Value, String> {
+    let entries = theme
+        .get("settings")
+        .and_then(Value::as_array)
+        .ok_or("Not a TextMate theme: settings is missing")?;
+
+    let mut rules = Vec::new();
+    let mut colors = Map::new();
+    convert_settings(entries, &mut rules, &mut colors);
+
+    let name = theme.get("name").and_then(Value::as_str);
+    Ok(monaco_theme(name, None, rules, colors))
+}
+
+fn vscode_base(kind: &str) -> Option<&'static str> {
+    match kind {
+        "dark" => Some("vs-dark"),
+        "light" => Some("vs"),
+        "hc" | "hcDark" | "hc-black" => Some("hc-black"),
+        "hcLight" | "hc-light" => Some("hc-light"),
+        _ => None,
+    }
+}
+
+// Reads a VS Code theme, merging in the themes it includes. `tokenColors`
+// may also point at a TextMate theme next to it.
+fn read_vscode(path: &Path, depth: usize) -> Result<Value, String> {
+    if depth > MAX_INCLUDE_DEPTH {
+        return Err("Theme includes are nested too deeply".to_string());
+    }
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let mut theme: Value = json5::from_str(&content).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let dir = path.parent().unwrap_or(Path::new("."));
+
+    if let Some(Value::String(file)) = theme.get("tokenColors") {
+        let settings = read_plist(&dir.join(file))?.get("settings").cloned().unwrap_or(Value::Array(Vec::new()));
+        theme["tokenColors"] = settings;
+    }
+
+    let Some(Value::String(include)) = theme.get("include").cloned() else {
+        return Ok(theme);
+    };
+    let mut merged = read_vscode(&dir.join(include), depth + 1)?;
+
+    if let (Some(Value::Object(colors)), Some(Value::Object(own))) = (merged.get_mut("colors"), theme.get("colors")) {
+        colors.extend(own.clone());
+    } else if let Some(own) = theme.get("colors") {
+        merged["colors"] = own.clone();
+    }
+    if let (Some(Value::Array(token_colors)), Some(Value::Array(own))) = (merged.get_mut("tokenColors"), theme.get("tokenColors")) {
+        token_colors.extend(own.clone());
+    } else if let Some(own) = theme.get("tokenColors") {
+        merged["tokenColors"] = own.clone();
+    }
+    for key in ["name", "type"] {
+        if let Some(value) = theme.get(key) {
+            merged[key] = value.clone();
+        }
+    }
+    Ok(merged)
+}
+
+pub fn convert_vscode(theme: &Value) -> Result<Value, String> {
+    let mut colors = Map::new();
+    if let Some(workbench) = theme.get("colors").and_then(Value::as_object) {
+        for (id, color) in workbench {
+            if let Some(color) = color.as_str().and_then(workbench_color) {
+                colors.insert(id.clone(), Value::String(color));
+            }
+        }
+    }
+
+    let mut rules = Vec::new();
+    if let Some(entries) = theme.get("tokenColors").and_then(Value::as_array) {
+        convert_settings(entries, &mut rules, &mut colors);
+    }
+
+    let name = theme.get("name").and_then(Value::as_str);
+    let base = theme.get("type").and_then(Value::as_str).and_then(vscode_base);
+    Ok(monaco_theme(name, base, rules, colors))
+}
+
+fn is_textmate(path: &Path) -> bool {
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default().to_lowercase();
+    if extension == "tmtheme" || extension == "plist" {
+        return true;
+    }
+    fs::read_to_string(path)
+        .map(|content| {
+            let start = content.trim_start();
+            start.starts_with("<?xml") || start.starts_with("<plist")
+        })
+        .unwrap_or(false)
+}
+
+// Reads a TextMate, VS Code or Monaco theme and returns it in Monaco's
+// format.
+pub fn convert_file(path: &Path) -> Result<Value, String> {
+    if is_textmate(path) {
+        return convert_textmate(&read_plist(path)?);
+    }
+
+    let theme = read_vscode(path, 0)?;
+    if !theme.is_object() {
+        return Err("Not a theme: expected a JSON object".to_string());
+    }
+    if theme.get("base").is_some() && theme.get("tokenColors").is_none() {
+        validate_monaco_theme(&theme)?;
+        return Ok(theme);
+    }
+    if theme.get("tokenColors").is_none() && theme.get("colors").is_none() {
+        return Err("Not a Monaco, VS Code or TextMate theme".to_string());
+    }
+    convert_vscode(&theme)
+}
+
+// Monaco only accepts theme names made of ASCII letters, digits and '-', so
+// "Solarized: Dark" is saved as "solarized-dark".
+pub fn theme_id_for(name: &str) -> String {
+    let mut id = String::new();
+    for c in name.chars().flat_map(char::to_lowercase) {
+        if c.is_ascii_alphanumeric() {
+            id.push(c);
+        } else if !id.is_empty() && !id.ends_with('-') {
+            id.push('-');
+        }
+        if id.len() >= 100 {
+            break;
+        }
+    }
+    let id = id.trim_end_matches('-');
+    if id.is_empty() {
+        "theme".to_string()
+    } else {
+        id.to_string()
+    }
+}
+
+// Converts the theme at `path` and saves it into the themes directory under
+// an id made from `name`, the theme's own name or the file name. Returns the
+// id, which is the name to apply it by.
+pub fn import_theme(app_handle: &tauri::AppHandle, path: &Path, name: Option<&str>) -> Result<String, String> {
+    let mut theme = convert_file(path)?;
+    validate_monaco_theme(&theme).map_err(|e| format!("The converted theme is invalid: {}", e))?;
+
+    // The file is named after an id Monaco accepts; the name shown in the
+    // menu stays in the theme's `name`.
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+    let display_name = name
+        .or_else(|| theme.get("name").and_then(Value::as_str))
+        .unwrap_or(stem)
+        .trim()
+        .to_string();
+    let name = theme_id_for(&display_name);
+    if let Some(theme) = theme.as_object_mut() {
+        theme.insert("name".to_string(), Value::String(display_name));
+    }
+    if is_builtin(&name) {
+        return Err(format!("{} is a built-in theme", name));
+    }
+
+    let theme_path = theme_path(&ConfigManager::get_monaco_themes_dir(app_handle)?, &name)?;
+    if theme_path.exists() {
+        return Err(format!("Theme already exists: {}", name));
+    }
+    let content = serde_json::to_string_pretty(&theme).map_err(|e| e.to_string())?;
+    config::write_atomic(&theme_path, &content)?;
+    Ok(name)
+}
+
+pub fn is_theme_id(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+// Theme names come from the frontend and become file names. Only names
+// Monaco accepts are allowed, which also keeps them in the themes directory.
+fn theme_path(themes_dir: &Path, name: &str) -> Result<PathBuf, String> {
+    if !is_theme_id(name) {
+        return Err(format!(
+            "Invalid theme name: {}. Theme file names may only contain letters, digits and '-', such as {}.json",
+            name,
+            theme_id_for(name)
+        ));
+    }
+    Ok(themes_dir.join(format!("{}.json", name)))
+}
+
+fn read_theme(themes_dir: &Path, name: &str) -> Result<Value, String> {
+    let path = theme_path(themes_dir, name)?;
+    if !path.exists() {
+        return Err(format!("Theme file not found: {}", name));
+    }
+
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let theme: Value = serde_json::from_str(&content)
+        .map_err(|e| format!("Theme {} is not valid JSON: {}", name, e))?;
+    validate_monaco_theme(&theme)
+        .map_err(|e| format!("Theme {} is invalid: {}", name, e))?;
+    Ok(theme)
+}
+
+// Editor background and foreground of each base, for themes that don't set
+// their own.
+fn base_colors(base: &str) -> (&'static str, &'static str) {
+    match base {
+        "vs" => ("#fffffe", "#000000"),
+        "hc-black" => ("#000000", "#ffffff"),
+        "hc-light" => ("#ffffff", "#292929"),
+        _ => ("#1e1e1e", "#d4d4d4"),
+    }
+}
+
+fn rule_color(rules: &[Value], token: &str) -> Option<String> {
+    let prefix = format!("{}.", token);
+    rules
+        .iter()
+        .filter(|rule| rule.get("token").and_then(Value::as_str).is_some_and(|t| t == token || t.starts_with(&prefix)))
+        .find_map(|rule| rule.get("foreground").and_then(Value::as_str).and_then(token_color))
+        .map(|digits| format!("#{}", digits))
+}
+
+pub fn theme_info(name: &str, theme: &Value, builtin: bool) -> ThemeInfo {
+    let base = theme.get("base").and_then(Value::as_str).unwrap_or("vs-dark").to_string();
+    let (default_background, default_foreground) = base_colors(&base);
+
+    let colors = theme.get("colors").and_then(Value::as_object);
+    let color = |id: &str, default: &str| {
+        colors
+            .and_then(|colors| colors.get(id))
+            .and_then(Value::as_str)
+            .and_then(workbench_color)
+            .unwrap_or_else(|| default.to_string())
+    };
+    let rules = theme.get("rules").and_then(Value::as_array).map(Vec::as_slice).unwrap_or_default();
+
+    ThemeInfo {
+        name: name.to_string(),
+        display_name: theme.get("name").and_then(Value::as_str).unwrap_or(name).to_string(),
+        dark: matches!(base.as_str(), "vs-dark" | "hc-black"),
+        builtin,
+        preview: ThemePreview {
+            background: color("editor.background", default_background),
+            foreground: color("editor.foreground", default_foreground),
+            keyword: rule_color(rules, "keyword"),
+            string: rule_color(rules, "string"),
+            comment: rule_color(rules, "comment"),
+        },
+        base,
+        error: None,
+    }
+}
+
+fn theme_name(path: &Path) -> Option<String> {
+    if path.extension().and_then(|e| e.to_str()) != Some("json") {
+        return None;
+    }
+    path.file_stem()
+        .and_then(|stem| stem.to_str())
+        .filter(|stem| config::is_safe_file_name(stem))
+        .map(|stem| stem.to_string())
+}
+
+fn builtin_theme(name: &str, display_name: &str) -> Value {
+    serde_json::json!({ "name": display_name, "base": name })
+}
+
+// A theme by name in Monaco's format. Built-in themes are just their base.
+pub fn load_theme(app_handle: &tauri::AppHandle, name: &str) -> Result<Value, String> {
+    if let Some((name, display_name)) = BUILTIN_THEMES.iter().find(|(builtin, _)| *builtin == name) {
+        return Ok(builtin_theme(name, display_name));
+    }
+    read_theme(&ConfigManager::get_monaco_themes_dir(app_handle)?, name)
+}
+
+// Built-in themes first, then the theme files sorted by display name. Files
+// that fail validation are listed with their error so they can be fixed.
+pub fn list(themes_dir: &Path) -> Vec<ThemeInfo> {
+    let mut themes: Vec<ThemeInfo> = fs::read_dir(themes_dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|entry| theme_name(&entry.path()))
+                .map(|name| match read_theme(themes_dir, &name) {
+                    Ok(theme) => theme_info(&name, &theme, false),
+                    Err(e) => ThemeInfo { error: Some(e), ..theme_info(&name, &Value::Null, false) },
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    themes.sort_by_key(|theme| theme.display_name.to_lowercase());
+
+    let builtins = BUILTIN_THEMES
+        .iter()
+        .map(|(name, display_name)| theme_info(name, &builtin_theme(name, display_name), true));
+    builtins.chain(themes).collect()
+}
+
+fn schedule_reload(app_handle: &tauri::AppHandle, themes_dir: &Path, changed: Vec<String>) {
+    let generation = {
+        let state = app_handle.state::<Mutex<ThemeState>>();
+        let mut state = state.lock().unwrap();
+        state.pending.extend(changed);
+        state.generation += 1;
+        state.generation
+    };
+
+    let handle = app_handle.clone();
+    let themes_dir = themes_dir.to_path_buf();
+    std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(RELOAD_DELAY_MS));
+        let mut changed = {
+            let state = handle.state::<Mutex<ThemeState>>();
+            let mut state = state.lock().unwrap();
+            if state.generation != generation {
+                return;
+            }
+            std::mem::take(&mut state.pending)
+        };
+        changed.sort();
+        changed.dedup();
+        let _ = handle.emit("themes-changed", ThemesChanged { themes: list(&themes_dir), changed });
+    });
+}
+
+// Emits `themes-changed` with the new list whenever a theme file is added,
+// changed or removed, so themes dropped in show up without a restart.
+pub fn watch_themes(app_handle: &tauri::AppHandle) -> Result<(), String> {
+    let themes_dir = ConfigManager::get_monaco_themes_dir(app_handle)?;
+
+    let handle = app_handle.clone();
+    let dir = themes_dir.clone();
+    let mut watcher = notify::recommended_watcher(move |res: Result<Event, notify::Error>| {
+        match res {
+            Ok(event) => {
+                // Listing the themes reads every file, which must not
+                // trigger another round.
+                let relevant = matches!(event.kind, EventKind::Create(_) | EventKind::Remove(_))
+                    || matches!(event.kind, EventKind::Modify(kind) if !matches!(kind, notify::event::ModifyKind::Metadata(_)));
+                if !relevant {
+                    return;
+                }
+                let changed: Vec<String> = event.paths.iter().filter_map(|path| theme_name(path)).collect();
+                if !changed.is_empty() {
+                    schedule_reload(&handle, &dir, changed);
+                }
+            }
+            Err(e) => println!("Theme watch error: {:?}", e),
+        }
+    }).map_err(|e| e.to_string())?;
+
+    watcher.watch(&themes_dir, RecursiveMode::NonRecursive)
+        .map_err(|e| e.to_string())?;
+
+    // The old watcher is dropped outside the lock, as its callback takes it.
+    let state = app_handle.state::<Mutex<ThemeState>>();
+    let previous = state.lock().map_err(|e| e.to_string())?.watcher.replace(watcher);
+    drop(previous);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_monaco_themes(app_handle: tauri::AppHandle) -> Result<Vec<ThemeInfo>, String> {
+    let themes_dir = ConfigManager::get_monaco_themes_dir(&app_handle)?;
+    Ok(list(&themes_dir))
+}
+
+#[tauri::command]
+pub fn read_monaco_theme(app_handle: tauri::AppHandle, theme_name: String) -> Result<String, String> {
+    if is_builtin(&theme_name) {
+        return Ok(String::new());
+    }
+
+    let themes_dir = ConfigManager::get_monaco_themes_dir(&app_handle)?;
+    let theme = read_theme(&themes_dir, &theme_name)?;
+    serde_json::to_string(&theme).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn import_monaco_theme(app_handle: tauri::AppHandle, path: String, name: Option<String>) -> Result<String, String> {
+    import_theme(&app_handle, Path::new(&path), name.as_deref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn valid_monaco_theme_passes() {
+        let theme = json!({
+            "base": "vs-dark",
+            "inherit": true,
+            "rules": [{ "token": "comment", "foreground": "6A9955", "fontStyle": "italic" }],
+            "colors": { "editor.background": "#1e1e1e" }
+        });
+        assert!(validate_monaco_theme(&theme).is_ok());
+        assert!(validate_monaco_theme(&json!({ "base": "vs" })).is_ok());
+    }
+
+    #[test]
+    fn invalid_monaco_theme_lists_every_problem() {
+        let theme = json!({
+            "base": "dark",
+            "inherit": "yes",
+            "rules": [{ "foreground": "#fff" }, 3],
+            "colors": { "editor.background": "red" }
+        });
+        let error = validate_monaco_theme(&theme).unwrap_err();
+        for expected in ["base:", "inherit:", "rules[0].token", "rules[0].foreground", "rules[1]:", "colors.editor.background"] {
+            assert!(error.contains(expected), "{} in {}", expected, error);
+        }
+        assert!(validate_monaco_theme(&json!([])).is_err());
+    }
+
+    #[test]
+    fn token_colors_are_normalized() {
+        assert_eq!(token_color("#ABC").as_deref(), Some("aabbcc"));
+        assert_eq!(token_color(" #11223344 ").as_deref(), Some("112233"));
+        assert_eq!(token_color("ff0000").as_deref(), Some("ff0000"));
+        assert_eq!(token_color("red"), None);
+        assert_eq!(workbench_color("#11223344").as_deref(), Some("#11223344"));
+        assert_eq!(workbench_color("112233"), None);
+    }
+
+    #[test]
+    fn textmate_settings_become_rules_and_colors() {
+        let theme = json!({
+            "name": "Paper",
+            "settings": [
+                { "settings": { "background": "#FFFFFF", "foreground": "#333333", "caret": "#000000" } },
+                { "scope": "comment, string.quoted", "settings": { "foreground": "#999", "fontStyle": "italic bogus" } },
+                { "scope": "keyword", "settings": {} }
+            ]
+        });
+        let converted = convert_textmate(&theme).unwrap();
+        assert!(validate_monaco_theme(&converted).is_ok());
+        assert_eq!(converted["name"], "Paper");
+        assert_eq!(converted["base"], "vs");
+        assert_eq!(converted["colors"]["editorCursor.foreground"], "#000000");
+        assert_eq!(converted["rules"], json!([
+            { "token": "", "foreground": "333333", "background": "ffffff" },
+            { "token": "comment", "foreground": "999999", "fontStyle": "italic" },
+            { "token": "string.quoted", "foreground": "999999", "fontStyle": "italic" }
+        ]));
+        assert!(convert_textmate(&json!({ "name": "x" })).is_err());
+    }
+
+    #[test]
+    fn vscode_type_picks_the_base() {
+        let theme = json!({
+            "type": "hcLight",
+            "colors": { "editor.background": "#000000", "bad": "blue" },
+            "tokenColors": [{ "scope": ["keyword"], "settings": { "foreground": "#ff0000" } }]
+        });
+        let converted = convert_vscode(&theme).unwrap();
+        assert_eq!(converted["base"], "hc-light");
+        assert!(converted["colors"].get("bad").is_none());
+        assert_eq!(converted["rules"][1], json!({ "token": "keyword", "foreground": "ff0000" }));
+
+        let dark = convert_vscode(&json!({ "colors": { "editor.background": "#101010" } })).unwrap();
+        assert_eq!(dark["base"], "vs-dark");
+    }
+
+    #[test]
+    fn vscode_includes_are_merged() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("base.json"), r##"{
+            // Comments and trailing commas are allowed.
+            "name": "Base",
+            "type": "dark",
+            "colors": { "editor.background": "#000000", "editor.foreground": "#ffffff", },
+            "tokenColors": [{ "scope": "comment", "settings": { "foreground": "#00ff00" } }],
+        }"##).unwrap();
+        fs::write(dir.path().join("child.json"), r##"{
+            "include": "./base.json",
+            "name": "Child",
+            "colors": { "editor.background": "#111111" },
+            "tokenColors": [{ "scope": "string", "settings": { "foreground": "#ff0000" } }]
+        }"##).unwrap();
+
+        let converted = convert_file(&dir.path().join("child.json")).unwrap();
+        assert_eq!(converted["name"], "Child");
+        assert_eq!(converted["base"], "vs-dark");
+        assert_eq!(converted["colors"]["editor.background"], "#111111");
+        assert_eq!(converted["colors"]["editor.foreground"], "#ffffff");
+        let tokens: Vec<&str> = converted["rules"].as_array().unwrap().iter().filter_map(|rule| rule["token"].as_str()).collect();
+        assert_eq!(tokens, ["", "comment", "string"]);
+    }
+
+    #[test]
+    fn include_cycles_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("loop.json"), r#"{ "include": "loop.json", "colors": {} }"#).unwrap();
+        assert!(convert_file(&dir.path().join("loop.json")).is_err());
+    }
+
+    #[test]
+    fn theme_ids_are_names_monaco_accepts() {
+        assert_eq!(theme_id_for("Solarized: Dark"), "solarized-dark");
+        assert_eq!(theme_id_for("  One   Dark Pro!! "), "one-dark-pro");
+        assert_eq!(theme_id_for("Café"), "caf");
+        assert_eq!(theme_id_for("***"), "theme");
+        assert_eq!(theme_id_for(&"a".repeat(300)).len(), 100);
+        for name in ["Solarized: Dark", "***", "Café"] {
+            assert!(is_theme_id(&theme_id_for(name)));
+        }
+    }
+
+    #[test]
+    fn theme_paths_only_take_theme_ids() {
+        assert!(is_theme_id("one-dark-2"));
+        for name in ["", "One Dark", "../evil", "a.b", "a_b"] {
+            assert!(!is_theme_id(name), "{}", name);
+        }
+
+        let dir = Path::new("/themes");
+        assert_eq!(theme_path(dir, "one-dark").unwrap(), dir.join("one-dark.json"));
+        let error = theme_path(dir, "One Dark").unwrap_err();
+        assert!(error.contains("one-dark.json"), "{}", error);
+    }
+}
@@ -0,0 +1,581 @@
+use globset::{Glob, GlobMatcher};
+use notify::event::{CreateKind, ModifyKind, RenameMode};
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use serde::Serialize;
+use similar::{DiffOp, TextDiff};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{Emitter, Manager};
+
+use crate::config::{ConfigManager, WatchedFolder};
+use crate::{follow, replace};
+
+// The window created from tauri.conf.json, which gets the new files of
+// folders watched before windows were recorded.
+const MAIN_WINDOW: &str = "main";
+
+pub struct WatcherState {
+    watchers: HashMap<String, WatchEntry>,
+    snapshots: HashMap<String, FileSnapshot>,
+}
+
+impl WatcherState {
+    pub fn new() -> Self {
+        Self {
+            watchers: HashMap::new(),
+            snapshots: HashMap::new(),
+        }
+    }
+
+    // Returns the watch once no window uses it. It must be dropped after the
+    // lock is released: dropping a watcher can wait for its callback, which
+    // takes the lock too.
+    fn release(&mut self, path: &str, label: &str) -> Option<WatchEntry> {
+        let entry = self.watchers.get_mut(path)?;
+        if let Some(count) = entry.windows.get_mut(label) {
+            *count -= 1;
+            if *count == 0 {
+                entry.windows.remove(label);
+            }
+        }
+        if !entry.windows.is_empty() {
+            return None;
+        }
+        self.snapshots.remove(path);
+        self.watchers.remove(path)
+    }
+}
+
+struct WatchEntry {
+    _watcher: notify::RecommendedWatcher,
+    windows: HashMap<String, usize>,
+}
+
+#[derive(Serialize)]
+pub struct WatchInfo {
+    path: String,
+    windows: HashMap<String, usize>,
+    following: bool,
+    following_windows: Vec<String>,
+}
+
+struct FileSnapshot {
+    encoding: Option<String>,
+    content: String,
+    hash: String,
+}
+
+#[derive(Serialize, Clone)]
+struct LineEdit {
+    start: usize,
+    delete: usize,
+    insert: String,
+}
+
+#[derive(Serialize, Clone)]
+struct FileChanged {
+    path: String,
+    hash: String,
+    modified: u64,
+    edits: Vec<LineEdit>,
+    content: Option<String>,
+}
+
+// Records the content last handed to (or received from) the frontend so that
+// watcher events can be compared against it instead of forwarded blindly.
+// Only watched files keep a snapshot; `watch_file` takes one itself.
+pub fn remember_snapshot(app_handle: &tauri::AppHandle, path: &str, encoding: Option<String>, content: &str, hash: &str) {
+    let state = app_handle.state::<Mutex<WatcherState>>();
+    let mut state = state.lock().unwrap();
+
+    if !state.watchers.contains_key(path) {
+        return;
+    }
+    state.snapshots.insert(path.to_string(), FileSnapshot {
+        encoding,
+        content: content.to_string(),
+        hash: hash.to_string(),
+    });
+}
+
+pub fn update_snapshot(app_handle: &tauri::AppHandle, path: &str, content: &str) {
+    let state = app_handle.state::<Mutex<WatcherState>>();
+    let mut state = state.lock().unwrap();
+
+    if let Some(snapshot) = state.snapshots.get_mut(path) {
+        snapshot.content = content.to_string();
+        snapshot.hash = crate::calculate_file_hash(content);
+    }
+}
+
+// Follow mode appends to the buffer without a `file-changed`, so the
+// snapshot has to grow along with it.
+pub fn append_snapshot(app_handle: &tauri::AppHandle, path: &str, content: &str, reset: bool) {
+    let state = app_handle.state::<Mutex<WatcherState>>();
+    let mut state = state.lock().unwrap();
+
+    if let Some(snapshot) = state.snapshots.get_mut(path) {
+        if reset {
+            snapshot.content = content.to_string();
+        } else {
+            snapshot.content.push_str(content);
+        }
+        snapshot.hash = crate::calculate_file_hash(&snapshot.content);
+    }
+}
+
+fn line_edits(old: &str, new: &str) -> Vec<LineEdit> {
+    let diff = TextDiff::from_lines(old, new);
+    let new_lines = diff.new_slices();
+
+    diff.ops()
+        .iter()
+        .filter(|op| !matches!(op, DiffOp::Equal { .. }))
+        .map(|op| LineEdit {
+            start: op.old_range().start,
+            delete: op.old_range().len(),
+            insert: new_lines[op.new_range()].concat(),
+        })
+        .collect()
+}
+
+fn watching_windows(app_handle: &tauri::AppHandle, path: &str) -> Vec<String> {
+    let state = app_handle.state::<Mutex<WatcherState>>();
+    let state = state.lock().unwrap();
+    state.watchers.get(path)
+        .map(|entry| entry.windows.keys().cloned().collect())
+        .unwrap_or_default()
+}
+
+fn check_for_changes(app_handle: &tauri::AppHandle, path: &str) -> Result<Option<FileChanged>, String> {
+    let state = app_handle.state::<Mutex<WatcherState>>();
+    let encoding = {
+        let state = state.lock().unwrap();
+        state.snapshots.get(path).and_then(|snapshot| snapshot.encoding.clone())
+    };
+
+    let content = crate::read_file_content(path, encoding.as_deref())?;
+    let hash = crate::calculate_file_hash(&content);
+    let modified = crate::file_modified_secs(path)?;
+
+    let mut state = state.lock().unwrap();
+    let (edits, full_content) = match state.snapshots.get(path) {
+        Some(snapshot) if snapshot.hash == hash => return Ok(None),
+        Some(snapshot) => (line_edits(&snapshot.content, &content), None),
+        None => (vec![], Some(content.clone())),
+    };
+
+    state.snapshots.insert(path.to_string(), FileSnapshot {
+        encoding,
+        content,
+        hash: hash.clone(),
+    });
+
+    Ok(Some(FileChanged {
+        path: path.to_string(),
+        hash,
+        modified,
+        edits,
+        content: full_content,
+    }))
+}
+
+fn report_changes(app_handle: &tauri::AppHandle, path: &str) {
+    match check_for_changes(app_handle, path) {
+        Ok(Some(changed)) => {
+            for label in watching_windows(app_handle, path) {
+                let _ = app_handle.emit_to(label.as_str(), "file-changed", changed.clone());
+            }
+        }
+        Ok(None) => {}
+        Err(e) => println!("Watch error: {}", e),
+    }
+}
+
+// Watches the file's directory rather than the file: editors that save by
+// writing a new file and renaming it over the old one replace the file, and
+// a watch on the file itself would stop there.
+fn create_watcher(app_handle: &tauri::AppHandle, path: &str) -> Result<notify::RecommendedWatcher, String> {
+    let file_path = PathBuf::from(path);
+    let parent = file_path.parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .ok_or_else(|| format!("Cannot watch {}: no parent directory", path))?
+        .to_path_buf();
+    let file_name = file_path.file_name()
+        .ok_or_else(|| format!("Cannot watch {}: not a file", path))?
+        .to_os_string();
+
+    let handle = app_handle.clone();
+    let watched_path = path.to_string();
+    let mut watcher = notify::recommended_watcher(move |res: Result<Event, notify::Error>| {
+        match res {
+            Ok(event) => {
+                // Reading the file must not count as a change.
+                let relevant = matches!(event.kind, EventKind::Create(_))
+                    || matches!(event.kind, EventKind::Modify(kind) if !matches!(kind, ModifyKind::Metadata(_)));
+                if !relevant || !event.paths.iter().any(|p| p.file_name() == Some(file_name.as_os_str())) {
+                    return;
+                }
+                if follow::is_followed(&handle, &file_path) {
+                    return;
+                }
+                report_changes(&handle, &watched_path);
+            }
+            Err(e) => println!("Watch error: {:?}", e),
+        }
+    }).map_err(|e| e.to_string())?;
+
+    watcher.watch(&parent, RecursiveMode::NonRecursive)
+        .map_err(|e| e.to_string())?;
+    Ok(watcher)
+}
+
+pub fn release_window(app_handle: &tauri::AppHandle, label: &str) {
+    let released: Vec<WatchEntry> = {
+        let state = app_handle.state::<Mutex<WatcherState>>();
+        let mut state = state.lock().unwrap();
+
+        for entry in state.watchers.values_mut() {
+            entry.windows.remove(label);
+        }
+
+        let unused: Vec<String> = state.watchers.iter()
+            .filter(|(_, entry)| entry.windows.is_empty())
+            .map(|(path, _)| path.clone())
+            .collect();
+
+        unused.iter()
+            .filter_map(|path| {
+                state.snapshots.remove(path);
+                state.watchers.remove(path)
+            })
+            .collect()
+    };
+    drop(released);
+}
+
+#[tauri::command]
+pub fn watch_file(path: String, encoding: Option<String>, window: tauri::Window) -> Result<(), String> {
+    let app_handle = window.app_handle();
+    let label = window.label().to_string();
+    let needs_snapshot = {
+        let state = app_handle.state::<Mutex<WatcherState>>();
+        let mut state = state.lock().unwrap();
+        if let Some(entry) = state.watchers.get_mut(&path) {
+            *entry.windows.entry(label).or_insert(0) += 1;
+            return Ok(());
+        }
+        !state.snapshots.contains_key(&path)
+    };
+
+    // Read and hashed without the lock, which every watcher callback takes.
+    let snapshot = needs_snapshot
+        .then(|| crate::read_file_content(&path, encoding.as_deref()).ok())
+        .flatten()
+        .map(|content| {
+            let hash = crate::calculate_file_hash(&content);
+            FileSnapshot { encoding, content, hash }
+        });
+    let watcher = create_watcher(app_handle, &path)?;
+
+    let unused = {
+        let state = app_handle.state::<Mutex<WatcherState>>();
+        let mut state = state.lock().unwrap();
+        if let Some(snapshot) = snapshot {
+            state.snapshots.entry(path.clone()).or_insert(snapshot);
+        }
+        // Another window may have started watching the file meanwhile.
+        if let Some(entry) = state.watchers.get_mut(&path) {
+            *entry.windows.entry(label).or_insert(0) += 1;
+            Some(watcher)
+        } else {
+            let windows = HashMap::from([(label, 1)]);
+            state.watchers.insert(path, WatchEntry { _watcher: watcher, windows });
+            None
+        }
+    };
+    drop(unused);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn unwatch_file(path: String, window: tauri::Window) -> Result<(), String> {
+    let app_handle = window.app_handle();
+    let released = {
+        let state = app_handle.state::<Mutex<WatcherState>>();
+        let mut state = state.lock().unwrap();
+        state.release(&path, window.label())
+    };
+    drop(released);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn list_watches(app_handle: tauri::AppHandle) -> Vec<WatchInfo> {
+    // Collected first, so the follow and watcher locks are never held together.
+    let mut followed: HashMap<String, Vec<String>> = follow::followed(&app_handle).into_iter().collect();
+
+    let state = app_handle.state::<Mutex<WatcherState>>();
+    let state = state.lock().unwrap();
+
+    let mut watches: Vec<WatchInfo> = state.watchers.iter()
+        .map(|(path, entry)| {
+            let following_windows = followed.remove(path).unwrap_or_default();
+            WatchInfo {
+                path: path.clone(),
+                windows: entry.windows.clone(),
+                following: !following_windows.is_empty(),
+                following_windows,
+            }
+        })
+        .collect();
+
+    // Paths that are followed without being watched.
+    watches.extend(followed.into_iter().map(|(path, following_windows)| WatchInfo {
+        path,
+        windows: HashMap::new(),
+        following: true,
+        following_windows,
+    }));
+
+    watches.sort_by(|a, b| a.path.cmp(&b.path));
+    watches
+}
+
+pub struct FolderWatchState {
+    folders: HashMap<String, notify::RecommendedWatcher>,
+}
+
+impl FolderWatchState {
+    pub fn new() -> Self {
+        Self {
+            folders: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Clone)]
+struct FolderFileCreated {
+    folder: String,
+    path: String,
+    follow: bool,
+}
+
+fn compile_pattern(pattern: Option<&str>) -> Result<Option<GlobMatcher>, String> {
+    match pattern.filter(|p| !p.is_empty()) {
+        Some(pattern) => Glob::new(pattern)
+            .map(|glob| Some(glob.compile_matcher()))
+            .map_err(|e| format!("Invalid pattern {}: {}", pattern, e)),
+        None => Ok(None),
+    }
+}
+
+// Files already in a watched folder, so that one renamed over them is not
+// reported as new.
+fn existing_files(root: &Path) -> HashSet<PathBuf> {
+    ignore::WalkBuilder::new(root)
+        .standard_filters(false)
+        .build()
+        .flatten()
+        .filter(|entry| entry.file_type().is_some_and(|file_type| file_type.is_file()))
+        .map(|entry| entry.into_path())
+        .collect()
+}
+
+fn start_folder_watch(app_handle: &tauri::AppHandle, folder: &WatchedFolder) -> Result<(), String> {
+    let root = PathBuf::from(&folder.path);
+    if !root.is_dir() {
+        return Err(format!("Not a directory: {}", folder.path));
+    }
+
+    let matcher = compile_pattern(folder.pattern.as_deref())?;
+    let handle = app_handle.clone();
+    let folder_path = folder.path.clone();
+    let follow = folder.follow.unwrap_or(false);
+    let window = folder.window.clone().unwrap_or_else(|| MAIN_WINDOW.to_string());
+    let watch_root = root.clone();
+    let mut known = existing_files(&root);
+
+    let mut watcher = notify::recommended_watcher(move |res: Result<Event, notify::Error>| {
+        match res {
+            Ok(event) => {
+                let created: &[PathBuf] = match event.kind {
+                    EventKind::Create(CreateKind::File | CreateKind::Any)
+                    | EventKind::Modify(ModifyKind::Name(RenameMode::To)) => &event.paths,
+                    EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
+                        if let Some(from) = event.paths.first() {
+                            known.retain(|path| !path.starts_with(from));
+                        }
+                        event.paths.get(1..).unwrap_or_default()
+                    }
+                    EventKind::Modify(ModifyKind::Name(RenameMode::From)) | EventKind::Remove(_) => {
+                        for removed in &event.paths {
+                            known.retain(|path| !path.starts_with(removed));
+                        }
+                        return;
+                    }
+                    _ => return,
+                };
+
+                for path in created.iter().filter(|p| p.is_file()) {
+                    // Atomic saves rename a temporary file over one that was
+                    // already there, which is not a new file.
+                    if replace::is_staging_path(path) || !known.insert(path.clone()) {
+                        continue;
+                    }
+                    let relative = path.strip_prefix(&watch_root).unwrap_or(path);
+                    if matcher.as_ref().is_some_and(|m| !m.is_match(relative)) {
+                        continue;
+                    }
+                    let _ = handle.emit_to(window.as_str(), "folder-file-created", FolderFileCreated {
+                        folder: folder_path.clone(),
+                        path: path.to_string_lossy().into_owned(),
+                        follow,
+                    });
+                }
+            }
+            Err(e) => println!("Folder watch error: {:?}", e),
+        }
+    }).map_err(|e| e.to_string())?;
+
+    watcher.watch(&root, RecursiveMode::Recursive)
+        .map_err(|e| e.to_string())?;
+
+    let previous = {
+        let state = app_handle.state::<Mutex<FolderWatchState>>();
+        let mut state = state.lock().unwrap();
+        state.folders.insert(folder.path.clone(), watcher)
+    };
+    drop(previous);
+    Ok(())
+}
+
+pub fn restore_watched_folders(app_handle: &tauri::AppHandle) {
+    let folders = ConfigManager::get_watched_folders(app_handle).unwrap_or_default();
+    for folder in folders {
+        if let Err(e) = start_folder_watch(app_handle, &folder) {
+            println!("Failed to restore folder watch {}: {}", folder.path, e);
+        }
+    }
+}
+
+#[tauri::command]
+pub fn watch_folder(app_handle: tauri::AppHandle, window: tauri::Window, path: String, pattern: Option<String>, follow: Option<bool>) -> Result<(), String> {
+    let path = std::fs::canonicalize(&path)
+        .map_err(|e| e.to_string())?
+        .to_string_lossy()
+        .into_owned();
+    let folder = WatchedFolder { path, pattern, follow, window: Some(window.label().to_string()) };
+
+    let previous = {
+        let state = app_handle.state::<Mutex<FolderWatchState>>();
+        let mut state = state.lock().unwrap();
+        state.folders.remove(&folder.path)
+    };
+    drop(previous);
+    start_folder_watch(&app_handle, &folder)?;
+
+    let mut folders = ConfigManager::get_watched_folders(&app_handle)?;
+    folders.retain(|f| f.path != folder.path);
+    folders.push(folder);
+    ConfigManager::set_watched_folders(&app_handle, folders)
+}
+
+#[tauri::command]
+pub fn unwatch_folder(app_handle: tauri::AppHandle, path: String) -> Result<(), String> {
+    // Folders are stored canonicalized; a folder that no longer exists can
+    // only be matched by the path it was stored under.
+    let path = std::fs::canonicalize(&path)
+        .map(|p| p.to_string_lossy().into_owned())
+        .unwrap_or(path);
+
+    let removed = {
+        let state = app_handle.state::<Mutex<FolderWatchState>>();
+        let mut state = state.lock().unwrap();
+        state.folders.remove(&path)
+    };
+    drop(removed);
+
+    let mut folders = ConfigManager::get_watched_folders(&app_handle)?;
+    folders.retain(|f| Path::new(&f.path) != Path::new(&path));
+    ConfigManager::set_watched_folders(&app_handle, folders)
+}
+
+#[tauri::command]
+pub fn get_watched_folders(app_handle: tauri::AppHandle) -> Result<Vec<WatchedFolder>, String> {
+    ConfigManager::get_watched_folders(&app_handle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(old: &str, edits: &[LineEdit]) -> String {
+        let mut lines: Vec<String> = old.split_inclusive('\n').map(String::from).collect();
+        for edit in edits.iter().rev() {
+            let inserted = edit.insert.split_inclusive('\n').map(String::from);
+            lines.splice(edit.start..edit.start + edit.delete, inserted);
+        }
+        lines.concat()
+    }
+
+    #[test]
+    fn line_edits_describe_only_changed_lines() {
+        let edits = line_edits("a\nb\n", "a\nb\nc\n");
+        let edits: Vec<_> = edits.iter().map(|edit| (edit.start, edit.delete, edit.insert.as_str())).collect();
+        assert_eq!(edits, [(2, 0, "c\n")]);
+
+        assert!(line_edits("same\n", "same\n").is_empty());
+    }
+
+    #[test]
+    fn line_edits_rebuild_the_new_content() {
+        let old = "one\ntwo\nthree\nfour\n";
+        let new = "zero\none\n2\nthree\n";
+        assert_eq!(apply(old, &line_edits(old, new)), new);
+    }
+
+    #[test]
+    fn release_drops_the_watch_with_its_last_window() {
+        let mut state = WatcherState::new();
+        let watcher = notify::recommended_watcher(|_: Result<Event, notify::Error>| {}).unwrap();
+        let windows = HashMap::from([("main".to_string(), 2), ("other".to_string(), 1)]);
+        state.watchers.insert("/a.txt".to_string(), WatchEntry { _watcher: watcher, windows });
+        state.snapshots.insert("/a.txt".to_string(), FileSnapshot { encoding: None, content: String::new(), hash: String::new() });
+
+        assert!(state.release("/a.txt", "main").is_none());
+        assert!(state.release("/a.txt", "other").is_none());
+        assert!(state.release("/a.txt", "unknown").is_none());
+        assert!(state.release("/a.txt", "main").is_some());
+        assert!(state.watchers.is_empty());
+        assert!(state.snapshots.is_empty());
+        assert!(state.release("/a.txt", "main").is_none());
+    }
+
+    #[test]
+    fn folder_patterns_match_relative_paths() {
+        let matcher = compile_pattern(Some("*.log")).unwrap().unwrap();
+        assert!(matcher.is_match(Path::new("app.log")));
+        assert!(matcher.is_match(Path::new("nested/app.log")));
+        assert!(!matcher.is_match(Path::new("app.txt")));
+
+        assert!(compile_pattern(None).unwrap().is_none());
+        assert!(compile_pattern(Some("")).unwrap().is_none());
+        assert!(compile_pattern(Some("[")).is_err());
+    }
+
+    #[test]
+    fn existing_files_lists_nested_and_hidden_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("nested")).unwrap();
+        std::fs::write(dir.path().join("a.log"), "").unwrap();
+        std::fs::write(dir.path().join(".hidden"), "").unwrap();
+        std::fs::write(dir.path().join("nested").join("b.log"), "").unwrap();
+
+        let files = existing_files(dir.path());
+        assert_eq!(files.len(), 3);
+        assert!(files.contains(&dir.path().join("nested").join("b.log")));
+        assert!(!files.contains(&dir.path().join("nested")));
+    }
+}
@@ -0,0 +1,89 @@
+import { get } from 'svelte/store';
+import { invoke } from "@tauri-apps/api/core";
+import { fileStore } from './files';
+import { configStore } from './configStore';
+import type { FileInfo } from '../types/file';
+
+export type AutosaveMode = 'off' | 'delay' | 'focus_loss' | 'window_close';
+
+const PUSH_DELAY = 300;
+
+const pending = new Map<string, ReturnType<typeof setTimeout>>();
+const pushedContent = new Map<string, string>();
+
+function canAutosave(file: FileInfo): boolean {
+  return file.isModified && !!file.path && !file.readOnly;
+}
+
+async function pushBuffer(file: FileInfo) {
+  pending.delete(file.id);
+  try {
+    await invoke('update_autosave_buffer', {
+      buffer: {
+        buffer_id: file.id,
+        path: file.path,
+        content: file.content,
+        encoding: file.encoding || null,
+        saved_hash: file.hash || null,
+        read_only: file.readOnly ?? false
+      }
+    });
+    pushedContent.set(file.id, file.content);
+  } catch (error) {
+    console.error('Error updating autosave buffer:', error);
+  }
+}
+
+function dropBuffer(id: string) {
+  const timer = pending.get(id);
+  if (timer) {
+    clearTimeout(timer);
+    pending.delete(id);
+  }
+  if (pushedContent.delete(id)) {
+    invoke('clear_autosave_buffer', { bufferId: id }).catch(error => {
+      console.error('Error clearing autosave buffer:', error);
+    });
+  }
+}
+
+// Reports dirty buffers to the backend, which decides when to save them
+// according to the autosave mode.
+export function startAutosave(): () => void {
+  return fileStore.subscribe(store => {
+    const mode = (get(configStore).autosave || 'off') as AutosaveMode;
+    const dirtyIds = new Set<string>();
+
+    for (const file of store.files) {
+      if (mode === 'off' || !canAutosave(file)) continue;
+      dirtyIds.add(file.id);
+
+      if (pushedContent.get(file.id) === file.content || pending.has(file.id)) continue;
+      pending.set(file.id, setTimeout(() => {
+        const current = get(fileStore).files.find(f => f.id === file.id);
+        if (current && canAutosave(current)) {
+          pushBuffer(current);
+        } else {
+          pending.delete(file.id);
+        }
+      }, PUSH_DELAY));
+    }
+
+    for (const id of new Set([...pushedContent.keys(), ...pending.keys()])) {
+      if (!dirtyIds.has(id)) {
+        dropBuffer(id);
+      }
+    }
+  });
+}
+
+// Sends pending content right away so a save on close uses the latest edits.
+export async function flushAutosave() {
+  const files = get(fileStore).files;
+  const pushes = [...pending.entries()].map(([id, timer]) => {
+    clearTimeout(timer);
+    const file = files.find(f => f.id === id);
+    return file ? pushBuffer(file) : Promise.resolve();
+  });
+  await Promise.all(pushes);
+}
@@ -2,6 +2,15 @@ import { writable } from 'svelte/store';
 import { invoke } from "@tauri-apps/api/core";
 import type { AppConfig } from '../types/config';
 
+// Recent files are only changed through the recent-file commands, and the
+// copy in the store may be stale, so it is left out of saves.
+function saveConfig(config: AppConfig) {
+  const { recent_files, ...saved } = config;
+  invoke('save_config', { config: saved }).catch(error => {
+    console.error('Error saving config:', error);
+  });
+}
+
 function createConfigStore() {
   const { subscribe, set, update } = writable<AppConfig>({
     colorscheme: 'cerberus',
@@ -30,20 +39,19 @@ function createConfigStore() {
       try {
         update(store => {
           const newConfig = { ...store, ...updates };
-          invoke('save_config', { config: newConfig }).catch(error => {
-            console.error('Error saving config:', error);
-          });
+          saveConfig(newConfig);
           return newConfig;
         });
       } catch (error) {
         console.error('Error saving config:', error);
       }
     },
+    replace: (config: AppConfig) => {
+      set(config);
+    },
     updateConfig: (config: AppConfig) => {
       set(config);
-      invoke('save_config', { config }).catch(error => {
-        console.error('Error saving config:', error);
-      });
+      saveConfig(config);
     }
   };
 }
@@ -63,4 +63,27 @@ function createEditorStore() {
   };
 }
 
-export const editorStore = createEditorStore();
\ No newline at end of file
+export const editorStore = createEditorStore();
+// A match to select once its file is the active one, e.g. a search result.
+export interface RevealTarget {
+  path: string;
+  line: number;
+  column: number;
+  length: number;
+}
+
+export const pendingReveal = writable<RevealTarget | null>(null);
+
+let activeViewStateCapture: (() => Promise<void>) | null = null;
+
+export function registerViewStateCapture(capture: (() => Promise<void>) | null) {
+  activeViewStateCapture = capture;
+}
+
+// Saves the active tab's view state, which is otherwise only recorded when
+// switching away from it.
+export async function captureActiveViewState() {
+  if (activeViewStateCapture) {
+    await activeViewStateCapture();
+  }
+}
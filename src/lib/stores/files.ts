@@ -1,5 +1,5 @@
 import { writable, get } from 'svelte/store';
-import type { FileInfo } from '../types/file';
+import type { FileInfo, LineEdit } from '../types/file';
 import { configStore } from './configStore';
 import { message, ask } from '@tauri-apps/plugin-dialog';
 import { invoke } from "@tauri-apps/api/core";
@@ -12,6 +12,18 @@ interface FileStore {
   untitledCounter: number;
 }
 
+function splitLines(text: string): string[] {
+  return text.split(/(?<=\n)/).filter(line => line !== '');
+}
+
+export function applyLineEdits(content: string, edits: LineEdit[]): string {
+  const lines = splitLines(content);
+  for (const edit of [...edits].reverse()) {
+    lines.splice(edit.start, edit.delete, ...splitLines(edit.insert));
+  }
+  return lines.join('');
+}
+
 function createFileStore() {
   const { subscribe, update } = writable<FileStore>({
     files: [],
@@ -80,13 +92,8 @@ function createFileStore() {
     removeFile: (id: string) => update(store => {
       const fileToRemove = store.files.find(f => f.id === id);
       if (fileToRemove?.path) {
-        const config = get(configStore);
-        const recentFiles = config.recent_files || [];
-        const updatedRecent = [fileToRemove.path, 
-          ...recentFiles.filter(f => f !== fileToRemove.path)
-        ].slice(0, 100);
-        configStore.save({
-          recent_files: updatedRecent
+        invoke('add_to_recent_files', { path: fileToRemove.path }).catch(error => {
+          console.error('Error updating recent files:', error);
         });
       }
       
@@ -171,9 +178,7 @@ function createFileStore() {
         fileStore.addFile(fileInfo);
         fileStore.setActiveFile(store.nextId.toString());
         
-        await configStore.save({
-          recent_files: recentFiles.slice(1)
-        });
+        await invoke('remove_from_recent_files', { path: filePath });
         
         await invoke('watch_file', { path: filePath });
       } catch (error) {
@@ -196,11 +201,49 @@ function createFileStore() {
         } else {
           await message(`Failed to restore file: ${filePath}`, { title: 'Error' });
         }
-        await configStore.save({
-          recent_files: recentFiles.slice(1)
-        });
+        await invoke('remove_from_recent_files', { path: filePath });
       }
     },
+    openFile: async (filePath: string, setActive: boolean = true, encoding?: string) => {
+      const config = get(configStore);
+      const fileData = await invoke('read_file', { 
+        path: filePath,
+        encoding: encoding || config.default_encoding || 'utf-8'
+      }) as { content: string, hash: string };
+      
+      let fileSystemModified: Date | undefined;
+      try {
+        const modifiedTimestamp = await invoke('get_file_metadata', { path: filePath }) as number;
+        fileSystemModified = new Date(modifiedTimestamp * 1000);
+      } catch (error) {
+        console.error('Error getting file metadata:', error);
+      }
+      
+      const pathParts = filePath.split(/[/\\]/);
+      const fileName = pathParts[pathParts.length - 1];
+      const extension = fileName.split('.').pop()?.toLowerCase() || '';
+      
+      fileStore.addFile({
+        id: '',
+        path: filePath,
+        name: fileName,
+        content: fileData.content,
+        encoding: encoding || 'utf-8',
+        language: getLanguageFromExtension(extension),
+        created: new Date(),
+        modified: new Date(),
+        fileSystemModified,
+        isModified: false,
+        hash: fileData.hash,
+        cursor: { line: 1, column: 1 },
+        stats: {
+          lines: fileData.content.split('\n').length,
+          length: fileData.content.length
+        }
+      }, false, setActive);
+      
+      await invoke('watch_file', { path: filePath, encoding: encoding || config.default_encoding || 'utf-8' });
+    },
     setActiveFile: (id: string) => update(store => ({
       ...store,
       activeFileId: id
@@ -0,0 +1,90 @@
+import { get } from 'svelte/store';
+import { invoke } from "@tauri-apps/api/core";
+import { fileStore } from './files';
+import type { FileInfo, JournalEntry } from '../types/file';
+
+const JOURNAL_DELAY = 1000;
+
+const pending = new Map<string, ReturnType<typeof setTimeout>>();
+const journaledContent = new Map<string, string>();
+
+function needsJournal(file: FileInfo): boolean {
+  return file.isModified || (!file.path && file.content !== '');
+}
+
+async function writeEntry(file: FileInfo) {
+  pending.delete(file.id);
+  const entry: JournalEntry = {
+    buffer_id: file.id,
+    path: file.path || null,
+    name: file.name,
+    content: file.content,
+    encoding: file.encoding || null,
+    language: file.language || null,
+    saved_hash: file.hash || null,
+    updated_at: 0
+  };
+
+  try {
+    await invoke('journal_buffer', { entry });
+    journaledContent.set(file.id, file.content);
+  } catch (error) {
+    console.error('Error journaling buffer:', error);
+  }
+}
+
+async function clearEntry(id: string) {
+  const timer = pending.get(id);
+  if (timer) {
+    clearTimeout(timer);
+    pending.delete(id);
+  }
+  journaledContent.delete(id);
+
+  try {
+    await invoke('clear_journal_buffer', { bufferId: id });
+  } catch (error) {
+    console.error('Error clearing journaled buffer:', error);
+  }
+}
+
+// Keeps the backend journal in line with the open tabs: dirty and untitled
+// buffers are written after a short delay, and entries go away once a buffer
+// is saved, emptied or closed.
+export function startJournal(): () => void {
+  return fileStore.subscribe(store => {
+    const dirtyIds = new Set<string>();
+
+    for (const file of store.files) {
+      if (!needsJournal(file)) continue;
+      dirtyIds.add(file.id);
+
+      if (journaledContent.get(file.id) === file.content || pending.has(file.id)) continue;
+      pending.set(file.id, setTimeout(() => {
+        const current = get(fileStore).files.find(f => f.id === file.id);
+        if (current && needsJournal(current)) {
+          writeEntry(current);
+        } else {
+          pending.delete(file.id);
+        }
+      }, JOURNAL_DELAY));
+    }
+
+    for (const id of new Set([...journaledContent.keys(), ...pending.keys()])) {
+      if (!dirtyIds.has(id)) {
+        clearEntry(id);
+      }
+    }
+  });
+}
+
+// Writes pending entries right away, e.g. before the window closes.
+export async function flushJournal() {
+  const files = get(fileStore).files;
+  const writes = [...pending.entries()].map(([id, timer]) => {
+    clearTimeout(timer);
+    const file = files.find(f => f.id === id);
+    return file ? writeEntry(file) : Promise.resolve();
+  });
+  await Promise.all(writes);
+}
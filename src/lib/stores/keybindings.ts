@@ -0,0 +1,83 @@
+import { writable, get } from 'svelte/store';
+import { invoke } from "@tauri-apps/api/core";
+import type { KeybindingReport } from '../types/config';
+
+// Same defaults as COMMANDS in config/keybindings.rs. `Mod` is Cmd on macOS
+// and Ctrl elsewhere.
+const DEFAULT_BINDINGS: Record<string, string> = {
+  'file.new': 'Mod+N',
+  'file.open': 'Mod+O',
+  'file.save': 'Mod+S',
+  'file.save_as': 'Mod+Shift+S',
+  'file.close': 'Mod+W',
+  'file.restore': 'Mod+Shift+T',
+  'file.rename': 'F2',
+  'file.reveal': 'Mod+E',
+  'recent.open': 'Mod+R',
+  'view.toggle_word_wrap': 'Alt+Z',
+  'view.toggle_side_panel': 'Mod+B',
+  'search.find_in_files': 'Mod+Shift+F',
+  'tabs.next': 'Ctrl+Tab',
+  'tabs.previous': 'Ctrl+Shift+Tab'
+};
+
+const MODIFIER_ORDER = ['Ctrl', 'Alt', 'Shift', 'Meta'];
+
+// Resolves `Mod` and orders the modifiers the way the backend does.
+function platformChord(chord: string, macos: boolean): string {
+  const parts = chord.split('+');
+  const key = parts.pop()!;
+  const modifiers = parts.map(part => part === 'Mod' ? (macos ? 'Meta' : 'Ctrl') : part);
+  return [...MODIFIER_ORDER.filter(m => modifiers.includes(m)), key].join('+');
+}
+
+function defaultBindings(): Record<string, string> {
+  const macos = /Mac/i.test(navigator.userAgent);
+  return Object.fromEntries(
+    Object.entries(DEFAULT_BINDINGS).map(([command, chord]) => [command, platformChord(chord, macos)])
+  );
+}
+
+// Command id -> chord, as resolved by the backend from the platform defaults,
+// the global config and keybindings.json. Starts with the defaults, so the
+// shortcuts work before the backend answers or if it fails.
+export const keybindingStore = writable<Record<string, string>>(defaultBindings());
+
+export async function loadKeybindings(): Promise<KeybindingReport | null> {
+  try {
+    const report = await invoke<KeybindingReport>('get_keybindings');
+    keybindingStore.set({ ...defaultBindings(), ...report.bindings });
+    return report;
+  } catch (error) {
+    console.error('Error loading keybindings:', error);
+    return null;
+  }
+}
+
+const MODIFIER_CODES = new Set([
+  'ControlLeft', 'ControlRight', 'AltLeft', 'AltRight',
+  'ShiftLeft', 'ShiftRight', 'MetaLeft', 'MetaRight'
+]);
+
+// Builds the chord the backend would write for this event, e.g. "Ctrl+Shift+S".
+export function eventChord(event: KeyboardEvent): string | null {
+  if (MODIFIER_CODES.has(event.code)) return null;
+
+  const key = event.code.replace(/^Key/, '').replace(/^Digit/, '');
+  const parts: string[] = [];
+  if (event.ctrlKey) parts.push('Ctrl');
+  if (event.altKey) parts.push('Alt');
+  if (event.shiftKey) parts.push('Shift');
+  if (event.metaKey) parts.push('Meta');
+  parts.push(key);
+  return parts.join('+');
+}
+
+// The command bound to the pressed chord, if any of `commands` is.
+export function commandForEvent(event: KeyboardEvent, commands: string[]): string | null {
+  const chord = eventChord(event);
+  if (!chord) return null;
+
+  const bindings = get(keybindingStore);
+  return commands.find(command => bindings[command] === chord) ?? null;
+}
@@ -1,6 +1,7 @@
 import { writable, get } from 'svelte/store';
 import { invoke } from '@tauri-apps/api/core';
 import { configStore } from './configStore';
+import { notificationStore } from './notification';
 
 export interface MonacoThemeData {
   base?: string;
@@ -16,6 +17,42 @@ export interface MonacoThemeData {
   };
 }
 
+export interface MonacoThemePreview {
+  background: string;
+  foreground: string;
+  keyword: string | null;
+  string: string | null;
+  comment: string | null;
+}
+
+export interface MonacoThemeInfo {
+  name: string;
+  display_name: string;
+  base: string;
+  dark: boolean;
+  builtin: boolean;
+  preview: MonacoThemePreview;
+  error: string | null;
+}
+
+export interface ThemesChanged {
+  themes: MonacoThemeInfo[];
+  changed: string[];
+}
+
+const BUILTIN_THEMES: MonacoThemeInfo[] = [
+  { name: 'vs', display_name: 'Visual Studio Light', base: 'vs', dark: false, builtin: true, preview: { background: '#fffffe', foreground: '#000000', keyword: null, string: null, comment: null }, error: null },
+  { name: 'vs-dark', display_name: 'Visual Studio Dark', base: 'vs-dark', dark: true, builtin: true, preview: { background: '#1e1e1e', foreground: '#d4d4d4', keyword: null, string: null, comment: null }, error: null },
+  { name: 'hc-black', display_name: 'High Contrast Dark', base: 'hc-black', dark: true, builtin: true, preview: { background: '#000000', foreground: '#ffffff', keyword: null, string: null, comment: null }, error: null }
+];
+
+function isBuiltinTheme(themeName: string): boolean {
+  return BUILTIN_THEMES.some(theme => theme.name === themeName);
+}
+
+// The themes available to pick from, kept up to date by `themes-changed`.
+export const monacoThemesStore = writable<MonacoThemeInfo[]>(BUILTIN_THEMES);
+
 function createMonacoThemeStore() {
   const { subscribe, set, update } = writable<string>('vs-dark');
   let monaco: any = null;
@@ -67,46 +104,59 @@ function createMonacoThemeStore() {
         monaco_editor_theme: themeName
       });
 
-      // Apply theme to Monaco Editor
-      if (monaco) {
-        if (themeName === 'vs' || themeName === 'vs-dark' || themeName === 'hc-black') {
-          // Built-in themes
-          monaco.editor.setTheme(themeName);
-        } else {
-          // Custom theme from file
-          try {
-            const themeContent = await invoke<string>('read_monaco_theme', { themeName });
-            const themeData: MonacoThemeData = JSON.parse(themeContent);
-            
-            // Define the custom theme
-            monaco.editor.defineTheme(themeName, {
-              base: themeData.base || 'vs-dark',
-              inherit: themeData.inherit !== false,
-              rules: themeData.rules || [],
-              colors: themeData.colors || {}
-            });
-            
-            // Apply the theme
-            monaco.editor.setTheme(themeName);
-          } catch (error) {
-            console.error('Error loading Monaco theme:', error);
-            // Fallback to vs-dark if theme loading fails
-            monaco.editor.setTheme('vs-dark');
-            set('vs-dark');
-          }
-        }
-      }
+      await applyTheme(themeName);
+    },
+    // Re-reads the current theme after its file changed on disk.
+    reload: async () => {
+      await applyTheme(get(monacoThemeStore));
+    },
+    importTheme: async (path: string): Promise<string> => {
+      return await invoke<string>('import_monaco_theme', { path, name: null });
     },
-    getAvailableThemes: async (): Promise<string[]> => {
+    getAvailableThemes: async (): Promise<MonacoThemeInfo[]> => {
       try {
-        const themes = await invoke<string[]>('get_monaco_themes');
+        const themes = await invoke<MonacoThemeInfo[]>('get_monaco_themes');
+        monacoThemesStore.set(themes);
         return themes;
       } catch (error) {
         console.error('Error getting Monaco themes:', error);
-        return ['vs', 'vs-dark', 'hc-black'];
+        return BUILTIN_THEMES;
       }
     }
   };
+
+  async function applyTheme(themeName: string) {
+    // Apply theme to Monaco Editor
+    if (monaco) {
+      if (isBuiltinTheme(themeName)) {
+        // Built-in themes
+        monaco.editor.setTheme(themeName);
+      } else {
+        // Custom theme from file
+        try {
+          const themeContent = await invoke<string>('read_monaco_theme', { themeName });
+          const themeData: MonacoThemeData = JSON.parse(themeContent);
+          
+          // Define the custom theme
+          monaco.editor.defineTheme(themeName, {
+            base: themeData.base || 'vs-dark',
+            inherit: themeData.inherit !== false,
+            rules: themeData.rules || [],
+            colors: themeData.colors || {}
+          });
+          
+          // Apply the theme
+          monaco.editor.setTheme(themeName);
+        } catch (error) {
+          console.error('Error loading Monaco theme:', error);
+          notificationStore.show(`Could not load theme "${themeName}": ${error}`, 'error');
+          // Fallback to vs-dark if theme loading fails
+          monaco.editor.setTheme('vs-dark');
+          set('vs-dark');
+        }
+      }
+    }
+  }
 }
 
 export const monacoThemeStore = createMonacoThemeStore();
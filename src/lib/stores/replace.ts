@@ -0,0 +1,140 @@
+import { writable, get } from 'svelte/store';
+import { invoke, Channel } from '@tauri-apps/api/core';
+import type { SearchMatch, SearchOptions, SearchSummary } from './search';
+
+export interface ReplaceOptions extends SearchOptions {
+  replacement: string;
+}
+
+export interface ReplaceMatch extends SearchMatch {
+  replacement: string;
+}
+
+export interface FilePreview {
+  path: string;
+  encoding: string;
+  hash: string;
+  matches: ReplaceMatch[];
+  diff: string;
+}
+
+// Matches to replace in a file by their index, or all of them when null.
+export interface ReplaceSelection {
+  path: string;
+  hash: string;
+  matches: number[] | null;
+}
+
+export interface ReplaceJournal {
+  id: string;
+  created_at: number;
+  folder: string;
+  query: string;
+  replacement: string;
+  matches: number;
+  files: { path: string; backup: string; hash: string }[];
+}
+
+type ReplaceEvent =
+  | ({ kind: 'file' } & FilePreview)
+  | ({ kind: 'done' } & SearchSummary);
+
+interface ReplaceState {
+  folder: string;
+  options: ReplaceOptions | null;
+  files: FilePreview[];
+  summary: SearchSummary | null;
+  running: boolean;
+  error: string | null;
+}
+
+export const replaceStore = writable<ReplaceState>({
+  folder: '',
+  options: null,
+  files: [],
+  summary: null,
+  running: false,
+  error: null
+});
+
+// Replaces that can still be undone, newest first.
+export const replaceJournalsStore = writable<ReplaceJournal[]>([]);
+
+let currentPreviewId: number | null = null;
+let currentChannel: Channel<ReplaceEvent> | null = null;
+
+export async function loadReplaceJournals() {
+  try {
+    replaceJournalsStore.set(await invoke<ReplaceJournal[]>('get_replace_journals'));
+  } catch (error) {
+    console.error('Error loading replace journals:', error);
+  }
+}
+
+export async function cancelPreview() {
+  if (currentPreviewId === null) return;
+  try {
+    await invoke('cancel_search', { searchId: currentPreviewId });
+  } catch (error) {
+    console.error('Error cancelling replace preview:', error);
+  }
+}
+
+export async function startPreview(folder: string, options: ReplaceOptions) {
+  await cancelPreview();
+
+  const onEvent = new Channel<ReplaceEvent>();
+  currentChannel = onEvent;
+  currentPreviewId = null;
+  replaceStore.set({ folder, options, files: [], summary: null, running: true, error: null });
+
+  onEvent.onmessage = (event) => {
+    if (currentChannel !== onEvent) return;
+
+    if (event.kind === 'file') {
+      const { kind, ...file } = event;
+      replaceStore.update(state => ({ ...state, files: [...state.files, file] }));
+    } else {
+      const { kind, ...summary } = event;
+      currentPreviewId = null;
+      replaceStore.update(state => ({ ...state, summary, running: false }));
+    }
+  };
+
+  try {
+    const previewId = await invoke<number>('preview_replace_in_folder', { folder, options, onEvent });
+    if (currentChannel === onEvent && get(replaceStore).running) {
+      currentPreviewId = previewId;
+    }
+  } catch (error) {
+    if (currentChannel === onEvent) {
+      replaceStore.update(state => ({ ...state, running: false, error: String(error) }));
+    }
+  }
+}
+
+export function clearPreview() {
+  cancelPreview();
+  currentChannel = null;
+  currentPreviewId = null;
+  replaceStore.update(state => ({ ...state, options: null, files: [], summary: null, running: false, error: null }));
+}
+
+// Applies the previewed replace to the selected matches. The preview is
+// cleared, since the files it describes have changed.
+export async function applyReplace(selections: ReplaceSelection[]): Promise<ReplaceJournal> {
+  const { folder, options } = get(replaceStore);
+  if (!options) {
+    throw new Error('Nothing to replace');
+  }
+  const journal = await invoke<ReplaceJournal>('apply_replace_in_folder', { folder, options, files: selections });
+  clearPreview();
+  await loadReplaceJournals();
+  return journal;
+}
+
+export async function undoReplace(journalId: string): Promise<string[]> {
+  const restored = await invoke<string[]>('undo_replace', { journalId });
+  await loadReplaceJournals();
+  return restored;
+}
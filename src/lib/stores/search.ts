@@ -0,0 +1,113 @@
+import { writable, get } from 'svelte/store';
+import { invoke, Channel } from '@tauri-apps/api/core';
+
+export interface SearchOptions {
+  query: string;
+  regex: boolean;
+  case_sensitive: boolean;
+  whole_word: boolean;
+  include_hidden: boolean;
+  include_ignored: boolean;
+  include: string[];
+  exclude: string[];
+}
+
+// Lines and columns start at 1, as in the editor.
+export interface SearchMatch {
+  line: number;
+  column: number;
+  length: number;
+  preview: string;
+  preview_start: number;
+  preview_end: number;
+}
+
+export interface FileMatches {
+  path: string;
+  encoding: string;
+  matches: SearchMatch[];
+}
+
+export interface SearchSummary {
+  files_searched: number;
+  files_skipped: number;
+  matches: number;
+  truncated: boolean;
+  cancelled: boolean;
+  elapsed_ms: number;
+}
+
+type SearchEvent =
+  | ({ kind: 'file' } & FileMatches)
+  | ({ kind: 'done' } & SearchSummary);
+
+interface SearchState {
+  folder: string;
+  results: FileMatches[];
+  summary: SearchSummary | null;
+  running: boolean;
+  error: string | null;
+}
+
+export const searchStore = writable<SearchState>({
+  folder: '',
+  results: [],
+  summary: null,
+  running: false,
+  error: null
+});
+
+let currentSearchId: number | null = null;
+let currentChannel: Channel<SearchEvent> | null = null;
+
+export async function cancelSearch() {
+  if (currentSearchId === null) return;
+  try {
+    await invoke('cancel_search', { searchId: currentSearchId });
+  } catch (error) {
+    console.error('Error cancelling search:', error);
+  }
+}
+
+// Replaces the results with those of a new search in `folder`, cancelling
+// the previous search if it is still running.
+export async function startSearch(folder: string, options: SearchOptions) {
+  await cancelSearch();
+
+  const onEvent = new Channel<SearchEvent>();
+  currentChannel = onEvent;
+  currentSearchId = null;
+  searchStore.set({ folder, results: [], summary: null, running: true, error: null });
+
+  onEvent.onmessage = (event) => {
+    // Events of an earlier search can still arrive after a new one started.
+    if (currentChannel !== onEvent) return;
+
+    if (event.kind === 'file') {
+      const { kind, ...file } = event;
+      searchStore.update(state => ({ ...state, results: [...state.results, file] }));
+    } else {
+      const { kind, ...summary } = event;
+      currentSearchId = null;
+      searchStore.update(state => ({ ...state, summary, running: false }));
+    }
+  };
+
+  try {
+    const searchId = await invoke<number>('search_in_folder', { folder, options, onEvent });
+    if (currentChannel === onEvent && get(searchStore).running) {
+      currentSearchId = searchId;
+    }
+  } catch (error) {
+    if (currentChannel === onEvent) {
+      searchStore.update(state => ({ ...state, running: false, error: String(error) }));
+    }
+  }
+}
+
+export function clearSearch() {
+  cancelSearch();
+  currentChannel = null;
+  currentSearchId = null;
+  searchStore.update(state => ({ ...state, results: [], summary: null, running: false, error: null }));
+}
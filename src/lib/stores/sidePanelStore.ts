@@ -11,4 +11,7 @@ function createSidePanelStore() {
   };
 }
 
-export const sidePanelStore = createSidePanelStore();
\ No newline at end of file
+export const sidePanelStore = createSidePanelStore();
+
+// What the side panel shows: the open files or the find in files results.
+export const sidePanelMode = writable<'files' | 'search'>('files');
\ No newline at end of file
@@ -1,28 +1,120 @@
-import { writable } from 'svelte/store';
+import { writable, get } from 'svelte/store';
+import { invoke } from '@tauri-apps/api/core';
 import { configStore } from './configStore';
+import { monacoThemeStore } from './monacoTheme';
+import { notificationStore } from './notification';
 
-export type Theme = 
+export type Theme =
   | 'Firow'
-  | 'catppuccin' | 'cerberus' | 'concord' | 'crimson' | 'fennec' 
-  | 'hamlindigo' | 'legacy' | 'mint' | 'modern' | 'mona' | 'nosh' 
-  | 'nouveau' | 'pine' | 'reign' | 'rocket' | 'rose' | 'sahara' 
+  | 'catppuccin' | 'cerberus' | 'concord' | 'crimson' | 'fennec'
+  | 'hamlindigo' | 'legacy' | 'mint' | 'modern' | 'mona' | 'nosh'
+  | 'nouveau' | 'pine' | 'reign' | 'rocket' | 'rose' | 'sahara'
   | 'seafoam' | 'terminus' | 'vintage' | 'vox' | 'wintry';
 
+// Follows the active Monaco theme.
+export const MONACO_SCHEME = 'monaco';
+
+export interface ColorScheme {
+  name: string;
+  display_name: string;
+  base: Theme;
+  variables: Record<string, string>;
+}
+
+export interface ColorSchemeInfo {
+  name: string;
+  display_name: string;
+  base: string;
+  builtin: boolean;
+  preview: {
+    background: string | null;
+    foreground: string | null;
+    accent: string | null;
+  };
+  error: string | null;
+}
+
+export interface ColorSchemesChanged {
+  schemes: ColorSchemeInfo[];
+  changed: string[];
+}
+
+// The schemes available to pick from, kept up to date by `colorschemes-changed`.
+export const colorSchemesStore = writable<ColorSchemeInfo[]>([]);
+
+export async function loadColorSchemes() {
+  try {
+    colorSchemesStore.set(await invoke<ColorSchemeInfo[]>('get_colorschemes'));
+  } catch (error) {
+    console.error('Error getting color schemes:', error);
+  }
+}
+
+// Variables set by the current user scheme, removed again when it changes.
+let appliedVariables: string[] = [];
+
+function applyScheme(base: string, variables: Record<string, string> = {}) {
+  const root = document.documentElement;
+  for (const name of appliedVariables) {
+    root.style.removeProperty(name);
+  }
+  root.setAttribute('data-theme', base);
+  for (const [name, value] of Object.entries(variables)) {
+    root.style.setProperty(name, value);
+  }
+  appliedVariables = Object.keys(variables);
+}
+
+async function resolveScheme(theme: string): Promise<ColorScheme | null> {
+  if (theme === MONACO_SCHEME) {
+    return await invoke<ColorScheme>('derive_colorscheme', { themeName: get(monacoThemeStore) });
+  }
+  return await invoke<ColorScheme | null>('read_colorscheme', { name: theme });
+}
+
 function createThemeStore() {
-  const { subscribe, set } = writable<Theme>('Firow');
+  const { subscribe, set } = writable<string>('Firow');
 
-  return {
+  async function apply(theme: string) {
+    try {
+      const scheme = await resolveScheme(theme);
+      if (scheme) {
+        applyScheme(scheme.base, scheme.variables);
+      } else {
+        applyScheme(theme);
+      }
+    } catch (error) {
+      console.error('Error loading color scheme:', error);
+      notificationStore.show(`Could not load color scheme "${theme}": ${error}`, 'error');
+      applyScheme('Firow');
+    }
+  }
+
+  const store = {
     subscribe,
-    setTheme: (theme: Theme) => {
-      document.documentElement.setAttribute('data-theme', theme);
+    setTheme: async (theme: string) => {
       configStore.save({ colorscheme: theme });
       set(theme);
+      await apply(theme);
     },
-    loadTheme: (theme: Theme) => {
-      document.documentElement.setAttribute('data-theme', theme);
+    loadTheme: async (theme: string) => {
       set(theme);
+      await apply(theme);
+    },
+    // Re-applies the current scheme after its file or, for the scheme that
+    // follows the editor, the Monaco theme changed.
+    refresh: async () => {
+      await apply(get(store));
     }
   };
+
+  return store;
 }
 
 export const themeStore = createThemeStore();
+
+monacoThemeStore.subscribe(() => {
+  if (get(themeStore) === MONACO_SCHEME) {
+    themeStore.refresh();
+  }
+});
@@ -1,7 +1,7 @@
-import type { Theme } from '../stores/theme';
+import type { FileViewState } from './file';
 
 export interface AppConfig {
-  colorscheme?: Theme;
+  colorscheme?: string;
   monaco_editor_theme?: string;
   recent_files?: string[];
   opened_files?: string[];
@@ -11,4 +11,129 @@ export interface AppConfig {
   default_encoding?: string;
   transparent_mode?: boolean;
   window_opacity?: number;
+  languages?: Record<string, LanguageSettings>;
+  instance_retention_days?: number;
+  autosave?: 'off' | 'delay' | 'focus_loss' | 'window_close';
+  autosave_delay_ms?: number;
+  keybindings?: Record<string, string>;
+}
+
+export interface LanguageSettings {
+  tab_size?: number | null;
+  insert_spaces?: boolean | null;
+  word_wrap?: boolean | null;
+  rulers?: number[] | null;
+  font_size?: number | null;
+  default_eol?: 'LF' | 'CRLF' | null;
+  trim_on_save?: boolean | null;
+  format_on_save?: boolean | null;
+}
+
+export interface EditorSettings {
+  tab_size: number;
+  insert_spaces: boolean;
+  word_wrap: boolean;
+  rulers: number[];
+  font_size: number;
+  default_eol: 'LF' | 'CRLF' | null;
+  trim_on_save: boolean;
+  format_on_save: boolean;
+}
+
+export interface WatchedFolder {
+  path: string;
+  pattern?: string | null;
+  follow?: boolean | null;
+  window?: string | null;
+}
+
+export interface ConfigRecovery {
+  file: string;
+  error: string;
+  quarantined_to: string;
+  restored_from: string | null;
+}
+
+export interface FieldError {
+  field: string;
+  message: string;
+}
+
+export interface ConfigValidation {
+  file: string;
+  errors: FieldError[];
+}
+
+export interface KeybindingConflict {
+  chord: string;
+  commands: string[];
+}
+
+export interface KeybindingReport {
+  bindings: Record<string, string>;
+  errors: FieldError[];
+  conflicts: KeybindingConflict[];
+}
+
+export interface RecentFile {
+  path: string;
+  open_count: number;
+  last_used: number;
+  pinned: boolean;
+  score: number;
+}
+
+export interface InstanceInfo {
+  id: string;
+  status: 'running' | 'closed' | 'crashed' | 'unknown';
+  pid: number | null;
+  started_at: number | null;
+  closed_at: number | null;
+  modified: number;
+  opened_files: string[];
+}
+
+export interface SessionFile {
+  path: string;
+  view_state: FileViewState | null;
+}
+
+export interface Session {
+  name: string;
+  files: SessionFile[];
+  active_file: string | null;
+  saved_at: number;
+}
+
+export interface SessionSummary {
+  name: string;
+  file_count: number;
+  active_file: string | null;
+  saved_at: number;
+}
+
+export interface ProfileInfo {
+  name: string;
+  active: boolean;
+}
+
+export type BundleImportMode = 'merge' | 'replace';
+
+export interface BundleContents {
+  global: boolean;
+  profiles: string[];
+  themes: string[];
+  snippets: string[];
+  keybindings: boolean;
+}
+
+export interface BundleChange {
+  path: string;
+  change: 'added' | 'modified' | 'removed';
+}
+
+export interface BundlePreview {
+  contents: BundleContents;
+  created_at: number;
+  changes: BundleChange[];
 }
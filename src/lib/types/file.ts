@@ -9,6 +9,8 @@ export interface FileInfo {
   modified: Date;
   fileSystemModified?: Date; // File system's last modified time
   isModified: boolean;
+  isFollowing?: boolean;
+  readOnly?: boolean;
   hash: string;
   cursor: {
     line: number;
@@ -18,4 +20,40 @@ export interface FileInfo {
     lines: number;
     length: number;
   };
-}
\ No newline at end of file
+}
+
+export interface LineEdit {
+  start: number;
+  delete: number;
+  insert: string;
+}
+
+export interface FileChangedEvent {
+  path: string;
+  hash: string;
+  modified: number;
+  edits: LineEdit[];
+  content: string | null;
+}
+
+export interface FileViewState {
+  cursor?: { line: number, column: number } | null;
+  selections?: { start_line: number, start_column: number, end_line: number, end_column: number }[] | null;
+  scroll_top?: number | null;
+  folded_ranges?: { start_line: number, end_line: number }[] | null;
+  encoding?: string | null;
+  eol?: string | null;
+  language?: string | null;
+  read_only?: boolean | null;
+}
+
+export interface JournalEntry {
+  buffer_id: string;
+  path: string | null;
+  name: string;
+  content: string;
+  encoding: string | null;
+  language: string | null;
+  saved_hash: string | null;
+  updated_at: number;
+}